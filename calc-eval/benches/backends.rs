@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use calc_eval::{Backend, Calculator, Value};
+
+const EXPRESSIONS: &[(&str, &str)] = &[
+    ("arithmetic", "3 * x ^ 2 - 2 * x * y + y / 4 - 7"),
+    ("conditional", "x > y && x > 0 ? x - y : y - x"),
+    ("units", "x * 1 km / (y * 1 h) in m/s"),
+    ("calls", "max(sqrt(x * x + y * y), abs(x - y)) + sq(x)"),
+];
+
+fn evaluate_backends(c: &mut Criterion) {
+    let mut calc = Calculator::new();
+    calc.eval("sq(t) = t * t").unwrap();
+
+    let bindings = (0..1000)
+        .map(|idx| HashMap::from([
+            ("x".to_owned(), Value::Float(idx as f64 * 0.5)),
+            ("y".to_owned(), Value::Integer(idx % 17 + 1))
+        ]))
+        .collect::<Vec<_>>();
+
+    for &(name, input) in EXPRESSIONS {
+        let mut group = c.benchmark_group(name);
+
+        for (backend_name, backend) in [("bytecode", Backend::Bytecode), ("closure", Backend::Closure)] {
+            let expr = calc.compile_with_backend(input, backend).unwrap();
+            group.bench_function(BenchmarkId::from_parameter(backend_name), |b| b.iter(|| {
+                for binding in bindings.iter() {
+                    expr.evaluate(binding).unwrap();
+                }
+            }));
+        }
+
+        group.finish();
+    }
+}
+
+fn evaluate_columns(c: &mut Criterion) {
+    let calc = Calculator::new();
+
+    let x = (0..1000).map(|idx| idx as f64 * 0.5).collect::<Vec<_>>();
+    let y = (0..1000).map(|idx| (idx % 17 + 1) as f64).collect::<Vec<_>>();
+    let columns = HashMap::from([("x", x.as_slice()), ("y", y.as_slice())]);
+    let bindings = x.iter().zip(y.iter())
+        .map(|(&x, &y)| HashMap::from([
+            ("x".to_owned(), Value::Float(x)),
+            ("y".to_owned(), Value::Float(y))
+        ]))
+        .collect::<Vec<_>>();
+
+    for &(name, input) in EXPRESSIONS.iter().filter(|(name, _)| ["arithmetic", "conditional"].contains(name)) {
+        let mut group = c.benchmark_group(format!("{}_columns", name));
+        let expr = calc.compile(input).unwrap();
+
+        group.bench_function(BenchmarkId::from_parameter("rows"), |b| b.iter(|| {
+            for binding in bindings.iter() {
+                expr.evaluate(binding).unwrap();
+            }
+        }));
+        group.bench_function(BenchmarkId::from_parameter("columns"), |b| b.iter(|| expr.evaluate_columns(&columns).unwrap()));
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, evaluate_backends, evaluate_columns);
+criterion_main!(benches);
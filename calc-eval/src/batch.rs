@@ -0,0 +1,143 @@
+use std::io::Write;
+use calc_eval::{CalcError, Calculator, Value};
+use serde_json::json;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Results go to stdout and errors to stderr, as they would be shown in the interactive prompt.
+    Text,
+    /// Every line produces one JSON object on stdout, whether it succeeded or not.
+    Json
+}
+
+/// Evaluates `lines` in order with the same calculator, so later lines can use variables set by earlier ones.
+/// Blank lines and lines starting with '#' are skipped. Returns how many lines failed to evaluate.
+pub fn run<I>(calc: &mut Calculator, source_name: &str, lines: I, format: OutputFormat, out: &mut impl Write, err_out: &mut impl Write) -> std::io::Result<usize>
+where
+    I: IntoIterator<Item = std::io::Result<String>>
+{
+    let mut failures = 0;
+
+    for (idx, line) in lines.into_iter().enumerate() {
+        let line = line?;
+        let input = line.trim();
+        if input.is_empty() || input.starts_with('#') {
+            continue;
+        }
+
+        let line_number = idx + 1;
+        let result = calc.eval(input);
+        if result.is_err() {
+            failures += 1;
+        }
+
+        match (format, result) {
+            (OutputFormat::Text, Ok(val)) => writeln!(out, "{}", calc.format(&val))?,
+            (OutputFormat::Text, Err(err)) => writeln!(err_out, "{}:{}: {}", source_name, line_number, err.render(input))?,
+            (OutputFormat::Json, result) => writeln!(out, "{}", to_json(line_number, input, result))?
+        }
+    }
+
+    Ok(failures)
+}
+
+fn to_json(line_number: usize, input: &str, result: Result<Value, CalcError>) -> serde_json::Value {
+    match result {
+        Ok(val) => json!({
+            "line": line_number,
+            "input": input,
+            "value": value_to_json(&val),
+            "type": val.get_type_name()
+        }),
+        Err(err) => {
+            let (kind, spans) = match &err {
+                CalcError::Lex(diagnostics) => ("lex", diagnostics.0.iter().map(|diagnostic| diagnostic.span).collect()),
+                CalcError::Parse(diagnostics) => ("parse", diagnostics.0.iter().map(|diagnostic| diagnostic.span).collect()),
+                CalcError::Runtime { span, .. } => ("runtime", span.iter().copied().collect()),
+                CalcError::InvalidBytecode(_) |
+                CalcError::UnknownMode(_) |
+                CalcError::UnknownRadix(_) => ("runtime", vec![])
+            };
+
+            json!({
+                "line": line_number,
+                "input": input,
+                "error": err.to_string(),
+                "kind": kind,
+                "spans": spans.iter().map(|span| [span.start, span.end]).collect::<Vec<_>>()
+            })
+        }
+    }
+}
+
+//Numbers JSON can represent exactly are written as numbers; everything else uses the same text the prompt would show
+fn value_to_json(val: &Value) -> serde_json::Value {
+    match val {
+        Value::Integer(num) => json!(num),
+        Value::Float(num) if num.is_finite() => json!(num),
+        Value::Boolean(val) => json!(val),
+        val => json!(val.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_lines(lines: &[&str], format: OutputFormat) -> (usize, String, String) {
+        let mut calc = Calculator::new();
+        let mut out = vec![];
+        let mut err_out = vec![];
+
+        let lines = lines.iter().map(|line| Ok(line.to_string()));
+        let failures = run(&mut calc, "script.calc", lines, format, &mut out, &mut err_out).unwrap();
+
+        (failures, String::from_utf8(out).unwrap(), String::from_utf8(err_out).unwrap())
+    }
+
+    #[test]
+    fn run_should_evaluate_every_line() {
+        let lines = &["# Totals", "x = 2", "", "x * 1.5", "x / 0", "\"done\""];
+
+        let (failures, out, err_out) = run_lines(lines, OutputFormat::Text);
+        assert_eq!(failures, 1);
+        assert_eq!(out, "2\n3\ndone\n");
+        assert_eq!(err_out, "script.calc:5: Division by zero\n  x / 0\n  ^^^^^\n");
+
+        let (failures, out, err_out) = run_lines(lines, OutputFormat::Json);
+        assert_eq!(failures, 1);
+        assert_eq!(out, [
+            r#"{"input":"x = 2","line":2,"type":"integer","value":2}"#,
+            r#"{"input":"x * 1.5","line":4,"type":"float","value":3.0}"#,
+            r#"{"error":"Division by zero","input":"x / 0","kind":"runtime","line":5,"spans":[[0,5]]}"#,
+            r#"{"input":"\"done\"","line":6,"type":"string","value":"done"}"#,
+            ""
+        ].join("\n"));
+        assert_eq!(err_out, "");
+
+        let (failures, out, _) = run_lines(&["1 +", "2 $"], OutputFormat::Json);
+        assert_eq!(failures, 2);
+        assert_eq!(out, [
+            r#"{"error":"Expected expression, found end of input.","input":"1 +","kind":"parse","line":1,"spans":[[3,3]]}"#,
+            r#"{"error":"Unrecognized input: '$'.","input":"2 $","kind":"lex","line":2,"spans":[[2,3]]}"#,
+            ""
+        ].join("\n"));
+    }
+
+    #[test]
+    fn run_should_show_text_results_in_the_radix() {
+        let mut calc = Calculator::new();
+        calc.set_radix(calc_eval::Radix::Hex);
+
+        let mut out = vec![];
+        let lines = ["0b1111_1111", "-16 / 2"].iter().map(|line| Ok(line.to_string()));
+        run(&mut calc, "<args>", lines, OutputFormat::Text, &mut out, &mut vec![]).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "0xFF\n-0x8\n");
+
+        //JSON output stays machine readable, so numbers are written as numbers whatever the radix
+        let mut out = vec![];
+        let lines = ["0xFF"].iter().map(|line| Ok(line.to_string()));
+        run(&mut calc, "<args>", lines, OutputFormat::Json, &mut out, &mut vec![]).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "{\"input\":\"0xFF\",\"line\":1,\"type\":\"integer\",\"value\":255}\n");
+    }
+}
@@ -1,40 +1,824 @@
-use anyhow::{anyhow, Result};
+use std::collections::HashMap;
 use crate::calculator::{
-    interpreter::{Interpreter, MethodBuilder},
+    compiled_expression::{Backend, CompiledExpression},
+    error::Result,
+    interpreter::{ClosureCompiler, Environment, EvaluationMode, Interpreter, MethodBuilder, Optimizer, Radix, Value},
     tokenizer::{Tokenizer, Token},
-    syntax::try_parse_expression
+    syntax::{parse_expression, ExpressionSyntax}
 };
 
 pub struct Calculator {
     tokenizer: Tokenizer,
-    interpreter: Interpreter
+    interpreter: Interpreter,
+    environment: Environment,
+    radix: Radix
 }
 
 impl Calculator {
     pub fn new() -> Self {
         Calculator {
             tokenizer: Tokenizer::new(),
-            interpreter: Interpreter::new()
+            interpreter: Interpreter::new(),
+            environment: Environment::new(),
+            radix: Radix::default()
         }
     }
 
-    #[allow(unused)]
-    pub fn eval<T: AsRef<str>>(&self, str: T) -> Result<f64> {
-        let str = str.as_ref();
-        let tokens = self.tokenizer.tokenize(str)
-            .collect::<Vec<Token>>();
+    pub fn get_mode(&self) -> EvaluationMode {
+        self.interpreter.get_mode()
+    }
+
+    pub fn set_mode(&mut self, mode: EvaluationMode) {
+        self.interpreter.set_mode(mode);
+    }
 
-        let mut pos = 0;
-        let expr = try_parse_expression(&tokens, &mut pos)
-            .ok_or(anyhow!("Failed to parse expression."))?;
+    pub fn get_radix(&self) -> Radix {
+        self.radix
+    }
+
+    /// Sets the base [`Calculator::format`] writes whole numbers in. Evaluation isn't affected.
+    pub fn set_radix(&mut self, radix: Radix) {
+        self.radix = radix;
+    }
+
+    /// Formats a result for display in the current radix.
+    pub fn format(&self, val: &Value) -> String {
+        self.radix.format(val)
+    }
+
+    pub fn get_environment(&self) -> &Environment {
+        &self.environment
+    }
+
+    /// Forgets every variable and function defined so far.
+    pub fn clear(&mut self) {
+        self.environment = Environment::new();
+    }
+
+    pub fn eval<T: AsRef<str>>(&mut self, str: T) -> Result<Value> {
+        let method_builder = self.emit(str)?;
+        self.interpreter.evaluate_method(&method_builder, &mut self.environment)
+    }
+
+    /// Parses and emits `str` once, so it can be evaluated many times against different variable bindings.
+    /// Variables defined in this calculator so far are captured as they are now.
+    pub fn compile<T: AsRef<str>>(&self, str: T) -> Result<CompiledExpression> {
+        self.compile_with_backend(str, Backend::Bytecode)
+    }
+
+    /// Like `compile`, but chooses how the expression will be evaluated.
+    pub fn compile_with_backend<T: AsRef<str>>(&self, str: T, backend: Backend) -> Result<CompiledExpression> {
+        let expr = self.parse(str)?;
+        let method_builder = self.emit_syntax(expr.as_ref())?;
+        let compiled = CompiledExpression::new(method_builder, self.get_mode(), self.environment.clone())?;
+
+        match backend {
+            Backend::Bytecode => Ok(compiled),
+            Backend::Closure => {
+                let closure = expr.compile_closure(&ClosureCompiler::new(self.get_mode()))?;
+                Ok(compiled.with_closure(closure))
+            }
+        }
+    }
+
+    /// Evaluates `str` once per row of `columns`, which must all be the same length. See
+    /// `CompiledExpression::evaluate_columns`.
+    pub fn evaluate_columns<T: AsRef<str>>(&self, str: T, columns: &HashMap<&str, &[f64]>) -> Result<Vec<f64>> {
+        self.compile(str)?.evaluate_columns(columns)
+    }
+
+    /// Parses `str` without evaluating it, failing if it contains any syntax errors.
+    pub fn parse<T: AsRef<str>>(&self, str: T) -> Result<Box<dyn ExpressionSyntax>> {
+        let tokens = self.tokenizer.tokenize(str.as_ref())
+            .collect::<Vec<Token>>();
 
-        if pos != tokens.len() - 1 {
-            return Err(anyhow!("Unexpected token: {}.", tokens[pos]));
+        let result = parse_expression(&tokens);
+        if !result.errors.is_empty() {
+            return Err(result.errors.into());
         }
 
+        Ok(result.expr)
+    }
+
+    /// Parses `str` and differentiates it with respect to `var`, giving a simplified expression that can be displayed
+    /// or evaluated.
+    pub fn differentiate<T: AsRef<str>>(&self, str: T, var: &str) -> Result<Box<dyn ExpressionSyntax>> {
+        self.parse(str)?.derivative(var)
+    }
+
+    /// Parses `str` and emits the optimized bytecode `eval` would run for it.
+    pub fn emit<T: AsRef<str>>(&self, str: T) -> Result<MethodBuilder> {
+        let expr = self.parse(str)?;
+        self.emit_syntax(expr.as_ref())
+    }
+
+    fn emit_syntax(&self, expr: &dyn ExpressionSyntax) -> Result<MethodBuilder> {
         let mut method_builder = MethodBuilder::new();
         expr.emit_bytecode(&mut method_builder)?;
+        Optimizer::new(self.get_mode()).optimize(&mut method_builder);
+
+        Ok(method_builder)
+    }
+}
+
+impl Default for Calculator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calculator::error::CalcError;
+    use super::*;
+
+    #[test]
+    fn eval_should_persist_variables_between_calls() -> Result<()> {
+        let mut calc = Calculator::new();
+
+        assert_eq!(calc.eval("x = 3 * 4")?, Value::Integer(12));
+        assert_eq!(calc.eval("x + 1")?, Value::Integer(13));
+        assert_eq!(calc.eval("x = x / 2")?, Value::Integer(6));
+        assert_eq!(calc.eval("y = z = x - 1")?, Value::Integer(5));
+        assert_eq!(calc.eval("y * z")?, Value::Integer(25));
+        assert!(calc.eval("unknown + 1").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn eval_should_apply_power_operator() -> Result<()> {
+        let mut calc = Calculator::new();
+
+        assert_eq!(calc.eval("2 ^ 3 ^ 2")?, Value::Integer(512));
+        assert_eq!(calc.eval("(2 ^ 3) ^ 2")?, Value::Integer(64));
+        assert_eq!(calc.eval("-2 ^ 2")?, Value::Integer(-4));
+        assert_eq!(calc.eval("(-2) ^ 2")?, Value::Integer(4));
+        assert_eq!(calc.eval("2 ^ -1")?, Value::Float(0.5));
+        assert_eq!(calc.eval("3 * 2 ^ 2")?, Value::Integer(12));
+
+        Ok(())
+    }
+
+    #[test]
+    fn eval_should_evaluate_conditions() -> Result<()> {
+        let mut calc = Calculator::new();
+
+        assert_eq!(calc.eval("1 < 2")?, Value::Boolean(true));
+        assert_eq!(calc.eval("1 >= 2")?, Value::Boolean(false));
+        assert_eq!(calc.eval("1 + 1 == 2 && 3 != 4")?, Value::Boolean(true));
+        assert_eq!(calc.eval("!(1 < 2) || 2 <= 1")?, Value::Boolean(false));
+
+        calc.eval("a = 6")?;
+        calc.eval("b = 2")?;
+        assert_eq!(calc.eval("a > 3 && b != 0 ? a / b : 0")?, Value::Integer(3));
+        calc.eval("b = 0")?;
+        assert_eq!(calc.eval("a > 3 && b != 0 ? a / b : 0")?, Value::Integer(0));
+        assert_eq!(calc.eval("a < 0 ? -1 : a == 0 ? 0 : 1")?, Value::Integer(1));
+
+        //The right side of a logical expression and the untaken branch of a conditional are never evaluated
+        assert_eq!(calc.eval("false && undefined")?, Value::Boolean(false));
+        assert_eq!(calc.eval("true || undefined")?, Value::Boolean(true));
+        assert_eq!(calc.eval("true ? 2 : undefined")?, Value::Integer(2));
+
+        assert!(calc.eval("1 ? 2 : 3").is_err());
+        assert!(calc.eval("!1").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn eval_should_keep_values_typed() -> Result<()> {
+        let mut calc = Calculator::new();
+
+        assert_eq!(calc.eval("9007199254740993 + 2")?, Value::Integer(9007199254740995));
+        assert_eq!(calc.eval("7 / 2")?, Value::Integer(3));
+        assert_eq!(calc.eval("7 % 4")?, Value::Integer(3));
+        assert_eq!(calc.eval("7 / 2.0")?, Value::Float(3.5));
+        assert_eq!(calc.eval("1 + 0.5")?, Value::Float(1.5));
+        assert_eq!(calc.eval("2 == 2.0")?, Value::Boolean(true));
+        assert_eq!(calc.eval("\"fish\" + \" & \" + \"chips\"")?, Value::from("fish & chips"));
+        assert_eq!(calc.eval("\"abc\" < \"abd\"")?, Value::Boolean(true));
+        assert_eq!(calc.eval("\"1\" == 1")?, Value::Boolean(false));
+
+        let err = calc.eval("9223372036854775807 + 1").unwrap_err();
+        assert_eq!(err.to_string(), "Integer overflow");
+        let err = calc.eval("1 / 0").unwrap_err();
+        assert_eq!(err.to_string(), "Division by zero");
+        let err = calc.eval("1 + true").unwrap_err();
+        assert_eq!(err.to_string(), "Cannot apply '+' to integer and boolean");
+
+        Ok(())
+    }
+
+    #[test]
+    fn eval_should_track_units() -> Result<()> {
+        let mut calc = Calculator::new();
+
+        assert_eq!(calc.eval("3 km + 250 m")?.to_string(), "3.25 km");
+        assert_eq!(calc.eval("60 mi/h in m/s")?.to_string(), "26.8224 m/s");
+        assert_eq!(calc.eval("1.5 h in min")?.to_string(), "90 min");
+        assert_eq!(calc.eval("(2 m)^2 in cm^2")?.to_string(), "40000 cm^2");
+        assert_eq!(calc.eval("10 kg * 9.81 m/s^2 in N")?.to_string(), "98.10000000000001 N");
+        calc.eval("d = 100 km")?;
+        assert_eq!(calc.eval("d / 2 h")?.to_string(), "50 km/h");
+        assert_eq!(calc.eval("3 km / 500 m")?, Value::Float(6.0));
+        assert_eq!(calc.eval("1 mi > 1 km")?, Value::Boolean(true));
+        assert_eq!(calc.eval("max(1 ft, 1 m, 1 yd)")?.to_string(), "1 m");
+        assert_eq!(calc.eval("-(2 s) * 3")?.to_string(), "-6 s");
+
+        calc.set_mode(EvaluationMode::Rational);
+        assert_eq!(calc.eval("1/2 * 1 km in m")?.to_string(), "500 m");
+        calc.set_mode(EvaluationMode::Float);
+
+        let err = calc.eval("2 kg + 3 s").unwrap_err();
+        assert_eq!(err.to_string(), "Cannot apply '+' to kg and s");
+        let err = calc.eval("2 kg + 3").unwrap_err();
+        assert_eq!(err.to_string(), "Cannot apply '+' to kg and dimensionless");
+        let err = calc.eval("5 in m").unwrap_err();
+        assert_eq!(err.to_string(), "Cannot convert dimensionless to m");
+        let err = calc.eval("2 kg in s").unwrap_err();
+        assert_eq!(err.to_string(), "Cannot convert kg to s");
+        let err = calc.eval("(2 m)^0.5").unwrap_err();
+        assert_eq!(err.to_string(), "Quantities can only be raised to whole powers");
+
+        Ok(())
+    }
+
+    #[test]
+    fn eval_should_report_unit_exponent_overflow() {
+        let mut calc = Calculator::new();
+
+        let test_cases: &[(&str, &str)] = &[
+            ("(1 m)^2000000000 / (1 m)^-2000000000", "Unit exponent overflow"),
+            ("(1 m)^-2000000000 / (1 m)^2000000000", "Unit exponent overflow"),
+            ("(1 m)^2000000000 * (1 m*s)^2000000000", "Unit exponent overflow"),
+            ("(1 m)^-2000000000 * (1 m*s)^-2000000000", "Unit exponent overflow"),
+            ("(1 m)^2000000000 * (1 m)^2000000000", "Exponent is too large"),
+            ("1 m^2000000000/m^-2000000000", "Unit exponent overflow."),
+        ];
+
+        for &(input, expected_message) in test_cases {
+            let err = calc.eval(input).unwrap_err();
+            assert_eq!(err.to_string(), expected_message, "for input {:?}", input);
+        }
+    }
+
+    #[test]
+    fn eval_should_be_exact_in_rational_mode() -> Result<()> {
+        let mut calc = Calculator::new();
+
+        assert_eq!(calc.eval("0.1 + 0.2 == 0.3")?, Value::Boolean(false));
+
+        calc.set_mode(EvaluationMode::Rational);
+        assert_eq!(calc.eval("0.1 + 0.2 == 0.3")?, Value::Boolean(true));
+        assert_eq!(calc.eval("0.1 + 0.2")?.to_string(), "0.3");
+        assert_eq!(calc.eval("7 / 2")?.to_string(), "3.5");
+        assert_eq!(calc.eval("1 / 3")?.to_string(), "1/3");
+        assert_eq!(calc.eval("x = 1 / 3")?.to_string(), "1/3");
+        assert_eq!(calc.eval("x * 3")?.to_string(), "1");
+        assert_eq!(calc.eval("19.99 * 3")?.to_string(), "59.97");
+        assert_eq!(calc.eval("round(2.5) + floor(-0.5)")?.to_string(), "2");
+        assert_eq!(calc.eval("(1 / 2) ^ -2")?.to_string(), "4");
+
+        //Anything that can't stay exact falls back to a float
+        assert_eq!(calc.eval("sqrt(1 / 4)")?, Value::Float(0.5));
+        assert_eq!(calc.eval("2 ^ 0.5")?, Value::Float(2f64.sqrt()));
+
+        calc.set_mode(EvaluationMode::Float);
+        assert_eq!(calc.eval("x")?.to_string(), "1/3");
+        assert_eq!(calc.eval("7 / 2")?, Value::Integer(3));
+
+        Ok(())
+    }
+
+    #[test]
+    fn eval_should_call_builtin_functions() -> Result<()> {
+        let mut calc = Calculator::new();
+
+        assert_eq!(calc.eval("min(3.5, 2.7)")?, Value::Float(2.7));
+        assert_eq!(calc.eval("max(1, 5, 3)")?, Value::Integer(5));
+        assert_eq!(calc.eval("sqrt(16) + pow(2, 10)")?, Value::Float(1028.0));
+        assert_eq!(calc.eval("log(1000)")?, Value::Float(3.0));
+        assert_eq!(calc.eval("log(8, 2)")?, Value::Float(3.0));
+        assert_eq!(calc.eval("sin(0)")?, Value::Float(0.0));
+        assert_eq!(calc.eval("cos(pi)")?, Value::Float(-1.0));
+
+        let err = calc.eval("sqrt(1, 2)").unwrap_err();
+        assert_eq!(err.to_string(), "Function 'sqrt' expects 1 argument(s), but 2 were given.");
+        let err = calc.eval("min()").unwrap_err();
+        assert_eq!(err.to_string(), "Function 'min' expects at least 1 argument(s), but 0 were given.");
+        let err = calc.eval("frobnicate(1)").unwrap_err();
+        assert_eq!(err.to_string(), "Unknown function: frobnicate");
+
+        Ok(())
+    }
+
+    #[test]
+    fn eval_should_report_error_locations() {
+        let test_cases: &[(&str, &str)] = &[
+            ("1 + 10 / 0", "Division by zero\n  1 + 10 / 0\n      ^^^^^^"),
+            ("2 * (y - 1)", "Unknown variable: y\n  2 * (y - 1)\n       ^"),
+            ("-true + 1", "Cannot apply '-' to boolean\n  -true + 1\n  ^^^^^"),
+            ("sqrt(1, 2)", "Function 'sqrt' expects 1 argument(s), but 2 were given.\n  sqrt(1, 2)\n  ^^^^^^^^^^"),
+            ("1 ? 2 : 3", "Expected a boolean, found integer\n  1 ? 2 : 3\n  ^"),
+        ];
+
+        for &(input, expected_output) in test_cases {
+            let mut calc = Calculator::new();
+            let err = calc.eval(input).unwrap_err();
+            assert!(matches!(err, CalcError::Runtime { span: Some(_), .. }));
+            assert_eq!(err.render(input), expected_output);
+        }
+    }
+
+    #[test]
+    fn eval_should_report_every_syntax_error() {
+        let mut calc = Calculator::new();
+        let input = "max(1 2, 3) + (4 * ) $";
+
+        let err = calc.eval(input).unwrap_err();
+        assert!(matches!(err, CalcError::Parse(_)));
+        assert_eq!(err.render(input), [
+            "Expected ',' or ')', found '2'.\n  max(1 2, 3) + (4 * ) $\n        ^",
+            "Expected expression, found ')'.\n  max(1 2, 3) + (4 * ) $\n                     ^",
+            "Unrecognized input: '$'.\n  max(1 2, 3) + (4 * ) $\n                       ^",
+        ].join("\n"));
+
+        //Input that only fails to tokenize is a lexical error rather than a parse error
+        let err = calc.eval("1 + 2 $").unwrap_err();
+        assert!(matches!(err, CalcError::Lex(_)));
+        assert_eq!(err.to_string(), "Unrecognized input: '$'.");
+    }
+
+    #[test]
+    fn eval_should_reject_expressions_nested_too_deeply() -> Result<()> {
+        let mut calc = Calculator::new();
+
+        //The whole expression counts as one level, so 99 parentheses are the most that fit
+        let nested = format!("{}1{}", "(".repeat(99), ")".repeat(99));
+        assert_eq!(calc.eval(&nested)?, Value::Integer(1));
+        assert_eq!(calc.compile_with_backend(&nested, Backend::Closure)?.evaluate(&HashMap::new())?, Value::Integer(1));
+        calc.eval("x = 3")?;
+        assert_eq!(calc.eval(format!("d/dx {}x ^ 2{}", "(".repeat(97), ")".repeat(97)))?, Value::Integer(6));
+
+        let test_cases = [
+            format!("{}1{}", "(".repeat(100), ")".repeat(100)),
+            format!("{}1{}", "(".repeat(5000), ")".repeat(5000)),
+            format!("1{}", "^1".repeat(20000)),
+            format!("{}1", "-".repeat(5000)),
+            format!("{}1", "a = ".repeat(5000)),
+            format!("{}1", "true ? 1 : ".repeat(5000)),
+            format!("{}1{}", "[".repeat(5000), "]".repeat(5000)),
+            format!("{}1{}", "sqrt(".repeat(5000), ")".repeat(5000)),
+        ];
+
+        for input in test_cases.iter() {
+            let err = calc.eval(input).unwrap_err();
+            assert!(matches!(err, CalcError::Parse(_)), "for input {:.20}", input);
+            assert_eq!(err.to_string(), "Expression is nested too deeply.", "for input {:.20}", input);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn compile_should_evaluate_against_bindings() -> Result<()> {
+        let mut calc = Calculator::new();
+        calc.eval("k = 10")?;
+
+        let expr = calc.compile("a * x + b + k")?;
+        assert_eq!(expr.get_free_variables(), &["a", "x", "b"]);
+
+        //Later changes to the calculator don't affect an expression that has already been compiled
+        calc.eval("k = 20")?;
+
+        for (x, expected_output) in [(0, 13), (1, 15), (-4, 5)] {
+            let bindings = HashMap::from([
+                (String::from("a"), Value::Integer(2)),
+                (String::from("x"), Value::Integer(x)),
+                (String::from("b"), Value::Integer(3)),
+            ]);
+            assert_eq!(expr.evaluate(&bindings)?, Value::Integer(expected_output));
+        }
+
+        let err = expr.evaluate(&HashMap::from([(String::from("a"), Value::Integer(2))])).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown variable: x");
+
+        //Assignments inside a compiled expression don't leak from one evaluation into the next
+        let expr = calc.compile("n = (n > 0 ? n : 1) * 2")?;
+        assert_eq!(expr.get_free_variables(), &["n"]);
+        assert_eq!(expr.evaluate(&HashMap::from([(String::from("n"), Value::Integer(5))]))?, Value::Integer(10));
+        assert_eq!(expr.evaluate(&HashMap::new()).unwrap_err().to_string(), "Unknown variable: n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn built_in_constants_should_not_be_redefined() -> Result<()> {
+        let mut calc = Calculator::new();
+        calc.eval("x = 1")?;
+
+        for input in ["pi = 1", "e = x", "x = (pi = 2)", "x > 0 ? (e = 3) : 0"] {
+            let err = calc.eval(input).unwrap_err();
+            assert!(err.to_string().starts_with("Cannot redefine built-in constant"), "for input {:?}: {}", input, err);
+        }
+        assert_eq!(calc.eval("pi")?, Value::Float(std::f64::consts::PI));
+        assert_eq!(calc.eval("e")?, Value::Float(std::f64::consts::E));
+
+        for backend in [Backend::Bytecode, Backend::Closure] {
+            let expr = calc.compile_with_backend("e = x", backend)?;
+            let err = expr.evaluate(&HashMap::from([(String::from("x"), Value::Integer(1))])).unwrap_err();
+            assert_eq!(err.to_string(), "Cannot redefine built-in constant 'e'");
+
+            let expr = calc.compile_with_backend("pi * 2", backend)?;
+            let err = expr.evaluate(&HashMap::from([(String::from("pi"), Value::Integer(3))])).unwrap_err();
+            assert_eq!(err.to_string(), "Cannot redefine built-in constant 'pi'");
+        }
+
+        let x = [1.0, 2.0];
+        let pi = [3.0, 3.0];
+        let err = calc.evaluate_columns("pi * x", &HashMap::from([("x", &x[..]), ("pi", &pi[..])])).unwrap_err();
+        assert_eq!(err.to_string(), "Cannot redefine built-in constant 'pi'");
+
+        Ok(())
+    }
+
+    #[test]
+    fn eval_should_call_user_functions() -> Result<()> {
+        let mut calc = Calculator::new();
+
+        assert_eq!(calc.eval("f(x, y) = x * x + y")?, Value::from("f(x, y)"));
+        assert_eq!(calc.eval("f(3, 4)")?, Value::Integer(13));
+        assert_eq!(calc.eval("f(f(1, 1), 0) + 1")?, Value::Integer(5));
+
+        //Parameters shadow variables, and assigning to one doesn't leak out of the call
+        calc.eval("x = 100")?;
+        assert_eq!(calc.eval("g(x) = (x = x * 2) + x")?, Value::from("g(x)"));
+        assert_eq!(calc.eval("g(5)")?, Value::Integer(20));
+        assert_eq!(calc.eval("x")?, Value::Integer(100));
+
+        calc.eval("fact(n) = n <= 1 ? 1 : n * fact(n - 1)")?;
+        assert_eq!(calc.eval("fact(20)")?, Value::Integer(2432902008176640000));
+
+        calc.eval("fib(n) = n < 2 ? n : fib(n - 1) + fib(n - 2)")?;
+        assert_eq!(calc.eval("fib(15)")?, Value::Integer(610));
+
+        let test_cases: &[(&str, &str)] = &[
+            ("f(1)", "Function 'f' expects 2 argument(s), but 1 were given."),
+            ("forever(n) = forever(n + 1)", "forever(n)"),
+            ("forever(0)", "Maximum call depth of 1000 exceeded (in function 'forever')"),
+            ("fact(21)", "Integer overflow (in function 'fact')"),
+            ("sqrt(x) = x", "Cannot redefine built-in function 'sqrt'"),
+            ("e = 3", "Cannot redefine built-in constant 'e'"),
+            ("x = pi = 3", "Cannot redefine built-in constant 'pi'"),
+            ("e", "2.718281828459045"),
+            ("k(e) = e * 2", "k(e)"),
+            ("k(3)", "6"),
+            ("h(x, x) = x", "Duplicate parameter: x"),
+        ];
+
+        for &(input, expected_output) in test_cases {
+            let output = match calc.eval(input) {
+                Result::Ok(val) => val.to_string(),
+                Err(err) => err.to_string()
+            };
+            assert_eq!(output, expected_output);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn eval_should_evaluate_derivatives() -> Result<()> {
+        let mut calc = Calculator::new();
+        calc.eval("x = 2")?;
+
+        assert_eq!(calc.differentiate("x ^ 2 * sin(x)", "x")?.to_string(), "2 * x * sin(x) + x ^ 2 * cos(x)");
+        assert_eq!(calc.eval("d/dx (x ^ 3)")?, Value::Float(12.0));
+        assert_eq!(calc.eval("d/dx (1 / x)")?, Value::Float(-0.25));
+        assert_eq!(calc.eval("d/dx (x ^ 2 * sin(x))")?, calc.eval("2 * x * sin(x) + x ^ 2 * cos(x)")?);
+
+        //Inside a function, the derivative is taken with respect to a parameter
+        calc.eval("slope(t) = d/dt (t ^ 2 - 3 * t)")?;
+        assert_eq!(calc.eval("slope(5)")?, Value::Float(7.0));
+
+        //Without the parentheses it's just a division
+        calc.eval("d = 6")?;
+        calc.eval("dx = 3")?;
+        assert_eq!(calc.eval("d/dx")?, Value::Integer(2));
+
+        let test_cases: &[(&str, &str)] = &[
+            ("d/dx (x > 1)", "Comparisons can't be differentiated\n  d/dx (x > 1)\n        ^^^^^"),
+            ("d/dx (1 + max(x, 1))", "Function 'max' can't be differentiated\n  d/dx (1 + max(x, 1))\n            ^^^^^^^^^"),
+            ("d/dx (x % 3)", "'%' can't be differentiated\n  d/dx (x % 3)\n        ^^^^^"),
+        ];
+
+        for &(input, expected_output) in test_cases {
+            let err = calc.eval(input).unwrap_err();
+            assert_eq!(err.render(input), expected_output);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn eval_should_support_complex_numbers() -> Result<()> {
+        let mut calc = Calculator::new();
+
+        let test_cases: &[(&str, &str)] = &[
+            ("(3 + 4i) * (1 - 2i)", "11 - 2i"),
+            ("(3 + 4i) / 2i", "2 - 1.5i"),
+            ("abs(3 + 4i)", "5"),
+            ("arg(1i)", "1.5707963267948966"),
+            ("arg(-2)", "3.141592653589793"),
+            ("sqrt(-1)", "1i"),
+            ("sqrt(-4) ^ 2", "-4"),
+            ("sqrt(2i)", "1 + 1i"),
+            ("exp(1i * pi / 2)", "0.00000000000000006123233995736766 + 1i"),
+            ("(1 + 1i) * (1 - 1i)", "2"),
+            ("2i == sqrt(-4)", "true"),
+            ("-2.5i", "-2.5i"),
+            ("(-8) ^ 0.5", "2.8284271247461903i"),
+            ("(-8) ^ 0.5 == sqrt(-8)", "true"),
+            ("(-8) ^ 2.0", "64"),
+            ("ln(-1)", "3.141592653589793i"),
+            ("ln(1i)", "1.5707963267948966i"),
+            ("log(-100)", "2 + 1.3643763538418412i"),
+            ("log(-8, 2)", "3 + 4.532360141827194i"),
+            ("log2(-4)", "2 + 4.532360141827194i"),
+            ("log(100)", "2"),
+            ("(-8) ^ (1 / 3.0)", "1.0000000000000002 + 1.7320508075688772i"),
+            ("1e308i * 10", "infi"),
+            ("(1e308i * 10) * 1", "infi"),
+            ("(1e308i * 10) / 1", "infi"),
+            ("(1 - 1e308i * 10) / 2", "0.5 - infi"),
+        ];
+
+        for &(input, expected_output) in test_cases {
+            assert_eq!(calc.eval(input)?.to_string(), expected_output, "for input {:?}", input);
+        }
+
+        calc.set_mode(EvaluationMode::Rational);
+        assert_eq!(calc.eval("(0.1 + 2i) * 10")?.to_string(), "1 + 20i");
+        assert_eq!(calc.eval("(-0.25) ^ 0.5")?.to_string(), "0.5i");
+
+        let test_cases: &[(&str, &str)] = &[
+            ("2i < 3", "Cannot apply '<' to complex numbers\n  2i < 3\n  ^^^^^^"),
+            ("sin(1 + 1i)", "Expected a real number, found complex\n  sin(1 + 1i)\n  ^^^^^^^^^^^"),
+            ("2i km", "Expected end of input, found 'km'.\n  2i km\n     ^^"),
+        ];
+
+        for &(input, expected_output) in test_cases {
+            let err = calc.eval(input).unwrap_err();
+            assert_eq!(err.render(input), expected_output);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn eval_should_give_complex_results_for_fractional_powers_and_logs_of_negative_numbers() -> Result<()> {
+        let mut calc = Calculator::new();
+
+        //These used to be NaN, which then quietly spread through the rest of the expression
+        let test_cases: &[(&str, &str)] = &[
+            ("(-8) ^ 0.5", "2.8284271247461903i"),
+            ("(-8) ^ 0.5 * 0", "0"),
+            ("(-8) ^ 3", "-512"),
+            ("(-8.0) ^ 2.0", "64"),
+            ("ln(-1)", "3.141592653589793i"),
+            ("log(-100)", "2 + 1.3643763538418412i"),
+            ("log10(-100) == log(-100)", "true"),
+            ("log(-8, 2) == log2(-8)", "true"),
+            ("ln(1)", "0"),
+            ("ln(0)", "-inf"),
+        ];
+
+        for &(input, expected_output) in test_cases {
+            assert_eq!(calc.eval(input)?.to_string(), expected_output, "for input {:?}", input);
+        }
+
+        //Columns hold real numbers only, so a row with a complex result is an error rather than NaN
+        let x: &[f64] = &[4.0, -4.0];
+        let columns = HashMap::from([("x", x)]);
+        let err = calc.evaluate_columns("x ^ 0.5", &columns).unwrap_err();
+        assert_eq!(err.to_string(), "Expected a real number, found complex");
+
+        Ok(())
+    }
+
+    #[test]
+    fn eval_should_support_vectors_and_matrices() -> Result<()> {
+        let mut calc = Calculator::new();
+        calc.eval("v = [1, 2, 3]")?;
+        calc.eval("m = [[1, 2], [3, 4]]")?;
+
+        let test_cases: &[(&str, &str)] = &[
+            ("v", "[1, 2, 3]"),
+            ("v[0] + v[2]", "4"),
+            ("m[1][0]", "3"),
+            ("v * 2 + 1", "[3, 5, 7]"),
+            ("v - [3, 2, 1]", "[-2, 0, 2]"),
+            ("12 / v", "[12, 6, 4]"),
+            ("m * m", "[[1, 4], [9, 16]]"),
+            ("m + [10, 20]", "[[11, 22], [13, 24]]"),
+            ("-m[0]", "[-1, -2]"),
+            ("matmul(m, m)", "[[7, 10], [15, 22]]"),
+            ("matmul(v, v)", "14"),
+            ("transpose(m)", "[[1, 3], [2, 4]]"),
+            ("det(m)", "-2"),
+            ("inverse(m)", "[[-2, 1], [1.5, -0.5]]"),
+            ("matmul(m, inverse(m))", "[[1, 0], [0, 1]]"),
+            ("v == [1, 2, 3]", "true"),
+            ("[]", "[]"),
+            ("d/dx ([x ^ 2, 3 * x])", "[10, 3]"),
+        ];
+
+        calc.eval("x = 5")?;
+        for &(input, expected_output) in test_cases {
+            assert_eq!(calc.eval(input)?.to_string(), expected_output, "for input {:?}", input);
+        }
+
+        let test_cases: &[(&str, &str)] = &[
+            ("v[3]", "Index 3 is out of range for a vector of length 3\n  v[3]\n  ^^^^"),
+            ("v[\"a\"]", "Expected a whole number as the index, found string\n  v[\"a\"]\n  ^^^^^^"),
+            ("[1, 2][1.5]", "Expected a whole number as the index, found 1.5\n  [1, 2][1.5]\n  ^^^^^^^^^^^"),
+            ("v[-1]", "Index -1 is out of range for a vector of length 3\n  v[-1]\n  ^^^^^"),
+            ("v + [1, 2]", "Cannot apply '+' to vectors of length 3 and 2\n  v + [1, 2]\n  ^^^^^^^^^^"),
+            ("1 + (2)[0]", "Cannot index into integer\n  1 + (2)[0]\n       ^^^^^"),
+            ("det([[1, 2, 3], [4, 5, 6]])", "Function 'det' expects a square matrix, found a 2x3 matrix\n  det([[1, 2, 3], [4, 5, 6]])\n  ^^^^^^^^^^^^^^^^^^^^^^^^^^^"),
+            ("inverse([[1, 2], [2, 4]])", "The matrix is singular, so it has no inverse\n  inverse([[1, 2], [2, 4]])\n  ^^^^^^^^^^^^^^^^^^^^^^^^^"),
+        ];
+
+        for &(input, expected_output) in test_cases {
+            let err = calc.eval(input).unwrap_err();
+            assert_eq!(err.render(input), expected_output);
+        }
+
+        calc.set_mode(EvaluationMode::Rational);
+        assert_eq!(calc.eval("inverse([[3, 0], [0, 3]])")?.to_string(), "[[1/3, 0], [0, 1/3]]");
+
+        Ok(())
+    }
+
+    #[test]
+    fn eval_should_solve_and_integrate() -> Result<()> {
+        let mut calc = Calculator::new();
+        let approx = |val: Value, expected: f64| (val.to_f64().unwrap() - expected).abs() <= 1e-9;
+
+        assert!(approx(calc.eval("solve(x ^ 2 - 2, x, 1)")?, 2f64.sqrt()));
+        assert!(approx(calc.eval("solve(cos(x) - x, x, 0, 1)")?, 0.7390851332151607));
+        assert!(approx(calc.eval("integrate(sin(x), x, 0, pi)")?, 2.0));
+        assert!(approx(calc.eval("integrate(solve(t ^ 2 - x, t, 1), x, 0, 4)")?, 16.0 / 3.0));
+
+        //The expression can use variables, and the parameters of the function it's in
+        calc.eval("a = 9")?;
+        assert!(approx(calc.eval("solve(x ^ 2 - a, x, 1)")?, 3.0));
+        calc.eval("root(n) = solve(x ^ 2 - n, x, 1)")?;
+        assert!(approx(calc.eval("root(16)")?, 4.0));
+        calc.eval("area(x) = integrate(x * t, t, 0, 2)")?;
+        assert!(approx(calc.eval("area(3)")?, 6.0));
+        assert!(approx(calc.eval("solve(d/dx (x ^ 3) - 12, x, 1)")?, 2.0));
+
+        let test_cases: &[(&str, &str)] = &[
+            ("solve(x ^ 2 + 1, x, 1)", "solve didn't converge after 100 iterations starting from 1\n  solve(x ^ 2 + 1, x, 1)\n  ^^^^^^^^^^^^^^^^^^^^^^"),
+            ("solve(x ^ 2 - 2, x, 2, 3)", "solve needs the expression to change sign between 2 and 3\n  solve(x ^ 2 - 2, x, 2, 3)\n  ^^^^^^^^^^^^^^^^^^^^^^^^^"),
+            ("integrate(1 / x, x, 0, 1)", "The expression is inf at 0\n  integrate(1 / x, x, 0, 1)\n  ^^^^^^^^^^^^^^^^^^^^^^^^^"),
+            ("integrate(y, x, 0, 1)", "Unknown variable: y\n  integrate(y, x, 0, 1)\n  ^^^^^^^^^^^^^^^^^^^^^"),
+            ("solve(x, 2 * x, 1)", "The second argument of 'solve' must be a variable\n  solve(x, 2 * x, 1)\n           ^^^^^"),
+            ("integrate(x, x, 1)", "Function 'integrate' expects 4 argument(s), but 3 were given.\n  integrate(x, x, 1)\n  ^^^^^^^^^^^^^^^^^^"),
+        ];
+
+        for &(input, expected_output) in test_cases {
+            let err = calc.eval(input).unwrap_err();
+            assert_eq!(err.render(input), expected_output);
+        }
+
+        let err = calc.eval("solve(x) = x").unwrap_err();
+        assert_eq!(err.to_string(), "Cannot redefine built-in function 'solve'");
+
+        Ok(())
+    }
+
+    #[test]
+    fn eval_should_name_only_the_innermost_failing_function() -> Result<()> {
+        let mut calc = Calculator::new();
+        calc.eval("g(n) = n <= 0 ? 1 / 0 : solve(x - g(n - 1), x, 0)")?;
+        calc.eval("h(n) = integrate(g(n) * t, t, 0, 1)")?;
+        calc.eval("deep(n) = n <= 0 ? solve(x - unknown, x, 0) : deep(n - 1)")?;
+
+        let test_cases: &[(&str, &str)] = &[
+            ("g(3)", "Division by zero (in function 'g')"),
+            ("h(2)", "Division by zero (in function 'g')"),
+            ("deep(200)", "Unknown variable: unknown (in function 'deep')"),
+            ("integrate(g(1) * x, x, 0, 1)", "Division by zero (in function 'g')"),
+        ];
+
+        for &(input, expected_message) in test_cases {
+            let err = calc.eval(input).unwrap_err();
+            assert_eq!(err.to_string(), expected_message, "for input {:?}", input);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn eval_should_read_numbers_in_every_notation() -> Result<()> {
+        let mut calc = Calculator::new();
+
+        let test_cases: &[(&str, &str)] = &[
+            ("0xFF + 0b1010 + 0o17", "280"),
+            ("0XfF", "255"),
+            ("1_000_000 * 2", "2000000"),
+            ("1e-9 * 1e9", "1"),
+            ("2.5E3", "2500"),
+            (".5 + .25", "0.75"),
+            ("0x7FFF_FFFF_FFFF_FFFF", "9223372036854775807"),
+            ("0xFFFF_FFFF_FFFF_FFFF", "Hex literal '0xFFFF_FFFF_FFFF_FFFF' does not fit in a 64-bit integer."),
+            ("0xFFFF_FFFF_FFFF_FFFF - 1", "Hex literal '0xFFFF_FFFF_FFFF_FFFF' does not fit in a 64-bit integer."),
+            ("0b1000000000000000000000000000000000000000000000000000000000000000", "Binary literal '0b1000000000000000000000000000000000000000000000000000000000000000' does not fit in a 64-bit integer."),
+            ("0o1_000_000_000_000_000_000_000", "Octal literal '0o1_000_000_000_000_000_000_000' does not fit in a 64-bit integer."),
+        ];
+
+        for &(input, expected_output) in test_cases {
+            let output = calc.eval(input).map_or_else(|err| err.to_string(), |val| val.to_string());
+            assert_eq!(output, expected_output, "for input {:?}", input);
+        }
+
+        calc.set_mode(EvaluationMode::Rational);
+        assert_eq!(calc.eval("1e-3 + 0x10")?.to_string(), "16.001");
+        calc.set_mode(EvaluationMode::Float);
+
+        let err = calc.eval("0b102").unwrap_err();
+        assert_eq!(err.render("0b102"), "Unrecognized input: '0b102'.\n  0b102\n  ^^^^^");
+
+        let input = "1 + 0x8000_0000_0000_0000";
+        let err = calc.eval(input).unwrap_err();
+        assert!(matches!(err, CalcError::Lex(_)));
+        assert_eq!(err.render(input), "Hex literal '0x8000_0000_0000_0000' does not fit in a 64-bit integer.\n  1 + 0x8000_0000_0000_0000\n      ^^^^^^^^^^^^^^^^^^^^^");
+
+        Ok(())
+    }
+
+    #[test]
+    fn eval_should_reject_hex_binary_and_octal_literals_wider_than_64_bits() -> Result<()> {
+        let mut calc = Calculator::new();
+
+        assert_eq!(calc.eval("0x7FFF_FFFF_FFFF_FFFF")?, Value::Integer(i64::MAX));
+        assert_eq!(calc.eval("0b111_1111")?, Value::Integer(127));
+        assert_eq!(calc.eval("0o777_777_777_777_777_777_777")?, Value::Integer(i64::MAX));
+
+        let test_cases: &[(&str, &str)] = &[
+            ("0x1_0000_0000_0000_0000", "Hex literal '0x1_0000_0000_0000_0000' does not fit in a 64-bit integer."),
+            ("0b1_0000000000000000000000000000000000000000000000000000000000000000", "Binary literal '0b1_0000000000000000000000000000000000000000000000000000000000000000' does not fit in a 64-bit integer."),
+            ("0o1_777_777_777_777_777_777_777", "Octal literal '0o1_777_777_777_777_777_777_777' does not fit in a 64-bit integer."),
+        ];
+
+        for &(input, expected_output) in test_cases {
+            let err = calc.eval(input).unwrap_err();
+            assert!(matches!(err, CalcError::Lex(_)), "for input {:?}", input);
+            assert_eq!(err.to_string(), expected_output);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn eval_should_read_decimal_integers_wider_than_64_bits_as_floats() -> Result<()> {
+        let mut calc = Calculator::new();
+
+        //Unlike hex, binary and octal literals, which are bit patterns, a decimal literal is a magnitude, so one that
+        //doesn't fit in an i64 is rounded to the nearest float instead of being refused
+        assert_eq!(calc.eval("9223372036854775807")?, Value::Integer(i64::MAX));
+        assert_eq!(calc.eval("9223372036854775808")?, Value::Float(9223372036854775808.0));
+        assert_eq!(calc.eval("99999999999999999999")?, Value::Float(1e20));
+        assert_eq!(calc.eval("99999999999999999999")?.to_string(), "100000000000000000000");
+        assert_eq!(calc.eval("-9223372036854775808")?, Value::Float(-9223372036854775808.0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn format_should_use_the_radix() -> Result<()> {
+        let mut calc = Calculator::new();
+
+        let test_cases: &[(Radix, &str, &str)] = &[
+            (Radix::Hex, "0xF0 + 0x0F", "0xFF"),
+            (Radix::Hex, "[255, -16, 1.5]", "[0xFF, -0x10, 1.5]"),
+            (Radix::Binary, "0xA", "0b1010"),
+            (Radix::Decimal, "0b1010", "10"),
+        ];
+
+        for &(radix, input, expected_output) in test_cases {
+            calc.set_radix(radix);
+            let val = calc.eval(input)?;
+            assert_eq!(calc.format(&val), expected_output, "for input {:?}", input);
+        }
+
+        let err = "octal".parse::<Radix>().unwrap_err();
+        assert_eq!(err.to_string(), "Unknown radix: octal. Expected \"decimal\", \"hex\" or \"binary\".");
 
-        self.interpreter.evaluate_method(&method_builder)
+        Ok(())
     }
 }
@@ -0,0 +1,205 @@
+use std::{collections::HashMap, rc::Rc};
+use crate::calculator::{
+    error::{runtime_error, Result},
+    interpreter::{verify, Closure, ClosureContext, Environment, EvaluationMode, Interpreter, MethodBuilder, Op, Value, VectorInterpreter}
+};
+
+/// How a compiled expression is evaluated. Both backends give the same results and the same errors, since optimizing
+/// the bytecode never changes either.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// Runs the verified bytecode on the interpreter's stack machine.
+    #[default]
+    Bytecode,
+    /// Compiles the syntax tree into nested closures, which is faster when the expression is evaluated many times.
+    Closure
+}
+
+/// An expression that has already been parsed and emitted, ready to be evaluated against different variable bindings.
+pub struct CompiledExpression {
+    method: MethodBuilder,
+    max_stack_depth: usize,
+    interpreter: Interpreter,
+    /// Shared with every evaluation, which layers its bindings over it instead of copying it.
+    environment: Rc<Environment>,
+    free_variables: Vec<String>,
+    /// Evaluated instead of the bytecode when present.
+    closure: Option<Closure>
+}
+
+impl CompiledExpression {
+    /// `environment` supplies any variables that the bindings passed to `evaluate` leave out. The method is verified
+    /// here, once, rather than every time it is evaluated.
+    pub fn new(method: MethodBuilder, mode: EvaluationMode, environment: Environment) -> Result<Self> {
+        let max_stack_depth = verify(&method)?;
+
+        let mut interpreter = Interpreter::new();
+        interpreter.set_mode(mode);
+
+        let free_variables = Self::collect_free_variables(&method, &environment);
+
+        Ok(Self {
+            method,
+            max_stack_depth,
+            interpreter,
+            environment: Rc::new(environment),
+            free_variables,
+            closure: None
+        })
+    }
+
+    /// Variables the expression reads before assigning them that `environment` doesn't define, in order of first use.
+    fn collect_free_variables(method: &MethodBuilder, environment: &Environment) -> Vec<String> {
+        let mut assigned_names = vec![];
+        let mut free_variables = vec![];
+
+        for op in method.ops.iter() {
+            match *op {
+                Op::Ldvar(name_idx) => {
+                    let name = &method.names[name_idx];
+                    if !assigned_names.contains(&name_idx) && !free_variables.contains(name) && environment.get_variable(name).is_none() {
+                        free_variables.push(name.clone());
+                    }
+                },
+                Op::Stvar(name_idx) => assigned_names.push(name_idx),
+                _ => { }
+            }
+        }
+
+        free_variables
+    }
+
+    /// Evaluates with `closure` instead of the bytecode. It has to have been compiled from the same expression.
+    pub fn with_closure(mut self, closure: Closure) -> Self {
+        self.closure = Some(closure);
+        self
+    }
+
+    pub fn get_free_variables(&self) -> &[String] {
+        &self.free_variables
+    }
+
+    /// Runs the expression with `bindings` layered over the variables captured when it was compiled. Binding a
+    /// built-in constant like `pi` is an error, the same as assigning it.
+    pub fn evaluate(&self, bindings: &HashMap<String, Value>) -> Result<Value> {
+        let mut environment = Environment::with_parent(self.environment.clone());
+        for (name, value) in bindings.iter() {
+            environment.assign_variable(name, value.clone())?;
+        }
+
+        match &self.closure {
+            Some(closure) => closure(&mut ClosureContext {
+                interpreter: &self.interpreter,
+                environment: &mut environment
+            }),
+            None => self.interpreter.evaluate_verified_method(&self.method, self.max_stack_depth, &mut environment)
+        }
+    }
+
+    /// Evaluates the expression once per row of `columns`, running each op over a whole column at a time. Each row gives
+    /// what [`Backend::Bytecode`] in float mode would with the columns bound to floats, and variables that aren't columns
+    /// keep the value they had when the expression was compiled. If any row fails, the error is the first failing row's.
+    pub fn evaluate_columns(&self, columns: &HashMap<&str, &[f64]>) -> Result<Vec<f64>> {
+        if let Some(name) = columns.keys().find(|name| Environment::is_constant(name)) {
+            return Err(runtime_error!("Cannot redefine built-in constant '{}'", name));
+        }
+        VectorInterpreter::new().evaluate_method(&self.method, columns, &self.environment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_complex::Complex64;
+    use crate::calculator::Calculator;
+    use super::*;
+
+    #[test]
+    fn evaluate_should_give_the_same_results_with_every_backend() {
+        let test_cases: &[&str] = &[
+            "1 + 2 * x - y / 4 % 3 ^ 2",
+            "-x ^ 2 + +y",
+            "!(x > y) && x != 2 || y >= 3",
+            "x < y ? \"less\" : x == y ? \"same\" : \"more\"",
+            "(y = x + 1) * y",
+            "z = x",
+            "x && (w = 1) ? w : x",
+            "x km + 250 m in m",
+            "(x m / 2 s) in km/h",
+            "x kg + 1 s",
+            "max(x, y, 4) + min(1.5, sqrt(16))",
+            "sq(x + 1) + fact(5)",
+            "sq(x, y)",
+            "sq(\"a\")",
+            "loop(x)",
+            "unknown + 1",
+            "undefined(x)",
+            "x / y",
+            "\"a\" + x",
+            "1 + true",
+            "9223372036854775808 * x",
+            "g(t) = t * k + x",
+            "sin(t) = t",
+            "1 / 3 + x",
+            "d/dx (x ^ 3 * y - sin(x) / x)",
+            "d/dy (x > 0 ? y ^ 2 : ln(y))",
+            "solve(t ^ 3 - x, t, 1) + integrate(t * y, t, 0, x)",
+            "solve(t, t, y)",
+            "(x + 2i) * (y - 0.5i) / sqrt(-x) + abs(3 - 4i) * arg(y * 1i)",
+            "[x, y] * 2 - [[1, x], [y, 3]][1] / [2, 4]",
+            "det([[x, 1], [2, y]]) + matmul(inverse([[2, 0], [0, 4]]), [x, y])[1]",
+            "[x, y][x]",
+            "true * 1",
+            "\"a\" - 0",
+            "-(-\"a\")",
+            "x * 1 + 1 * y",
+            "-(-x)",
+            "-(-(-x)) - 0",
+            "(x - y) * 1 / 1",
+            "(x ^ 2) ^ 1",
+            "(x * y) ^ 1",
+            "-x * 1 + -x / 1",
+            "(x - 1) * 1",
+            "(x % 2) * 1",
+            "(-x) ^ 0.5 + ln(-x) * log(-y, 2)",
+            "e = x",
+            "x > 1 ? (pi = x) : e",
+        ];
+
+        let binding_sets: &[&[(&str, Value)]] = &[
+            &[("x", Value::Integer(2)), ("y", Value::Integer(0))],
+            &[("x", Value::Float(-1.5)), ("y", Value::Integer(3))],
+            &[("x", Value::Boolean(true)), ("y", Value::Boolean(false))],
+            &[("x", Value::String("s".to_owned())), ("y", Value::Integer(1))],
+            &[("x", Value::Integer(4))],
+            &[("x", Value::from(Complex64::new(0.0, f64::INFINITY))), ("y", Value::Float(2.0))],
+        ];
+
+        for mode in [EvaluationMode::Float, EvaluationMode::Rational] {
+            let mut calc = Calculator::new();
+            calc.set_mode(mode);
+            calc.eval("sq(x) = x * x").unwrap();
+            calc.eval("fact(n) = n <= 1 ? 1 : n * fact(n - 1)").unwrap();
+            calc.eval("loop(n) = loop(n + 1)").unwrap();
+            calc.eval("k = 3").unwrap();
+
+            for &input in test_cases {
+                let Ok(bytecode) = calc.compile_with_backend(input, Backend::Bytecode) else {
+                    assert!(calc.compile_with_backend(input, Backend::Closure).is_err(), "for input {:?}", input);
+                    continue;
+                };
+                let closure = calc.compile_with_backend(input, Backend::Closure).unwrap();
+
+                for &binding_set in binding_sets {
+                    let bindings = binding_set.iter()
+                        .map(|(name, val)| (name.to_string(), val.clone()))
+                        .collect::<HashMap<_, _>>();
+
+                    //Compared through Debug so that NaN results match each other
+                    let closure_result = format!("{:?}", closure.evaluate(&bindings));
+                    let bytecode_result = format!("{:?}", bytecode.evaluate(&bindings));
+                    assert_eq!(closure_result, bytecode_result, "for input {:?} with {:?} in {} mode", input, binding_set, mode);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt::Display;
+use crate::calculator::tokenizer::Span;
+
+/// An error that can be traced back to a range of the source being evaluated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Span
+}
+
+impl Diagnostic {
+    pub fn new<T: Into<String>>(message: T, span: Span) -> Self {
+        Self {
+            message: message.into(),
+            span
+        }
+    }
+
+    /// Renders the message followed by the offending line of `source` with a caret underline beneath the span.
+    ///
+    /// The span doesn't have to fit `source`, since it may come from deserialized bytecode or another string: it's cut
+    /// off at the end of `source` and widened to whole characters.
+    pub fn render(&self, source: &str) -> String {
+        let mut start = self.span.start.min(source.len());
+        while !source.is_char_boundary(start) {
+            start -= 1;
+        }
+        let mut end = self.span.end.clamp(start, source.len());
+        while !source.is_char_boundary(end) {
+            end += 1;
+        }
+
+        let line_start = source[..start].rfind('\n').map_or(0, |idx| idx + 1);
+        let line_end = source[start..].find('\n').map_or(source.len(), |idx| start + idx);
+        let line = &source[line_start..line_end];
+
+        let padding = source[line_start..start].chars().count();
+        let width = source[start..end.min(line_end)].chars().count().max(1);
+
+        format!("{}\n  {}\n  {}{}", self.message, line, " ".repeat(padding), "^".repeat(width))
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for Diagnostic { }
+
+/// Several diagnostics reported together, such as every syntax error found in a single parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostics(pub Vec<Diagnostic>);
+
+impl Diagnostics {
+    pub fn render(&self, source: &str) -> String {
+        self.0.iter()
+            .map(|diagnostic| diagnostic.render(source))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl Display for Diagnostics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (idx, diagnostic) in self.0.iter().enumerate() {
+            if idx > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostics { }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_should_underline_span() {
+        let test_cases: &[(&str, (usize, usize), &str)] = &[
+            ("1 + )", (4, 5), "Oops\n  1 + )\n      ^"),
+            ("12 / 0 + 1", (0, 6), "Oops\n  12 / 0 + 1\n  ^^^^^^"),
+            ("1 +", (3, 3), "Oops\n  1 +\n     ^"),
+            ("é + ü", (5, 7), "Oops\n  é + ü\n      ^"),
+            ("1 +\n2 $", (6, 7), "Oops\n  2 $\n    ^"),
+            ("1 + 2", (3, 40), "Oops\n  1 + 2\n     ^^"),
+            ("1 + 2", (40, 50), "Oops\n  1 + 2\n       ^"),
+            ("1 + 2", (4, 2), "Oops\n  1 + 2\n      ^"),
+            ("é + ü", (1, 2), "Oops\n  é + ü\n  ^"),
+            ("é + ü", (4, 6), "Oops\n  é + ü\n     ^^"),
+        ];
+
+        for &(source, (start, end), expected_output) in test_cases {
+            let diagnostic = Diagnostic::new("Oops", Span::new(start, end));
+            assert_eq!(diagnostic.render(source), expected_output);
+        }
+    }
+
+    #[test]
+    fn render_should_widen_a_span_that_ends_inside_a_character() {
+        //`é` takes bytes 0..2 and `ü` takes bytes 4..6, so both spans end halfway through a character
+        let diagnostic = Diagnostic::new("Oops", Span::new(0, 1));
+        assert_eq!(diagnostic.render("é + ü"), "Oops\n  é + ü\n  ^");
+
+        let diagnostic = Diagnostic::new("Oops", Span::new(2, 5));
+        assert_eq!(diagnostic.render("é + ü"), "Oops\n  é + ü\n   ^^^");
+    }
+}
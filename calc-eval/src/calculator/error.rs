@@ -0,0 +1,99 @@
+use thiserror::Error;
+use super::{
+    diagnostic::{Diagnostic, Diagnostics},
+    tokenizer::Span
+};
+
+/// Everything that can go wrong while turning input into a value.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum CalcError {
+    /// The input contains text the tokenizer doesn't recognize, and nothing else is wrong with it.
+    #[error("{0}")]
+    Lex(Diagnostics),
+    /// The input isn't a well-formed expression. Every error that was found is included, unrecognized input too.
+    #[error("{0}")]
+    Parse(Diagnostics),
+    /// Evaluation failed, for example by dividing by zero or reading an unknown variable.
+    #[error("{message}")]
+    Runtime {
+        message: String,
+        span: Option<Span>,
+        /// Whether the message already names the user function that was running.
+        in_function: bool
+    },
+    /// Bytecode that would misuse the stack, jump somewhere it shouldn't or refer to something that doesn't exist.
+    #[error("Invalid bytecode: {0}")]
+    InvalidBytecode(String),
+    #[error("Unknown evaluation mode: {0}. Expected \"float\" or \"rational\".")]
+    UnknownMode(String),
+    #[error("Unknown radix: {0}. Expected \"decimal\", \"hex\" or \"binary\".")]
+    UnknownRadix(String)
+}
+
+pub type Result<T> = std::result::Result<T, CalcError>;
+
+impl CalcError {
+    pub fn runtime<T: Into<String>>(message: T) -> Self {
+        Self::Runtime {
+            message: message.into(),
+            span: None,
+            in_function: false
+        }
+    }
+
+    pub fn get_span(&self) -> Option<Span> {
+        match self {
+            Self::Runtime { span, .. } => *span,
+            _ => None
+        }
+    }
+
+    /// Attaches `span` to a runtime error, unless it already has a more specific location.
+    pub fn with_span(self, span: Span) -> Self {
+        match self {
+            Self::Runtime { message, span: None, in_function } => Self::Runtime {
+                message,
+                span: Some(span),
+                in_function
+            },
+            err => err
+        }
+    }
+
+    /// Names the user function `name` in a runtime error, unless a function it called already failed with it.
+    pub fn in_function(self, name: &str) -> Self {
+        match self {
+            Self::Runtime { message, span, in_function: false } => Self::Runtime {
+                message: format!("{} (in function '{}')", message, name),
+                span,
+                in_function: true
+            },
+            err => err
+        }
+    }
+
+    /// Renders the error with a caret underline beneath each location in `source` it refers to.
+    pub fn render(&self, source: &str) -> String {
+        match self {
+            Self::Lex(diagnostics) |
+            Self::Parse(diagnostics) => diagnostics.render(source),
+            Self::Runtime { message, span: Some(span), .. } => Diagnostic::new(message.clone(), *span).render(source),
+            err => err.to_string()
+        }
+    }
+}
+
+//Errors found while emitting bytecode, like a duplicate parameter name, are problems with the input itself
+impl From<Diagnostic> for CalcError {
+    fn from(value: Diagnostic) -> Self {
+        Self::Parse(Diagnostics(vec![value]))
+    }
+}
+
+/// Builds a `CalcError::Runtime` from a format string.
+macro_rules! runtime_error {
+    ($($arg:tt)*) => {
+        $crate::calculator::CalcError::runtime(format!($($arg)*))
+    };
+}
+pub(crate) use runtime_error;
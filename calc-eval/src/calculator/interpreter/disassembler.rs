@@ -0,0 +1,119 @@
+use std::fmt::Write;
+use super::{MethodBuilder, Op};
+
+/// Lists the ops in `method` one per line with their offset, resolved operands and the stack depth after they run,
+/// followed by the bodies of any functions it defines.
+pub fn disassemble(method: &MethodBuilder) -> String {
+    let mut output = String::new();
+    write_method(method, &mut output);
+
+    for function in method.functions.iter() {
+        writeln!(output, "\n{}:", function).unwrap();
+        write_method(&function.method, &mut output);
+    }
+
+    output
+}
+
+fn write_method(method: &MethodBuilder, output: &mut String) {
+    writeln!(output, "offset  stack  op").unwrap();
+
+    let depths = method.get_stack_depths();
+    for (op_idx, op) in method.ops.iter().enumerate() {
+        //Unreachable ops never run, so they have no stack to speak of
+        let depth = match depths[op_idx] {
+            Some(depth) => {
+                let (pops, pushes) = op.get_stack_effect();
+                (depth.saturating_sub(pops) + pushes).to_string()
+            },
+            None => "-".to_owned()
+        };
+
+        let operands = format_operands(method, op);
+        if operands.is_empty() {
+            writeln!(output, "{:>6}  {:>5}  {}", op_idx, depth, op.get_name()).unwrap();
+        }
+        else {
+            writeln!(output, "{:>6}  {:>5}  {} {}", op_idx, depth, op.get_name(), operands).unwrap();
+        }
+    }
+}
+
+fn format_operands(method: &MethodBuilder, op: &Op) -> String {
+    match *op {
+        Op::LdcI8(num) => num.to_string(),
+        Op::LdcF8(num) => format!("{:?}", num),
+        Op::LdcImag(num) => format!("{:?}i", num),
+        Op::LdcBool(val) => val.to_string(),
+        Op::Ldstr(string_idx) => format!("{} ({:?})", string_idx, method.strings[string_idx]),
+        Op::Ldvar(name_idx) |
+        Op::Stvar(name_idx) => format!("{} ({})", name_idx, method.names[name_idx]),
+        Op::Ldarg(arg_idx) |
+        Op::Starg(arg_idx) => match method.parameters.get(arg_idx) {
+            Some(parameter) => format!("{} ({})", arg_idx, parameter),
+            None => arg_idx.to_string()
+        },
+        Op::Defun(function_idx) => format!("{} ({})", function_idx, method.functions[function_idx]),
+        Op::Newvec(count) => format!("{} element(s)", count),
+        Op::Call(name_idx, arity) => format!("{} ({}), {} arg(s)", name_idx, method.names[name_idx], arity),
+        Op::Apply(name_idx, function_idx, arity) => {
+            format!("{} ({}), function {} ({}), {} arg(s)", name_idx, method.names[name_idx], function_idx, method.functions[function_idx], arity)
+        },
+        Op::Unit(unit_idx) |
+        Op::Convert(unit_idx) => format!("{} ({})", unit_idx, method.units[unit_idx]),
+        Op::Br(target) |
+        Op::Brtrue(target) |
+        Op::Brfalse(target) => format!("-> {}", target),
+        _ => String::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calculator::Calculator;
+    use super::*;
+
+    #[test]
+    fn disassemble_should_list_ops_with_operands_and_stack_depth() {
+        let test_cases: &[(&str, &str)] = &[
+            ("1 + x * 2.5", "\
+offset  stack  op
+     0      1  LdcI8 1
+     1      2  Ldvar 0 (x)
+     2      3  LdcF8 2.5
+     3      2  Mul
+     4      1  Add
+"),
+            ("a ? \"yes\" : 3 km in m", "\
+offset  stack  op
+     0      1  Ldvar 0 (a)
+     1      0  Brfalse -> 4
+     2      1  Ldstr 0 (\"yes\")
+     3      1  Br -> 6
+     4      1  LdcI8 3
+     5      1  Unit 0 (km)
+     6      1  Convert 1 (m)
+"),
+            ("f(x, y) = x = max(x, y)", "\
+offset  stack  op
+     0      1  Defun 0 (f(x, y))
+
+f(x, y):
+offset  stack  op
+     0      1  Ldarg 0 (x)
+     1      2  Ldarg 1 (y)
+     2      1  Call 0 (max), 2 arg(s)
+     3      2  Dup
+     4      1  Starg 0 (x)
+"),
+        ];
+
+        let calc = Calculator::new();
+
+        for &(input, expected_output) in test_cases {
+            let method = calc.emit(input).unwrap();
+
+            assert_eq!(disassemble(&method), expected_output, "for input {:?}", input);
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use std::{collections::HashMap, rc::Rc};
+use crate::calculator::error::{runtime_error, Result};
+use super::{UserFunction, Value};
+
+/// Variables every environment starts with, which expressions can read but not assign.
+const CONSTANTS: &[(&str, f64)] = &[
+    ("pi", std::f64::consts::PI),
+    ("e", std::f64::consts::E)
+];
+
+#[derive(Clone)]
+pub struct Environment {
+    variables: HashMap<String, Value>,
+    functions: HashMap<String, Rc<UserFunction>>,
+    /// Looked up for anything this environment doesn't define itself. Setting a variable never changes it.
+    parent: Option<Rc<Environment>>
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        let mut environment = Self {
+            variables: HashMap::new(),
+            functions: HashMap::new(),
+            parent: None
+        };
+
+        for &(name, val) in CONSTANTS {
+            environment.set_variable(name, Value::Float(val));
+        }
+
+        environment
+    }
+
+    /// An empty environment layered over `parent`, which is cheaper than cloning `parent` when only a few variables
+    /// are set before it's thrown away.
+    pub fn with_parent(parent: Rc<Environment>) -> Self {
+        Self {
+            variables: HashMap::new(),
+            functions: HashMap::new(),
+            parent: Some(parent)
+        }
+    }
+
+    pub fn get_variable(&self, name: &str) -> Option<&Value> {
+        self.variables.get(name).or_else(|| self.parent.as_ref()?.get_variable(name))
+    }
+
+    pub fn set_variable(&mut self, name: &str, value: Value) {
+        self.variables.insert(name.to_owned(), value);
+    }
+
+    pub fn is_constant(name: &str) -> bool {
+        CONSTANTS.iter().any(|&(constant, _)| constant == name)
+    }
+
+    /// Sets a variable the way an assignment does, which can't replace a built-in constant like `pi`.
+    pub fn assign_variable(&mut self, name: &str, value: Value) -> Result<()> {
+        if Self::is_constant(name) {
+            return Err(runtime_error!("Cannot redefine built-in constant '{}'", name));
+        }
+        self.set_variable(name, value);
+        Ok(())
+    }
+
+    /// Returns every variable, sorted by name.
+    pub fn get_variables(&self) -> Vec<(&str, &Value)> {
+        let mut variables = self.variables.iter()
+            .map(|(name, value)| (name.as_str(), value))
+            .collect::<Vec<_>>();
+        if let Some(parent) = &self.parent {
+            variables.extend(parent.get_variables().into_iter().filter(|(name, _)| !self.variables.contains_key(*name)));
+        }
+        variables.sort_by_key(|&(name, _)| name);
+        variables
+    }
+
+    pub fn get_function(&self, name: &str) -> Option<&Rc<UserFunction>> {
+        self.functions.get(name).or_else(|| self.parent.as_ref()?.get_function(name))
+    }
+
+    pub fn set_function(&mut self, function: Rc<UserFunction>) {
+        self.functions.insert(function.name.clone(), function);
+    }
+
+    /// Returns every user-defined function, sorted by name.
+    pub fn get_functions(&self) -> Vec<&Rc<UserFunction>> {
+        let mut functions = self.functions.values().collect::<Vec<_>>();
+        if let Some(parent) = &self.parent {
+            functions.extend(parent.get_functions().into_iter().filter(|function| !self.functions.contains_key(&function.name)));
+        }
+        functions.sort_by(|lhs, rhs| lhs.name.cmp(&rhs.name));
+        functions
+    }
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn with_parent_should_read_through_without_changing_the_parent() {
+        let mut parent = Environment::new();
+        parent.set_variable("x", Value::Integer(1));
+        parent.set_variable("y", Value::Integer(2));
+        let parent = Rc::new(parent);
+
+        let mut child = Environment::with_parent(parent.clone());
+        child.set_variable("x", Value::Integer(10));
+        child.set_variable("z", Value::Integer(30));
+
+        assert_eq!(child.get_variable("x"), Some(&Value::Integer(10)));
+        assert_eq!(child.get_variable("y"), Some(&Value::Integer(2)));
+        assert_eq!(child.get_variable("pi"), Some(&Value::Float(std::f64::consts::PI)));
+        assert_eq!(parent.get_variable("x"), Some(&Value::Integer(1)));
+        assert_eq!(parent.get_variable("z"), None);
+
+        let names = child.get_variables().into_iter().map(|(name, _)| name).collect::<Vec<_>>();
+        assert_eq!(names, ["e", "pi", "x", "y", "z"]);
+        assert!(child.assign_variable("e", Value::Integer(3)).is_err());
+    }
+}
@@ -0,0 +1,43 @@
+use std::{fmt::Display, str::FromStr};
+use crate::calculator::error::{CalcError, Result};
+use super::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EvaluationMode {
+    /// Integers are exact and everything else is an `f64`.
+    #[default]
+    Float,
+    /// Numeric literals are loaded as exact rationals, so `0.1 + 0.2` is exactly `0.3`.
+    Rational
+}
+
+impl EvaluationMode {
+    /// Converts a numeric literal into the representation this mode computes with.
+    pub fn load_constant(self, val: Value) -> Result<Value> {
+        match self {
+            Self::Float => Ok(val),
+            Self::Rational => Ok(Value::Rational(val.to_rational()?))
+        }
+    }
+}
+
+impl Display for EvaluationMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Float => write!(f, "float"),
+            Self::Rational => write!(f, "rational")
+        }
+    }
+}
+
+impl FromStr for EvaluationMode {
+    type Err = CalcError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "float" => Ok(Self::Float),
+            "rational" => Ok(Self::Rational),
+            _ => Err(CalcError::UnknownMode(s.to_owned()))
+        }
+    }
+}
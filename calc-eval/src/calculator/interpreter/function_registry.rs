@@ -0,0 +1,176 @@
+use std::{cmp::Ordering, collections::HashMap};
+use num_complex::Complex64;
+use num_rational::BigRational;
+use num_traits::Signed;
+use crate::calculator::error::{runtime_error, Result};
+use super::{linear_algebra, Value};
+
+pub struct BuiltinFunction {
+    pub min_arity: usize,
+    pub max_arity: Option<usize>,
+    pub body: fn(&[Value]) -> Result<Value>
+}
+
+impl BuiltinFunction {
+    pub fn check_arity(&self, name: &str, arity: usize) -> Result<()> {
+        match self.max_arity {
+            Some(max_arity) if max_arity == self.min_arity && arity != max_arity => {
+                Err(runtime_error!("Function '{}' expects {} argument(s), but {} were given.", name, max_arity, arity))
+            },
+            Some(max_arity) if arity < self.min_arity || arity > max_arity => {
+                Err(runtime_error!("Function '{}' expects between {} and {} arguments, but {} were given.", name, self.min_arity, max_arity, arity))
+            },
+            None if arity < self.min_arity => {
+                Err(runtime_error!("Function '{}' expects at least {} argument(s), but {} were given.", name, self.min_arity, arity))
+            },
+            _ => Ok(())
+        }
+    }
+}
+
+pub struct FunctionRegistry {
+    functions: HashMap<String, BuiltinFunction>
+}
+
+impl FunctionRegistry {
+    pub fn new() -> Self {
+        Self {
+            functions: HashMap::new()
+        }
+    }
+
+    pub fn with_standard_library() -> Self {
+        let mut registry = Self::new();
+
+        //The square root of a negative number is imaginary rather than NaN
+        registry.register_fixed("sqrt", 1, |args| match &args[0] {
+            Value::Complex(val) => Ok(Value::from(val.sqrt())),
+            val => match val.to_f64()? {
+                num if num < 0.0 => Ok(Value::from(Complex64::new(num, 0.0).sqrt())),
+                num => Ok(Value::Float(num.sqrt()))
+            }
+        });
+        registry.register_fixed("cbrt", 1, |args| Ok(Value::Float(args[0].to_f64()?.cbrt())));
+        registry.register_fixed("exp", 1, |args| match &args[0] {
+            Value::Complex(val) => Ok(Value::from(val.exp())),
+            val => Ok(Value::Float(val.to_f64()?.exp()))
+        });
+        registry.register_fixed("ln", 1, |args| logarithm(&args[0], f64::ln, 1.0));
+        registry.register_fixed("log2", 1, |args| logarithm(&args[0], f64::log2, std::f64::consts::LN_2));
+        registry.register_fixed("log10", 1, |args| logarithm(&args[0], f64::log10, std::f64::consts::LN_10));
+        registry.register_fixed("sin", 1, |args| Ok(Value::Float(args[0].to_f64()?.sin())));
+        registry.register_fixed("cos", 1, |args| Ok(Value::Float(args[0].to_f64()?.cos())));
+        registry.register_fixed("tan", 1, |args| Ok(Value::Float(args[0].to_f64()?.tan())));
+        registry.register_fixed("asin", 1, |args| Ok(Value::Float(args[0].to_f64()?.asin())));
+        registry.register_fixed("acos", 1, |args| Ok(Value::Float(args[0].to_f64()?.acos())));
+        registry.register_fixed("atan", 1, |args| Ok(Value::Float(args[0].to_f64()?.atan())));
+        registry.register_fixed("sinh", 1, |args| Ok(Value::Float(args[0].to_f64()?.sinh())));
+        registry.register_fixed("cosh", 1, |args| Ok(Value::Float(args[0].to_f64()?.cosh())));
+        registry.register_fixed("tanh", 1, |args| Ok(Value::Float(args[0].to_f64()?.tanh())));
+        registry.register_fixed("atan2", 2, |args| Ok(Value::Float(args[0].to_f64()?.atan2(args[1].to_f64()?))));
+        registry.register_fixed("hypot", 2, |args| Ok(Value::Float(args[0].to_f64()?.hypot(args[1].to_f64()?))));
+        registry.register_fixed("pow", 2, |args| args[0].pow(&args[1]));
+
+        //Rounding functions and abs keep integers and rationals exact
+        registry.register_fixed("abs", 1, |args| match &args[0] {
+            Value::Integer(val) => Ok(Value::Integer(val.checked_abs().ok_or(runtime_error!("Integer overflow"))?)),
+            Value::Rational(val) => Ok(Value::Rational(val.abs())),
+            Value::Complex(val) => Ok(Value::Float(val.norm())),
+            val => Ok(Value::Float(val.to_f64()?.abs()))
+        });
+        //The angle from the positive real axis, so negative reals have an argument of pi
+        registry.register_fixed("arg", 1, |args| Ok(Value::Float(args[0].to_complex()?.arg())));
+        registry.register_fixed("floor", 1, |args| round_with(&args[0], f64::floor, BigRational::floor));
+        registry.register_fixed("ceil", 1, |args| round_with(&args[0], f64::ceil, BigRational::ceil));
+        registry.register_fixed("round", 1, |args| round_with(&args[0], f64::round, BigRational::round));
+        registry.register_fixed("trunc", 1, |args| round_with(&args[0], f64::trunc, BigRational::trunc));
+
+        registry.register_fixed("matmul", 2, |args| linear_algebra::matmul(&args[0], &args[1]));
+        registry.register_fixed("transpose", 1, |args| linear_algebra::transpose(&args[0]));
+        registry.register_fixed("det", 1, |args| linear_algebra::determinant(&args[0]));
+        registry.register_fixed("inverse", 1, |args| linear_algebra::inverse(&args[0]));
+
+        //log(x) is the common (base 10) logarithm, log(x, b) uses an explicit base
+        registry.register("log", BuiltinFunction {
+            min_arity: 1,
+            max_arity: Some(2),
+            body: |args| match args.get(1) {
+                Some(base) => {
+                    let base = base.to_f64()?;
+                    logarithm(&args[0], |num| num.log(base), base.ln())
+                },
+                None => logarithm(&args[0], f64::log10, std::f64::consts::LN_10)
+            }
+        });
+
+        registry.register("min", BuiltinFunction {
+            min_arity: 1,
+            max_arity: None,
+            body: |args| select_extreme(args, Ordering::Less)
+        });
+        registry.register("max", BuiltinFunction {
+            min_arity: 1,
+            max_arity: None,
+            body: |args| select_extreme(args, Ordering::Greater)
+        });
+
+        registry
+    }
+
+    pub fn register(&mut self, name: &str, function: BuiltinFunction) {
+        self.functions.insert(name.to_owned(), function);
+    }
+
+    fn register_fixed(&mut self, name: &str, arity: usize, body: fn(&[Value]) -> Result<Value>) {
+        self.register(name, BuiltinFunction {
+            min_arity: arity,
+            max_arity: Some(arity),
+            body
+        });
+    }
+
+    pub fn get(&self, name: &str) -> Option<&BuiltinFunction> {
+        self.functions.get(name)
+    }
+}
+
+impl Default for FunctionRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn round_with(val: &Value, round: fn(f64) -> f64, round_rational: fn(&BigRational) -> BigRational) -> Result<Value> {
+    match val {
+        Value::Integer(_) => Ok(val.clone()),
+        Value::Rational(val) => Ok(Value::Rational(round_rational(val))),
+        _ => Ok(Value::Float(round(val.to_f64()?)))
+    }
+}
+
+//Like sqrt, logarithms of negative and complex numbers are complex. Those are taken with the natural logarithm and
+//divided by `ln_base`, while real ones use `real_log` so they're as precise as before
+fn logarithm(val: &Value, real_log: impl Fn(f64) -> f64, ln_base: f64) -> Result<Value> {
+    match val {
+        Value::Complex(val) => Ok(Value::from(val.ln() / ln_base)),
+        val => match val.to_f64()? {
+            num if num < 0.0 => Ok(Value::from(Complex64::new(num, 0.0).ln() / ln_base)),
+            num => Ok(Value::Float(real_log(num)))
+        }
+    }
+}
+
+//Returns the argument itself rather than a converted copy, so min(1, 2.5) is still the integer 1
+fn select_extreme(args: &[Value], wanted: Ordering) -> Result<Value> {
+    let mut selected = &args[0];
+
+    for arg in args[1..].iter() {
+        match arg.compare(selected, if wanted == Ordering::Less { "min" } else { "max" })? {
+            Some(ordering) if ordering == wanted => selected = arg,
+            None => return Ok(Value::Float(f64::NAN)),
+            _ => { }
+        }
+    }
+
+    Ok(selected.clone())
+}
@@ -0,0 +1,291 @@
+use crate::calculator::error::{runtime_error, Result};
+
+/// Built-ins whose first argument is an expression and whose second is the variable it's a function of, as in
+/// `solve(x ^ 2 - 2, x, 1)`, along with the least and most arguments each one takes in all.
+const HIGHER_ORDER_FUNCTIONS: [(&str, usize, usize); 2] = [
+    ("solve", 3, 4),
+    ("integrate", 4, 4)
+];
+
+const MAX_NEWTON_ITERATIONS: usize = 100;
+const MAX_BISECTION_ITERATIONS: usize = 2000;
+/// How close successive estimates of a root must be, relative to the root, for Newton's method to stop.
+const ROOT_TOLERANCE: f64 = 1e-12;
+/// The step used to estimate the slope for Newton's method, relative to the point it's taken at.
+const SLOPE_STEP: f64 = 1e-7;
+
+const INTEGRATION_TOLERANCE: f64 = 1e-10;
+const MAX_INTEGRATION_DEPTH: usize = 50;
+const MAX_INTEGRATION_EVALUATIONS: usize = 1_000_000;
+
+pub fn is_higher_order_function(name: &str) -> bool {
+    HIGHER_ORDER_FUNCTIONS.iter().any(|&(function_name, _, _)| function_name == name)
+}
+
+/// Checks how many arguments `name` is called with, counting the expression and its variable.
+pub fn check_higher_order_arity(name: &str, arity: usize) -> Result<()> {
+    let Some(&(_, min_arity, max_arity)) = HIGHER_ORDER_FUNCTIONS.iter().find(|&&(function_name, _, _)| function_name == name) else {
+        return Err(runtime_error!("Unknown function: {}", name));
+    };
+
+    if min_arity == max_arity && arity != max_arity {
+        Err(runtime_error!("Function '{}' expects {} argument(s), but {} were given.", name, max_arity, arity))
+    }
+    else if arity < min_arity || arity > max_arity {
+        Err(runtime_error!("Function '{}' expects between {} and {} arguments, but {} were given.", name, min_arity, max_arity, arity))
+    }
+    else {
+        Ok(())
+    }
+}
+
+/// Runs the higher-order built-in `name`, which calls `function` with the values of the variable it needs. `args` are
+/// the arguments after the expression and its variable.
+///
+/// - `solve(expr, x, guess)` finds a root with Newton's method, starting from `guess`.
+/// - `solve(expr, x, lower, upper)` finds a root by bisection, and needs `expr` to change sign between the bounds.
+/// - `integrate(expr, x, lower, upper)` integrates with adaptive Simpson quadrature.
+///
+/// Anything that fails to converge is an error rather than a NaN.
+pub fn apply_higher_order_function(name: &str, function: &mut dyn FnMut(f64) -> Result<f64>, args: &[f64]) -> Result<f64> {
+    match (name, args) {
+        ("solve", &[guess]) => solve_newton(function, guess),
+        ("solve", &[lower, upper]) => solve_bisection(function, lower, upper),
+        ("integrate", &[lower, upper]) => integrate_simpson(function, lower, upper),
+        _ => {
+            check_higher_order_arity(name, args.len() + 2)?;
+            Err(runtime_error!("Function '{}' can't be called with {} argument(s)", name, args.len() + 2))
+        }
+    }
+}
+
+fn evaluate(function: &mut dyn FnMut(f64) -> Result<f64>, x: f64) -> Result<f64> {
+    let val = function(x)?;
+    if !val.is_finite() {
+        return Err(runtime_error!("The expression is {} at {}", val, x));
+    }
+    Ok(val)
+}
+
+fn solve_newton(function: &mut dyn FnMut(f64) -> Result<f64>, guess: f64) -> Result<f64> {
+    if !guess.is_finite() {
+        return Err(runtime_error!("solve needs a finite starting guess, found {}", guess));
+    }
+
+    let mut x = guess;
+    for _ in 0..MAX_NEWTON_ITERATIONS {
+        let val = evaluate(function, x)?;
+        if val == 0.0 {
+            return Ok(x);
+        }
+
+        let step = SLOPE_STEP * x.abs().max(1.0);
+        let slope = (evaluate(function, x + step)? - evaluate(function, x - step)?) / (2.0 * step);
+        if slope == 0.0 {
+            return Err(runtime_error!("solve stalled at {}, where the expression is flat", x));
+        }
+
+        let next_x = x - val / slope;
+        if !next_x.is_finite() {
+            return Err(runtime_error!("solve diverged starting from {}", guess));
+        }
+
+        let tolerance = ROOT_TOLERANCE * next_x.abs().max(1.0);
+        if (next_x - x).abs() <= tolerance {
+            //Successive estimates can also bunch up near a minimum that never reaches zero, so make sure the next step
+            //would be tiny as well
+            let residual = evaluate(function, next_x)?;
+            if (residual / slope).abs() <= tolerance.sqrt() {
+                return Ok(next_x);
+            }
+            return Err(runtime_error!("solve didn't find a root near {}", guess));
+        }
+        x = next_x;
+    }
+
+    Err(runtime_error!("solve didn't converge after {} iterations starting from {}", MAX_NEWTON_ITERATIONS, guess))
+}
+
+fn solve_bisection(function: &mut dyn FnMut(f64) -> Result<f64>, mut lower: f64, mut upper: f64) -> Result<f64> {
+    if !lower.is_finite() || !upper.is_finite() {
+        return Err(runtime_error!("solve needs finite bounds, found {} and {}", lower, upper));
+    }
+
+    let mut lower_val = evaluate(function, lower)?;
+    let upper_val = evaluate(function, upper)?;
+    if lower_val == 0.0 {
+        return Ok(lower);
+    }
+    if upper_val == 0.0 {
+        return Ok(upper);
+    }
+    if lower_val.signum() == upper_val.signum() {
+        return Err(runtime_error!("solve needs the expression to change sign between {} and {}", lower, upper));
+    }
+
+    for _ in 0..MAX_BISECTION_ITERATIONS {
+        let mid = lower + (upper - lower) / 2.0;
+        //The bounds are as close as floats can get
+        if mid == lower || mid == upper {
+            return Ok(mid);
+        }
+
+        let mid_val = evaluate(function, mid)?;
+        if mid_val == 0.0 {
+            return Ok(mid);
+        }
+        if mid_val.signum() == lower_val.signum() {
+            lower = mid;
+            lower_val = mid_val;
+        }
+        else {
+            upper = mid;
+        }
+    }
+
+    Err(runtime_error!("solve didn't converge after {} iterations", MAX_BISECTION_ITERATIONS))
+}
+
+/// Part of the range being integrated, with the value of the integrand at its ends and middle.
+#[derive(Clone, Copy)]
+struct Interval {
+    lower: f64,
+    upper: f64,
+    lower_val: f64,
+    mid_val: f64,
+    upper_val: f64
+}
+
+impl Interval {
+    fn get_mid(&self) -> f64 {
+        self.lower + (self.upper - self.lower) / 2.0
+    }
+
+    fn simpson(&self) -> f64 {
+        (self.upper - self.lower) / 6.0 * (self.lower_val + 4.0 * self.mid_val + self.upper_val)
+    }
+}
+
+struct Integrator<'a> {
+    function: &'a mut dyn FnMut(f64) -> Result<f64>,
+    evaluations: usize
+}
+
+impl Integrator<'_> {
+    fn evaluate(&mut self, x: f64) -> Result<f64> {
+        self.evaluations += 1;
+        if self.evaluations > MAX_INTEGRATION_EVALUATIONS {
+            return Err(runtime_error!("integrate didn't converge after {} evaluations", MAX_INTEGRATION_EVALUATIONS));
+        }
+        evaluate(self.function, x)
+    }
+
+    fn split(&mut self, interval: Interval) -> Result<(Interval, Interval)> {
+        let mid = interval.get_mid();
+        let left = Interval {
+            lower: interval.lower,
+            upper: mid,
+            lower_val: interval.lower_val,
+            mid_val: self.evaluate(interval.lower + (mid - interval.lower) / 2.0)?,
+            upper_val: interval.mid_val
+        };
+        let right = Interval {
+            lower: mid,
+            upper: interval.upper,
+            lower_val: interval.mid_val,
+            mid_val: self.evaluate(mid + (interval.upper - mid) / 2.0)?,
+            upper_val: interval.upper_val
+        };
+        Ok((left, right))
+    }
+
+    /// Keeps halving `interval` until Simpson's rule gives the same area for it whole as for its two halves.
+    fn integrate(&mut self, interval: Interval, whole: f64, tolerance: f64, depth: usize) -> Result<f64> {
+        let (left, right) = self.split(interval)?;
+        let (left_area, right_area) = (left.simpson(), right.simpson());
+        let error = left_area + right_area - whole;
+
+        if error.abs() <= 15.0 * tolerance {
+            return Ok(left_area + right_area + error / 15.0);
+        }
+        if depth == 0 {
+            return Err(runtime_error!("integrate didn't converge between {} and {}", interval.lower, interval.upper));
+        }
+
+        Ok(self.integrate(left, left_area, tolerance / 2.0, depth - 1)? + self.integrate(right, right_area, tolerance / 2.0, depth - 1)?)
+    }
+}
+
+fn integrate_simpson(function: &mut dyn FnMut(f64) -> Result<f64>, lower: f64, upper: f64) -> Result<f64> {
+    if !lower.is_finite() || !upper.is_finite() {
+        return Err(runtime_error!("integrate needs finite bounds, found {} and {}", lower, upper));
+    }
+    if lower == upper {
+        return Ok(0.0);
+    }
+
+    let mut integrator = Integrator {
+        function,
+        evaluations: 0
+    };
+    let interval = Interval {
+        lower,
+        upper,
+        lower_val: integrator.evaluate(lower)?,
+        mid_val: integrator.evaluate(lower + (upper - lower) / 2.0)?,
+        upper_val: integrator.evaluate(upper)?
+    };
+
+    //The tolerance scales with the integrand, so large values don't demand more precision than floats have
+    let whole = interval.simpson();
+    let tolerance = INTEGRATION_TOLERANCE * whole.abs().max(1.0);
+    integrator.integrate(interval, whole, tolerance, MAX_INTEGRATION_DEPTH)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_higher_order_function_should_converge_or_fail() {
+        type TestFunction = fn(f64) -> f64;
+
+        let test_cases: &[(&str, TestFunction, &[f64], f64)] = &[
+            ("solve", |x| x * x - 2.0, &[1.0], std::f64::consts::SQRT_2),
+            ("solve", |x| x * x - 2.0, &[-1.0], -std::f64::consts::SQRT_2),
+            ("solve", |x| x.cos() - x, &[0.0], 0.7390851332151607),
+            ("solve", |x| 1e12 * (x - 3.0), &[0.0], 3.0),
+            ("solve", |x| x * x * x - x - 1.0, &[1.0, 2.0], 1.324717957244746),
+            ("solve", |x| x.sin(), &[3.0, 4.0], std::f64::consts::PI),
+            ("integrate", f64::sin, &[0.0, std::f64::consts::PI], 2.0),
+            ("integrate", |x| x * x, &[0.0, 3.0], 9.0),
+            ("integrate", |x| x * x, &[3.0, 0.0], -9.0),
+            ("integrate", |x| (-x * x).exp(), &[-10.0, 10.0], std::f64::consts::PI.sqrt()),
+            ("integrate", |x| 1e6 * x.sqrt(), &[0.0, 1.0], 2e6 / 3.0),
+        ];
+
+        for &(name, function, args, expected) in test_cases {
+            let result = apply_higher_order_function(name, &mut |x| Ok(function(x)), args).unwrap();
+            assert!((result - expected).abs() <= 1e-9 * expected.abs().max(1.0), "{}{:?} gave {}, expected {}", name, args, result, expected);
+        }
+
+        let test_cases: &[(&str, TestFunction, &[f64], &str)] = &[
+            ("solve", |x| x * x + 1.0, &[1.0], "solve didn't converge after 100 iterations starting from 1"),
+            ("solve", |_| 1.0, &[1.0], "solve stalled at 1, where the expression is flat"),
+            ("solve", |x| x * x - 2.0, &[2.0, 3.0], "solve needs the expression to change sign between 2 and 3"),
+            ("solve", |x| 1.0 / x, &[1.0, f64::INFINITY], "solve needs finite bounds, found 1 and inf"),
+            ("solve", |x| x.sqrt() + 1.0, &[-1.0], "The expression is NaN at -1"),
+            ("integrate", |x| 1.0 / x, &[0.0, 1.0], "The expression is inf at 0"),
+            ("integrate", |x| (1.0 / x).sin(), &[-1.0, 1.0], "The expression is NaN at 0"),
+            ("integrate", |x| if x < 0.5 { 0.0 } else { 1e300 }, &[0.0, 1.0], "integrate didn't converge between 0.4999999999999991 and 0.5"),
+        ];
+
+        for &(name, function, args, expected) in test_cases {
+            let err = apply_higher_order_function(name, &mut |x| Ok(function(x)), args).unwrap_err();
+            assert_eq!(err.to_string(), expected, "for {}{:?}", name, args);
+        }
+
+        assert_eq!(check_higher_order_arity("solve", 2).unwrap_err().to_string(), "Function 'solve' expects between 3 and 4 arguments, but 2 were given.");
+        assert_eq!(check_higher_order_arity("integrate", 3).unwrap_err().to_string(), "Function 'integrate' expects 4 argument(s), but 3 were given.");
+    }
+}
@@ -1,59 +1,296 @@
-use anyhow::*;
-use super::MethodBuilder;
+use std::rc::Rc;
+use num_complex::Complex64;
+use crate::calculator::{
+    error::{runtime_error, CalcError, Result}
+};
+use super::{apply_higher_order_function, is_higher_order_function, verify, Environment, EvaluationMode, FunctionRegistry, MethodBuilder, Quantity, UserFunction, Value};
 
-pub struct Interpreter { }
+/// How deeply user functions may call each other before evaluation is abandoned.
+pub const MAX_CALL_DEPTH: usize = 1000;
+
+/// The state of one invocation of a method: the entry method, or the body of a user function.
+struct CallFrame {
+    function: Option<Rc<UserFunction>>,
+    args: Vec<Value>,
+    ip: usize,
+    /// The stack height when the frame was entered, which it must leave exactly one value above.
+    stack_base: usize
+}
+
+/// A user function call requested by an op, along with the arguments it was called with.
+type PendingCall = (Rc<UserFunction>, Vec<Value>);
+
+pub struct Interpreter {
+    functions: FunctionRegistry,
+    mode: EvaluationMode
+}
 
 impl Interpreter {
     pub fn new() -> Self {
-        Self { }
-    }
-
-    pub fn evaluate_method(&self, method: &MethodBuilder) -> Result<f64> {
-        let mut stack = vec![];
-
-        for &op in method.ops.iter() {
-            match op {
-                super::Op::LdcF8(num) => {
-                    stack.push(num);
-                },
-                super::Op::Neg => {
-                    let val = -stack.pop().ok_or(anyhow!("Stack underflow"))?;
-                    stack.push(val);
-                },
-                super::Op::Mul => {
-                    let val1 = stack.pop().ok_or(anyhow!("Stack underflow"))?;
-                    let val2 = stack.pop().ok_or(anyhow!("Stack underflow"))?;
-                    stack.push(val2 * val1);
-                },
-                super::Op::Div => {
-                    let val1 = stack.pop().ok_or(anyhow!("Stack underflow"))?;
-                    let val2 = stack.pop().ok_or(anyhow!("Stack underflow"))?;
-                    stack.push(val2 / val1);
-                },
-                super::Op::Rem => {
-                    let val1 = stack.pop().ok_or(anyhow!("Stack underflow"))?;
-                    let val2 = stack.pop().ok_or(anyhow!("Stack underflow"))?;
-                    stack.push(val2 % val1);
-                },
-                super::Op::Add => {
-                    let val1 = stack.pop().ok_or(anyhow!("Stack underflow"))?;
-                    let val2 = stack.pop().ok_or(anyhow!("Stack underflow"))?;
-                    stack.push(val2 + val1);
-                },
-                super::Op::Sub => {
-                    let val1 = stack.pop().ok_or(anyhow!("Stack underflow"))?;
-                    let val2 = stack.pop().ok_or(anyhow!("Stack underflow"))?;
-                    stack.push(val2 - val1);
+        Self {
+            functions: FunctionRegistry::with_standard_library(),
+            mode: EvaluationMode::default()
+        }
+    }
+
+    pub fn get_mode(&self) -> EvaluationMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: EvaluationMode) {
+        self.mode = mode;
+    }
+
+    /// Verifies `method` before running it, so malformed bytecode is rejected without running any of it.
+    pub fn evaluate_method(&self, method: &MethodBuilder, environment: &mut Environment) -> Result<Value> {
+        let max_stack_depth = verify(method)?;
+        self.evaluate_verified_method(method, max_stack_depth, environment)
+    }
+
+    /// Runs a method that `verify` has accepted, which means no op needs to check the stack before using it.
+    /// `max_stack_depth` is what `verify` returned.
+    pub(crate) fn evaluate_verified_method(&self, method: &MethodBuilder, max_stack_depth: usize, environment: &mut Environment) -> Result<Value> {
+        self.run(method, None, vec![], Vec::with_capacity(max_stack_depth), environment, 0)
+    }
+
+    /// Calls the built-in or user function `name` the way a `Call` op would, for callers outside the bytecode.
+    /// Errors raised inside a user function are left for the caller to locate.
+    pub(crate) fn call_function(&self, name: &str, args: Vec<Value>, environment: &mut Environment) -> Result<Value> {
+        if let Some(function) = self.functions.get(name) {
+            function.check_arity(name, args.len())?;
+            return (function.body)(&args);
+        }
+
+        //The caller counts as a frame, so recursion is cut off at the same depth as it is in bytecode
+        let function = Self::get_user_function(name, args.len(), environment)?.clone();
+        self.run(&function.method, Some(function.clone()), args, vec![], environment, 1)
+    }
+
+    /// Adds `function` to `environment` the way a `Defun` op would, returning the value the op leaves on the stack.
+    pub(crate) fn define_function(&self, function: &Rc<UserFunction>, environment: &mut Environment) -> Result<Value> {
+        if self.functions.get(&function.name).is_some() || is_higher_order_function(&function.name) {
+            return Err(runtime_error!("Cannot redefine built-in function '{}'", function.name));
+        }
+
+        environment.set_function(function.clone());
+        Ok(Value::String(function.to_string()))
+    }
+
+    /// Runs the higher-order built-in `name` over `function`, whose first parameter is the variable the built-in varies
+    /// and whose other parameters are filled in by `captured`. `outer_depth` is how many calls are already in progress.
+    pub(crate) fn apply_function(&self, name: &str, function: &Rc<UserFunction>, captured: Vec<Value>, args: &[Value], environment: &mut Environment, outer_depth: usize) -> Result<Value> {
+        if outer_depth >= MAX_CALL_DEPTH {
+            return Err(runtime_error!("Maximum call depth of {} exceeded", MAX_CALL_DEPTH));
+        }
+
+        let args = args.iter().map(Value::to_f64).collect::<Result<Vec<_>>>()?;
+        let mut evaluate = |x: f64| {
+            let mut function_args = Vec::with_capacity(captured.len() + 1);
+            function_args.push(Value::Float(x));
+            function_args.extend(captured.iter().cloned());
+            self.run(&function.method, Some(function.clone()), function_args, vec![], environment, outer_depth + 1)?.to_f64()
+        };
+
+        Ok(Value::Float(apply_higher_order_function(name, &mut evaluate, &args)?))
+    }
+
+    pub(crate) fn load_variable(name: &str, environment: &Environment) -> Result<Value> {
+        environment.get_variable(name)
+            .cloned()
+            .ok_or_else(|| runtime_error!("Unknown variable: {}", name))
+    }
+
+    fn get_user_function<'a>(name: &str, arity: usize, environment: &'a Environment) -> Result<&'a Rc<UserFunction>> {
+        let function = environment.get_function(name).ok_or_else(|| runtime_error!("Unknown function: {}", name))?;
+        if function.parameters.len() != arity {
+            return Err(runtime_error!("Function '{}' expects {} argument(s), but {} were given.", name, function.parameters.len(), arity));
+        }
+        Ok(function)
+    }
+
+    /// Runs `method`, or the body of `function` with `args` when one is given, until it returns. `outer_depth` is how
+    /// many calls are already in progress outside this one.
+    fn run(&self, method: &MethodBuilder, function: Option<Rc<UserFunction>>, args: Vec<Value>, mut stack: Vec<Value>, environment: &mut Environment, outer_depth: usize) -> Result<Value> {
+        let mut frames = vec![CallFrame {
+            function,
+            args,
+            ip: 0,
+            stack_base: 0
+        }];
+
+        'frames: loop {
+            let function = frames.last().unwrap().function.clone();
+            let frame_method = function.as_deref().map_or(method, |function| &function.method);
+
+            loop {
+                let call_depth = outer_depth + frames.len();
+                let frame = frames.last_mut().unwrap();
+                if frame.ip >= frame_method.ops.len() {
+                    break;
                 }
+                let op_idx = frame.ip;
+                frame.ip += 1;
+
+                match self.execute_op(frame_method, op_idx, environment, &mut stack, frame, call_depth) {
+                    Result::Ok(None) => { },
+                    Result::Ok(Some((function, args))) => {
+                        if outer_depth + frames.len() > MAX_CALL_DEPTH {
+                            let err = runtime_error!("Maximum call depth of {} exceeded", MAX_CALL_DEPTH);
+                            return Err(Self::locate_error(err, method, &frames));
+                        }
+
+                        frames.push(CallFrame {
+                            function: Some(function),
+                            args,
+                            ip: 0,
+                            stack_base: stack.len()
+                        });
+                        continue 'frames;
+                    },
+                    Err(err) => return Err(Self::locate_error(err, method, &frames))
+                }
+            }
+
+            let frame = frames.pop().unwrap();
+            let retval = pop(&mut stack);
+            debug_assert_eq!(stack.len(), frame.stack_base);
+
+            if frames.is_empty() {
+                return Ok(retval);
             }
+            stack.push(retval);
         }
+    }
 
-        let retval = stack.pop().ok_or(anyhow!("Stack underflow"))?;
-        if stack.len() != 0 {
-            Err(anyhow!("Somehow the stack had multiple values before returning"))
+    /// Points an error at the op in the entry method that was running, since spans inside a function body refer to
+    /// the source it was defined in rather than the one being evaluated. The expression passed to a higher-order
+    /// built-in runs as a function too, but it's named after the built-in, so it isn't mentioned.
+    fn locate_error(err: CalcError, method: &MethodBuilder, frames: &[CallFrame]) -> CalcError {
+        let err = match &frames.last().unwrap().function {
+            Some(function) if !is_higher_order_function(&function.name) => err.in_function(&function.name),
+            _ => err
+        };
+        //A function called from outside the bytecode has no entry method to point into, so its caller locates the error
+        if frames[0].function.is_some() {
+            return err;
         }
-        else {
-            Ok(retval)
+
+        match method.get_span(frames[0].ip - 1) {
+            Some(span) => err.with_span(span),
+            None => err
         }
     }
+
+    fn execute_op(&self, method: &MethodBuilder, op_idx: usize, environment: &mut Environment, stack: &mut Vec<Value>, frame: &mut CallFrame, call_depth: usize) -> Result<Option<PendingCall>> {
+        match method.ops[op_idx] {
+            super::Op::LdcI8(num) => {
+                stack.push(self.mode.load_constant(Value::Integer(num))?);
+            },
+            super::Op::LdcF8(num) => {
+                stack.push(self.mode.load_constant(Value::Float(num))?);
+            },
+            super::Op::LdcImag(num) => {
+                stack.push(Value::from(Complex64::new(0.0, num)));
+            },
+            super::Op::LdcBool(val) => {
+                stack.push(Value::Boolean(val));
+            },
+            super::Op::Ldstr(string_idx) => {
+                stack.push(Value::String(method.strings[string_idx].clone()));
+            },
+            super::Op::Ldvar(name_idx) => {
+                stack.push(Self::load_variable(&method.names[name_idx], environment)?);
+            },
+            super::Op::Stvar(name_idx) => {
+                let val = pop(stack);
+                environment.assign_variable(&method.names[name_idx], val)?;
+            },
+            super::Op::Ldarg(arg_idx) => {
+                stack.push(frame.args[arg_idx].clone());
+            },
+            super::Op::Starg(arg_idx) => {
+                let val = pop(stack);
+                frame.args[arg_idx] = val;
+            },
+            super::Op::Defun(function_idx) => {
+                stack.push(self.define_function(&method.functions[function_idx], environment)?);
+            },
+            super::Op::Call(name_idx, arity) => {
+                let name = &method.names[name_idx];
+
+                if let Some(function) = self.functions.get(name) {
+                    function.check_arity(name, arity)?;
+                    let args = stack.split_off(stack.len() - arity);
+                    stack.push((function.body)(&args)?);
+                }
+                else {
+                    let function = Self::get_user_function(name, arity, environment)?;
+                    let args = stack.split_off(stack.len() - arity);
+                    return Ok(Some((function.clone(), args)));
+                }
+            },
+            super::Op::Apply(name_idx, function_idx, arity) => {
+                let function = &method.functions[function_idx];
+                let mut captured = stack.split_off(stack.len() - arity);
+                let args = captured.split_off(function.parameters.len() - 1);
+                stack.push(self.apply_function(&method.names[name_idx], function, captured, &args, environment, call_depth)?);
+            },
+            super::Op::Unit(unit_idx) => {
+                let val = pop(stack);
+                stack.push(Quantity::new(val.to_f64()?, method.units[unit_idx].clone()).into_value());
+            },
+            super::Op::Convert(unit_idx) => {
+                let val = pop(stack);
+                stack.push(val.convert_to(&method.units[unit_idx])?);
+            },
+            super::Op::Newvec(count) => {
+                let vals = stack.split_off(stack.len() - count);
+                stack.push(Value::Vector(vals));
+            },
+            super::Op::Dup => {
+                let val = stack.last().expect("verified bytecode never underflows the stack").clone();
+                stack.push(val);
+            },
+            super::Op::Pop => {
+                pop(stack);
+            },
+            super::Op::Br(target) => {
+                frame.ip = target;
+            },
+            super::Op::Brtrue(target) => {
+                let val = pop(stack);
+                if val.to_bool()? {
+                    frame.ip = target;
+                }
+            },
+            super::Op::Brfalse(target) => {
+                let val = pop(stack);
+                if !val.to_bool()? {
+                    frame.ip = target;
+                }
+            },
+            op if op.is_unary_operator() => {
+                let val = pop(stack);
+                stack.push(op.apply_unary(&val)?);
+            },
+            op => {
+                let val1 = pop(stack);
+                let val2 = pop(stack);
+                stack.push(op.apply_binary(&val2, &val1)?);
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+//Verification guarantees every op finds the values it needs on the stack
+fn pop(stack: &mut Vec<Value>) -> Value {
+    stack.pop().expect("verified bytecode never underflows the stack")
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
@@ -0,0 +1,251 @@
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use crate::calculator::error::{runtime_error, Result};
+use super::Value;
+
+//Matrices are vectors of rows, so [[1, 2], [3, 4]] has the rows [1, 2] and [3, 4]. Elements are combined with the
+//same arithmetic as everywhere else, so integer and rational matrices stay exact where they can
+
+type Matrix = Vec<Vec<Value>>;
+
+/// Multiplies two matrices. A vector on the left is treated as a row and a vector on the right as a column, and the
+/// result is a vector whenever one of the operands is, so multiplying two vectors gives their dot product.
+pub fn matmul(lhs: &Value, rhs: &Value) -> Result<Value> {
+    let lhs_is_vector = !is_matrix(lhs);
+    let rhs_is_vector = !is_matrix(rhs);
+    let lhs = match lhs_is_vector {
+        true => vec![to_row(lhs, "matmul")?],
+        false => to_matrix(lhs, "matmul")?
+    };
+    let rhs = match rhs_is_vector {
+        true => transpose_rows(&[to_row(rhs, "matmul")?]),
+        false => to_matrix(rhs, "matmul")?
+    };
+
+    if lhs[0].len() != rhs.len() {
+        return Err(runtime_error!("Cannot multiply a {} matrix by a {} matrix", describe(&lhs), describe(&rhs)));
+    }
+
+    let mut product = vec![];
+    for lhs_row in lhs.iter() {
+        let mut row = vec![];
+        for col in 0..rhs[0].len() {
+            let mut sum = lhs_row[0].mul(&rhs[0][col])?;
+            for k in 1..rhs.len() {
+                sum = sum.add(&lhs_row[k].mul(&rhs[k][col])?)?;
+            }
+            row.push(sum);
+        }
+        product.push(row);
+    }
+
+    match (lhs_is_vector, rhs_is_vector) {
+        (true, true) => Ok(product.remove(0).remove(0)),
+        (true, false) => Ok(Value::Vector(product.remove(0))),
+        (false, true) => Ok(Value::Vector(product.into_iter().map(|mut row| row.remove(0)).collect())),
+        (false, false) => Ok(from_matrix(product))
+    }
+}
+
+/// Swaps the rows and columns of a matrix. A vector is treated as a row, so it becomes a column.
+pub fn transpose(val: &Value) -> Result<Value> {
+    let matrix = match is_matrix(val) {
+        true => to_matrix(val, "transpose")?,
+        false => vec![to_row(val, "transpose")?]
+    };
+    Ok(from_matrix(transpose_rows(&matrix)))
+}
+
+/// Uses the Bareiss algorithm, whose divisions are always exact, so the determinant of an integer matrix is an integer.
+pub fn determinant(val: &Value) -> Result<Value> {
+    let mut matrix = to_square_matrix(val, "det")?;
+    let size = matrix.len();
+
+    let mut negate = false;
+    let mut previous_pivot = Value::Integer(1);
+    for k in 0..size - 1 {
+        if is_zero(&matrix[k][k]) {
+            match (k + 1..size).find(|&row| !is_zero(&matrix[row][k])) {
+                Some(row) => {
+                    matrix.swap(k, row);
+                    negate = !negate;
+                },
+                None => return Ok(Value::Integer(0))
+            }
+        }
+
+        for row in k + 1..size {
+            for col in k + 1..size {
+                let cross = matrix[row][col].mul(&matrix[k][k])?.sub(&matrix[row][k].mul(&matrix[k][col])?)?;
+                matrix[row][col] = cross.div(&previous_pivot)?;
+            }
+        }
+        previous_pivot = matrix[k][k].clone();
+    }
+
+    let determinant = matrix[size - 1][size - 1].clone();
+    if negate { determinant.neg() } else { Ok(determinant) }
+}
+
+/// Uses Gauss-Jordan elimination with partial pivoting. Integers are promoted to rationals first, so the divisions
+/// neither truncate nor round, and the rationals that result are turned into floats unless the matrix already held
+/// rationals.
+pub fn inverse(val: &Value) -> Result<Value> {
+    let matrix = to_square_matrix(val, "inverse")?;
+    let size = matrix.len();
+    let keep_rationals = matrix.iter().flatten().any(|val| matches!(val, Value::Rational(_)));
+
+    let mut augmented = matrix.into_iter().enumerate().map(|(row_idx, row)| {
+        let identity_row = (0..size).map(|col| Value::Integer((row_idx == col) as i64));
+        row.into_iter()
+            .map(|val| match val {
+                Value::Integer(val) => Value::Rational(BigRational::from_integer(BigInt::from(val))),
+                val => val
+            })
+            .chain(identity_row)
+            .collect::<Vec<_>>()
+    }).collect::<Vec<_>>();
+
+    for k in 0..size {
+        let mut pivot_row = k;
+        let mut pivot_magnitude = 0.0;
+        for (row, vals) in augmented.iter().enumerate().skip(k) {
+            let magnitude = vals[k].to_complex()?.norm();
+            if magnitude > pivot_magnitude {
+                pivot_row = row;
+                pivot_magnitude = magnitude;
+            }
+        }
+        if pivot_magnitude == 0.0 {
+            return Err(runtime_error!("The matrix is singular, so it has no inverse"));
+        }
+        augmented.swap(k, pivot_row);
+
+        let pivot = augmented[k][k].clone();
+        augmented[k] = augmented[k].iter().map(|val| val.div(&pivot)).collect::<Result<_>>()?;
+        for row in 0..size {
+            if row == k || is_zero(&augmented[row][k]) {
+                continue;
+            }
+
+            let factor = augmented[row][k].clone();
+            augmented[row] = augmented[row].iter()
+                .zip(augmented[k].iter())
+                .map(|(val, pivot_val)| val.sub(&factor.mul(pivot_val)?))
+                .collect::<Result<_>>()?;
+        }
+    }
+
+    let inverse = augmented.into_iter()
+        .map(|row| row[size..].iter()
+            .map(|val| match val {
+                Value::Rational(_) if !keep_rationals => val.to_f64().map(Value::Float),
+                val => Ok(val.clone())
+            })
+            .collect::<Result<Vec<_>>>())
+        .collect::<Result<Matrix>>()?;
+    Ok(from_matrix(inverse))
+}
+
+fn is_zero(val: &Value) -> bool {
+    val.equals(&Value::Integer(0))
+}
+
+fn is_matrix(val: &Value) -> bool {
+    matches!(val, Value::Vector(rows) if rows.iter().any(|row| matches!(row, Value::Vector(_))))
+}
+
+fn to_row(val: &Value, name: &str) -> Result<Vec<Value>> {
+    match val {
+        Value::Vector(vals) if !vals.is_empty() => Ok(vals.clone()),
+        Value::Vector(_) => Err(runtime_error!("Function '{}' can't be applied to an empty vector", name)),
+        _ => Err(runtime_error!("Function '{}' expects a vector or a matrix, found {}", name, val.get_type_name()))
+    }
+}
+
+fn to_matrix(val: &Value, name: &str) -> Result<Matrix> {
+    let rows = to_row(val, name)?.iter()
+        .map(|row| match row {
+            Value::Vector(vals) => Ok(vals.clone()),
+            _ => Err(runtime_error!("Function '{}' expects every row of the matrix to be a vector, found {}", name, row.get_type_name()))
+        })
+        .collect::<Result<Matrix>>()?;
+
+    if rows.iter().any(|row| row.len() != rows[0].len()) {
+        return Err(runtime_error!("Function '{}' expects the rows of the matrix to have the same length", name));
+    }
+    if rows[0].is_empty() {
+        return Err(runtime_error!("Function '{}' can't be applied to an empty matrix", name));
+    }
+    Ok(rows)
+}
+
+fn to_square_matrix(val: &Value, name: &str) -> Result<Matrix> {
+    let matrix = to_matrix(val, name)?;
+    if matrix.len() != matrix[0].len() {
+        return Err(runtime_error!("Function '{}' expects a square matrix, found a {} matrix", name, describe(&matrix)));
+    }
+    Ok(matrix)
+}
+
+fn from_matrix(matrix: Matrix) -> Value {
+    Value::Vector(matrix.into_iter().map(Value::Vector).collect())
+}
+
+fn transpose_rows(matrix: &[Vec<Value>]) -> Matrix {
+    (0..matrix[0].len())
+        .map(|col| matrix.iter().map(|row| row[col].clone()).collect())
+        .collect()
+}
+
+//Rows by columns, like 2x3
+fn describe(matrix: &[Vec<Value>]) -> String {
+    format!("{}x{}", matrix.len(), matrix[0].len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matrix(rows: &[&[i64]]) -> Value {
+        from_matrix(rows.iter().map(|row| row.iter().map(|&val| Value::Integer(val)).collect()).collect())
+    }
+
+    #[test]
+    fn linear_algebra_should_follow_the_usual_shapes() -> Result<()> {
+        let square = matrix(&[&[1, 2], &[3, 4]]);
+        let wide = matrix(&[&[1, 2, 3], &[4, 5, 6]]);
+        let vector = Value::Vector(vec![Value::Integer(5), Value::Integer(6)]);
+
+        assert_eq!(matmul(&square, &square)?.to_string(), "[[7, 10], [15, 22]]");
+        assert_eq!(matmul(&square, &wide)?.to_string(), "[[9, 12, 15], [19, 26, 33]]");
+        assert_eq!(matmul(&square, &vector)?.to_string(), "[17, 39]");
+        assert_eq!(matmul(&vector, &square)?.to_string(), "[23, 34]");
+        assert_eq!(matmul(&vector, &vector)?.to_string(), "61");
+        assert_eq!(transpose(&wide)?.to_string(), "[[1, 4], [2, 5], [3, 6]]");
+        assert_eq!(transpose(&vector)?.to_string(), "[[5], [6]]");
+
+        assert_eq!(determinant(&square)?, Value::Integer(-2));
+        assert_eq!(determinant(&matrix(&[&[0, 1, 2], &[3, 4, 5], &[6, 7, 9]]))?, Value::Integer(-3));
+        assert_eq!(determinant(&matrix(&[&[1, 2], &[2, 4]]))?, Value::Integer(0));
+        assert_eq!(determinant(&matrix(&[&[7]]))?, Value::Integer(7));
+        assert_eq!(inverse(&square)?.to_string(), "[[-2, 1], [1.5, -0.5]]");
+        assert_eq!(matmul(&inverse(&matrix(&[&[0, 1], &[1, 0]]))?, &vector)?.to_string(), "[6, 5]");
+
+        let test_cases: &[(Result<Value>, &str)] = &[
+            (matmul(&wide, &square), "Cannot multiply a 2x3 matrix by a 2x2 matrix"),
+            (determinant(&wide), "Function 'det' expects a square matrix, found a 2x3 matrix"),
+            (inverse(&matrix(&[&[1, 2], &[2, 4]])), "The matrix is singular, so it has no inverse"),
+            (transpose(&Value::Integer(1)), "Function 'transpose' expects a vector or a matrix, found integer"),
+            (determinant(&Value::Vector(vec![])), "Function 'det' can't be applied to an empty vector"),
+            (determinant(&Value::Vector(vec![vector.clone(), Value::Integer(1)])), "Function 'det' expects every row of the matrix to be a vector, found integer"),
+            (inverse(&Value::Vector(vec![vector.clone(), Value::Vector(vec![Value::Integer(1)])])), "Function 'inverse' expects the rows of the matrix to have the same length"),
+        ];
+
+        for (result, expected_message) in test_cases {
+            assert_eq!(result.as_ref().unwrap_err().to_string(), *expected_message);
+        }
+
+        Ok(())
+    }
+}
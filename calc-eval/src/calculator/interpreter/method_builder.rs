@@ -1,13 +1,135 @@
-use super::op::Op;
+use std::rc::Rc;
+use crate::calculator::tokenizer::Span;
+use super::{op::Op, Unit, UserFunction};
 
 pub struct MethodBuilder {
-    pub ops: Vec<Op>
+    pub ops: Vec<Op>,
+    pub names: Vec<String>,
+    pub strings: Vec<String>,
+    pub functions: Vec<Rc<UserFunction>>,
+    pub units: Vec<Unit>,
+    /// Names of the arguments this method is called with, when it is the body of a user function.
+    pub parameters: Vec<String>,
+    /// Source spans for the ops that can fail at runtime, sorted by op index.
+    pub spans: Vec<(usize, Span)>
 }
 
 impl MethodBuilder {
     pub fn new() -> Self {
+        Self::with_parameters(vec![])
+    }
+
+    pub fn with_parameters(parameters: Vec<String>) -> Self {
         Self {
-            ops: vec![]
+            ops: vec![],
+            names: vec![],
+            strings: vec![],
+            functions: vec![],
+            units: vec![],
+            parameters,
+            spans: vec![]
+        }
+    }
+
+    pub fn get_parameter_index(&self, name: &str) -> Option<usize> {
+        self.parameters.iter().position(|parameter| parameter == name)
+    }
+
+    pub fn add_function(&mut self, function: UserFunction) -> usize {
+        self.functions.push(Rc::new(function));
+        self.functions.len() - 1
+    }
+
+    pub fn intern_name(&mut self, name: &str) -> usize {
+        if let Some(idx) = self.names.iter().position(|existing| existing == name) {
+            idx
+        }
+        else {
+            self.names.push(name.to_owned());
+            self.names.len() - 1
         }
     }
+
+    pub fn intern_string(&mut self, string: &str) -> usize {
+        if let Some(idx) = self.strings.iter().position(|existing| existing == string) {
+            idx
+        }
+        else {
+            self.strings.push(string.to_owned());
+            self.strings.len() - 1
+        }
+    }
+
+    pub fn intern_unit(&mut self, unit: &Unit) -> usize {
+        if let Some(idx) = self.units.iter().position(|existing| existing == unit) {
+            idx
+        }
+        else {
+            self.units.push(unit.clone());
+            self.units.len() - 1
+        }
+    }
+
+    pub fn emit_spanned(&mut self, op: Op, span: Span) {
+        self.ops.push(op);
+        self.set_span(self.ops.len() - 1, span);
+    }
+
+    pub fn set_span(&mut self, op_idx: usize, span: Span) {
+        match self.spans.binary_search_by_key(&op_idx, |&(idx, _)| idx) {
+            Ok(existing_idx) => self.spans[existing_idx].1 = span,
+            Err(insert_idx) => self.spans.insert(insert_idx, (op_idx, span))
+        }
+    }
+
+    pub fn get_span(&self, op_idx: usize) -> Option<Span> {
+        self.spans.binary_search_by_key(&op_idx, |&(idx, _)| idx)
+            .ok()
+            .map(|existing_idx| self.spans[existing_idx].1)
+    }
+
+    /// Emits a branch op whose target isn't known yet, returning its index so it can be passed to `patch_jump` later.
+    pub fn emit_jump(&mut self, op: Op) -> usize {
+        debug_assert!(matches!(op, Op::Br(_) | Op::Brtrue(_) | Op::Brfalse(_)));
+        self.ops.push(op);
+        self.ops.len() - 1
+    }
+
+    /// Points the branch op at `jump_idx` to the next op that will be emitted.
+    pub fn patch_jump(&mut self, jump_idx: usize) {
+        let target = self.ops.len();
+        self.ops[jump_idx].set_jump_target(target);
+    }
+
+    /// Works out the stack depth before each op runs by following every branch. Ops that can't be reached are `None`.
+    pub fn get_stack_depths(&self) -> Vec<Option<usize>> {
+        let mut depths = vec![None; self.ops.len()];
+        let mut pending = vec![(0, 0usize)];
+
+        while let Some((op_idx, depth)) = pending.pop() {
+            if op_idx >= self.ops.len() || depths[op_idx].is_some() {
+                continue;
+            }
+            depths[op_idx] = Some(depth);
+
+            let op = self.ops[op_idx];
+            let (pops, pushes) = op.get_stack_effect();
+            let next_depth = depth.saturating_sub(pops) + pushes;
+
+            if let Some(target) = op.get_jump_target() {
+                pending.push((target, next_depth));
+            }
+            if !matches!(op, Op::Br(_)) {
+                pending.push((op_idx + 1, next_depth));
+            }
+        }
+
+        depths
+    }
+}
+
+impl Default for MethodBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
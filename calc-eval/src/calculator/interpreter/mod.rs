@@ -1,7 +1,38 @@
+mod closure_compiler;
+mod disassembler;
+mod environment;
+mod evaluation_mode;
+mod function_registry;
+mod higher_order;
 mod interpreter;
+mod linear_algebra;
 mod method_builder;
 mod op;
+mod optimizer;
+mod quantity;
+mod radix;
+mod serialization;
+mod unit;
+mod user_function;
+mod value;
+mod vector_interpreter;
+mod verifier;
 
+pub use closure_compiler::{Closure, ClosureCompiler, ClosureContext};
+pub use disassembler::disassemble;
+pub use environment::Environment;
+pub use evaluation_mode::EvaluationMode;
+pub use function_registry::FunctionRegistry;
+pub use higher_order::{apply_higher_order_function, check_higher_order_arity, is_higher_order_function};
 pub use interpreter::Interpreter;
 pub use method_builder::MethodBuilder;
 pub use op::Op;
+pub use optimizer::Optimizer;
+pub use quantity::Quantity;
+pub use radix::Radix;
+pub use serialization::BYTECODE_VERSION;
+pub use unit::Unit;
+pub use user_function::UserFunction;
+pub use value::Value;
+pub use vector_interpreter::VectorInterpreter;
+pub use verifier::verify;
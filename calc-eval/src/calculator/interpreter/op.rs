@@ -1,12 +1,173 @@
-
+use std::cmp::Ordering;
+use crate::calculator::error::{runtime_error, Result};
+use super::Value;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Op {
+    LdcI8(i64),
     LdcF8(f64),
+    /// Loads the imaginary number with the given imaginary part, like `3i`.
+    LdcImag(f64),
+    LdcBool(bool),
+    Ldstr(usize),
+    Ldvar(usize),
+    Stvar(usize),
+    Ldarg(usize),
+    Starg(usize),
+    Defun(usize),
+    Call(usize, usize),
+    /// Calls the higher-order built-in named by the first operand, like `solve`, with the function at the second
+    /// operand. Of the arguments the third operand counts, the first are the values the function captured and the rest
+    /// go to the built-in.
+    Apply(usize, usize, usize),
+    /// Attaches a unit to the number on top of the stack.
+    Unit(usize),
+    /// Converts the quantity on top of the stack to another unit with the same dimension.
+    Convert(usize),
+    /// Collects the given number of values on top of the stack into a vector, keeping their order.
+    Newvec(usize),
+    /// Loads the element of a vector at an index, both of which are popped.
+    Ldelem,
+    Dup,
+    Pop,
+    Br(usize),
+    Brtrue(usize),
+    Brfalse(usize),
     Neg,
+    Not,
+    Pow,
     Mul,
     Div,
     Rem,
     Add,
-    Sub
+    Sub,
+    Ceq,
+    Cne,
+    Clt,
+    Cle,
+    Cgt,
+    Cge
+}
+
+impl Op {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Op::LdcI8(_) => "LdcI8",
+            Op::LdcF8(_) => "LdcF8",
+            Op::LdcImag(_) => "LdcImag",
+            Op::LdcBool(_) => "LdcBool",
+            Op::Ldstr(_) => "Ldstr",
+            Op::Ldvar(_) => "Ldvar",
+            Op::Stvar(_) => "Stvar",
+            Op::Ldarg(_) => "Ldarg",
+            Op::Starg(_) => "Starg",
+            Op::Defun(_) => "Defun",
+            Op::Call(_, _) => "Call",
+            Op::Apply(_, _, _) => "Apply",
+            Op::Unit(_) => "Unit",
+            Op::Convert(_) => "Convert",
+            Op::Newvec(_) => "Newvec",
+            Op::Ldelem => "Ldelem",
+            Op::Dup => "Dup",
+            Op::Pop => "Pop",
+            Op::Br(_) => "Br",
+            Op::Brtrue(_) => "Brtrue",
+            Op::Brfalse(_) => "Brfalse",
+            Op::Neg => "Neg",
+            Op::Not => "Not",
+            Op::Pow => "Pow",
+            Op::Mul => "Mul",
+            Op::Div => "Div",
+            Op::Rem => "Rem",
+            Op::Add => "Add",
+            Op::Sub => "Sub",
+            Op::Ceq => "Ceq",
+            Op::Cne => "Cne",
+            Op::Clt => "Clt",
+            Op::Cle => "Cle",
+            Op::Cgt => "Cgt",
+            Op::Cge => "Cge"
+        }
+    }
+
+    /// How many values the op pops off the stack, and how many it pushes back on.
+    pub fn get_stack_effect(&self) -> (usize, usize) {
+        match self {
+            Op::LdcI8(_) |
+            Op::LdcF8(_) |
+            Op::LdcImag(_) |
+            Op::LdcBool(_) |
+            Op::Ldstr(_) |
+            Op::Ldvar(_) |
+            Op::Ldarg(_) |
+            Op::Defun(_) => (0, 1),
+            Op::Stvar(_) |
+            Op::Starg(_) |
+            Op::Pop |
+            Op::Brtrue(_) |
+            Op::Brfalse(_) => (1, 0),
+            Op::Call(_, arity) |
+            Op::Apply(_, _, arity) |
+            Op::Newvec(arity) => (*arity, 1),
+            Op::Unit(_) |
+            Op::Convert(_) => (1, 1),
+            Op::Dup => (1, 2),
+            Op::Br(_) => (0, 0),
+            op if op.is_unary_operator() => (1, 1),
+            _ => (2, 1)
+        }
+    }
+
+    pub fn get_jump_target(&self) -> Option<usize> {
+        match self {
+            Op::Br(target) |
+            Op::Brtrue(target) |
+            Op::Brfalse(target) => Some(*target),
+            _ => None
+        }
+    }
+
+    pub fn set_jump_target(&mut self, target: usize) {
+        match self {
+            Op::Br(jump_target) |
+            Op::Brtrue(jump_target) |
+            Op::Brfalse(jump_target) => *jump_target = target,
+            op => panic!("Op is not a branch: {:?}", op)
+        }
+    }
+
+    pub fn is_unary_operator(&self) -> bool {
+        matches!(self, Op::Neg | Op::Not)
+    }
+
+    pub fn is_binary_operator(&self) -> bool {
+        matches!(self, Op::Ldelem | Op::Pow | Op::Mul | Op::Div | Op::Rem | Op::Add | Op::Sub | Op::Ceq | Op::Cne | Op::Clt | Op::Cle | Op::Cgt | Op::Cge)
+    }
+
+    pub fn apply_unary(&self, val: &Value) -> Result<Value> {
+        match self {
+            Op::Neg => val.neg(),
+            Op::Not => val.not(),
+            op => Err(runtime_error!("Op is not a unary operator: {:?}", op))
+        }
+    }
+
+    pub fn apply_binary(&self, lhs: &Value, rhs: &Value) -> Result<Value> {
+        match self {
+            Op::Ldelem => lhs.index(rhs),
+            Op::Pow => lhs.pow(rhs),
+            Op::Mul => lhs.mul(rhs),
+            Op::Div => lhs.div(rhs),
+            Op::Rem => lhs.rem(rhs),
+            Op::Add => lhs.add(rhs),
+            Op::Sub => lhs.sub(rhs),
+            Op::Ceq => Ok(Value::Boolean(lhs.equals(rhs))),
+            Op::Cne => Ok(Value::Boolean(!lhs.equals(rhs))),
+            Op::Clt => Ok(Value::Boolean(matches!(lhs.compare(rhs, "<")?, Some(Ordering::Less)))),
+            Op::Cle => Ok(Value::Boolean(matches!(lhs.compare(rhs, "<=")?, Some(Ordering::Less | Ordering::Equal)))),
+            Op::Cgt => Ok(Value::Boolean(matches!(lhs.compare(rhs, ">")?, Some(Ordering::Greater)))),
+            Op::Cge => Ok(Value::Boolean(matches!(lhs.compare(rhs, ">=")?, Some(Ordering::Greater | Ordering::Equal)))),
+            op => Err(runtime_error!("Op is not a binary operator: {:?}", op))
+        }
+    }
 }
@@ -0,0 +1,327 @@
+use std::collections::HashSet;
+use num_complex::Complex64;
+use num_traits::ToPrimitive;
+use crate::calculator::{
+    error::Result,
+    tokenizer::Span
+};
+use super::{EvaluationMode, MethodBuilder, Op, Value};
+
+/// Replaces the `len` ops starting at `start` with `ops`.
+struct Rewrite {
+    start: usize,
+    len: usize,
+    ops: Vec<(Op, Option<Span>)>
+}
+
+/// Peephole optimizer that folds constants and strips redundant ops from emitted bytecode.
+///
+/// Every rewrite leaves the result of an evaluation unchanged, errors included, down to the sign of a float zero. Ops
+/// that would fail on constant operands (such as `1 / 0`) are left in place so the error is still reported at runtime,
+/// and identities like `x * 1` are only simplified when the op before them is known to leave a real number on the stack.
+pub struct Optimizer {
+    mode: EvaluationMode
+}
+
+impl Optimizer {
+    /// Constants are folded the way an interpreter running in `mode` would load them.
+    pub fn new(mode: EvaluationMode) -> Self {
+        Self {
+            mode
+        }
+    }
+
+    pub fn optimize(&self, method: &mut MethodBuilder) {
+        loop {
+            let rewrites = self.find_rewrites(method);
+            if rewrites.is_empty() {
+                break;
+            }
+            Self::apply_rewrites(method, rewrites);
+        }
+    }
+
+    fn find_rewrites(&self, method: &MethodBuilder) -> Vec<Rewrite> {
+        let jump_targets = method.ops.iter()
+            .filter_map(Op::get_jump_target)
+            .collect::<HashSet<_>>();
+
+        let mut rewrites = vec![];
+        let mut idx = 0;
+        while idx < method.ops.len() {
+            match self.try_rewrite_at(method, idx, &jump_targets) {
+                Some(rewrite) => {
+                    idx = rewrite.start + rewrite.len;
+                    rewrites.push(rewrite);
+                },
+                None => idx += 1
+            }
+        }
+
+        rewrites
+    }
+
+    fn try_rewrite_at(&self, method: &MethodBuilder, idx: usize, jump_targets: &HashSet<usize>) -> Option<Rewrite> {
+        let ops = &method.ops;
+
+        //Only the first op of a window may be a jump target, otherwise the window isn't straight-line code
+        let window = |len: usize| -> Option<&[Op]> {
+            if idx + len <= ops.len() && (idx + 1..idx + len).all(|op_idx| !jump_targets.contains(&op_idx)) {
+                Some(&ops[idx..idx + len])
+            }
+            else {
+                None
+            }
+        };
+        let replace = |len: usize, new_ops: Vec<(Op, Option<Span>)>| Some(Rewrite {
+            start: idx,
+            len,
+            ops: new_ops
+        });
+
+        if let Op::Br(target) = ops[idx] {
+            if target == idx + 1 {
+                return replace(1, vec![]);
+            }
+
+            //Nothing after an unconditional branch runs until some other branch jumps back in
+            let dead_len = (idx + 1..ops.len())
+                .take_while(|op_idx| !jump_targets.contains(op_idx))
+                .count();
+            if dead_len > 0 {
+                return Some(Rewrite {
+                    start: idx + 1,
+                    len: dead_len,
+                    ops: vec![]
+                });
+            }
+            return None;
+        }
+
+        //The operand of an identity is whatever the previous op pushed, unless a branch can also jump in here
+        let operand_op = idx.checked_sub(1)
+            .filter(|_| !jump_targets.contains(&idx))
+            .map(|operand_idx| ops[operand_idx]);
+
+        //Negating a negation can't overflow, since `-x` is never `i64::MIN`, so the second pair of negations is exact
+        if let (Some(Op::Neg), Some(&[Op::Neg, Op::Neg])) = (operand_op, window(2)) {
+            return replace(2, vec![]);
+        }
+
+        //Real numbers and quantities come through `x * 1` and `x / 1` unchanged, but complex numbers with an infinite part
+        //don't, and dropping the op would skip its type check. Only `%` is known to leave a real number, since it
+        //refuses complex ones. `x ^ 1` isn't simplified since it renames the unit of a quantity, `x - 0` isn't since it
+        //fails on quantities, and `x + 0` isn't since `-0.0 + 0` is `0.0`. In rational mode the constant would promote
+        //an integer `x` to a rational, so this only applies in float mode
+        if self.mode == EvaluationMode::Float && operand_op == Some(Op::Rem) {
+            if let Some(&[Op::LdcI8(1), Op::Mul | Op::Div]) = window(2) {
+                return replace(2, vec![]);
+            }
+        }
+
+        let lhs = self.get_constant(ops[idx])?;
+
+        if let Some(&[_, Op::Dup]) = window(2) {
+            return replace(2, vec![(ops[idx], None), (ops[idx], None)]);
+        }
+        if let Some(&[_, Op::Pop]) = window(2) {
+            return replace(2, vec![]);
+        }
+        if let Some(&[_, branch @ (Op::Brtrue(target) | Op::Brfalse(target))]) = window(2) {
+            let Value::Boolean(condition) = lhs else {
+                return None;
+            };
+            let is_taken = condition == matches!(branch, Op::Brtrue(_));
+            return replace(2, if is_taken { vec![(Op::Br(target), None)] } else { vec![] });
+        }
+        if let Some(&[_, op]) = window(2) {
+            if op.is_unary_operator() {
+                let folded_op = self.fold(op.apply_unary(&lhs))?;
+                return replace(2, vec![(folded_op, None)]);
+            }
+        }
+        if let Some(&[_, rhs_op, op]) = window(3) {
+            if op.is_binary_operator() {
+                let rhs = self.get_constant(rhs_op)?;
+                let folded_op = self.fold(op.apply_binary(&lhs, &rhs))?;
+                return replace(3, vec![(folded_op, None)]);
+            }
+        }
+
+        None
+    }
+
+    /// Returns the value `op` would push, if it loads a constant.
+    fn get_constant(&self, op: Op) -> Option<Value> {
+        match op {
+            Op::LdcI8(num) => self.mode.load_constant(Value::Integer(num)).ok(),
+            Op::LdcF8(num) => self.mode.load_constant(Value::Float(num)).ok(),
+            Op::LdcImag(num) => Some(Value::from(Complex64::new(0.0, num))),
+            Op::LdcBool(val) => Some(Value::Boolean(val)),
+            _ => None
+        }
+    }
+
+    /// Returns an op that loads `result`, as long as it can be loaded back without changing its value or type.
+    fn fold(&self, result: Result<Value>) -> Option<Op> {
+        match (result.ok()?, self.mode) {
+            (Value::Integer(num), EvaluationMode::Float) => Some(Op::LdcI8(num)),
+            (Value::Float(num), EvaluationMode::Float) => Some(Op::LdcF8(num)),
+            (Value::Rational(num), EvaluationMode::Rational) if num.is_integer() => num.to_integer().to_i64().map(Op::LdcI8),
+            (Value::Complex(num), _) if num.re == 0.0 => Some(Op::LdcImag(num.im)),
+            (Value::Boolean(val), _) => Some(Op::LdcBool(val)),
+            _ => None
+        }
+    }
+
+    fn apply_rewrites(method: &mut MethodBuilder, rewrites: Vec<Rewrite>) {
+        let mut ops = vec![];
+        let mut spans = vec![];
+        let mut emit = |op: Op, span: Option<Span>| {
+            if let Some(span) = span {
+                spans.push((ops.len(), span));
+            }
+            ops.push(op);
+            ops.len() - 1
+        };
+
+        //Maps each old op index (and the end of the method) to the index of whatever replaced it
+        let mut idx_map = vec![0; method.ops.len() + 1];
+        let mut new_len = 0;
+        let mut rewrites = rewrites.into_iter().peekable();
+        let mut idx = 0;
+        while idx < method.ops.len() {
+            if let Some(rewrite) = rewrites.next_if(|rewrite| rewrite.start == idx) {
+                idx_map[idx..idx + rewrite.len].fill(new_len);
+                for (op, span) in rewrite.ops {
+                    new_len = emit(op, span) + 1;
+                }
+                idx += rewrite.len;
+            }
+            else {
+                idx_map[idx] = new_len;
+                new_len = emit(method.ops[idx], method.get_span(idx)) + 1;
+                idx += 1;
+            }
+        }
+        idx_map[method.ops.len()] = new_len;
+
+        for op in ops.iter_mut() {
+            if let Some(target) = op.get_jump_target() {
+                op.set_jump_target(idx_map[target]);
+            }
+        }
+
+        method.ops = ops;
+        method.spans = spans;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calculator::{
+        interpreter::{Environment, Interpreter, Quantity, Unit},
+        syntax::parse_expression,
+        tokenizer::Tokenizer
+    };
+    use super::*;
+
+    fn compile(input: &str) -> MethodBuilder {
+        let tokens = Tokenizer::new().tokenize(input).collect::<Vec<_>>();
+        let result = parse_expression(&tokens);
+        assert!(result.errors.is_empty());
+
+        let mut method_builder = MethodBuilder::new();
+        result.expr.emit_bytecode(&mut method_builder).unwrap();
+        method_builder
+    }
+
+    #[test]
+    fn optimize_should_simplify_bytecode() {
+        let test_cases: &[(EvaluationMode, &str, &[Op])] = &[
+            (EvaluationMode::Float, "-(-(2*3))", &[Op::LdcI8(6)]),
+            (EvaluationMode::Float, "2^10 + 0.5", &[Op::LdcF8(1024.5)]),
+            (EvaluationMode::Float, "-(-x)", &[Op::Ldvar(0), Op::Neg, Op::Neg]),
+            (EvaluationMode::Float, "-(-(-x))", &[Op::Ldvar(0), Op::Neg]),
+            (EvaluationMode::Float, "x*1", &[Op::Ldvar(0), Op::LdcI8(1), Op::Mul]),
+            (EvaluationMode::Float, "1*x", &[Op::LdcI8(1), Op::Ldvar(0), Op::Mul]),
+            (EvaluationMode::Float, "(x%y)*1", &[Op::Ldvar(0), Op::Ldvar(1), Op::Rem]),
+            (EvaluationMode::Float, "x%y/(3-2)", &[Op::Ldvar(0), Op::Ldvar(1), Op::Rem]),
+            (EvaluationMode::Float, "(x-y)*1", &[Op::Ldvar(0), Op::Ldvar(1), Op::Sub, Op::LdcI8(1), Op::Mul]),
+            (EvaluationMode::Float, "(x*y)/1", &[Op::Ldvar(0), Op::Ldvar(1), Op::Mul, Op::LdcI8(1), Op::Div]),
+            (EvaluationMode::Float, "(x%y)^1", &[Op::Ldvar(0), Op::Ldvar(1), Op::Rem, Op::LdcI8(1), Op::Pow]),
+            (EvaluationMode::Float, "(x-y) - 0", &[Op::Ldvar(0), Op::Ldvar(1), Op::Sub, Op::LdcI8(0), Op::Sub]),
+            (EvaluationMode::Float, "(x+y)*1", &[Op::Ldvar(0), Op::Ldvar(1), Op::Add, Op::LdcI8(1), Op::Mul]),
+            (EvaluationMode::Float, "x+0", &[Op::Ldvar(0), Op::LdcI8(0), Op::Add]),
+            (EvaluationMode::Float, "x*1.0", &[Op::Ldvar(0), Op::LdcF8(1.0), Op::Mul]),
+            (EvaluationMode::Float, "2*x*3", &[Op::LdcI8(2), Op::Ldvar(0), Op::Mul, Op::LdcI8(3), Op::Mul]),
+            (EvaluationMode::Float, "2 * 3i", &[Op::LdcImag(6.0)]),
+            (EvaluationMode::Float, "1 + 2i", &[Op::LdcI8(1), Op::LdcImag(2.0), Op::Add]),
+            (EvaluationMode::Float, "1/0", &[Op::LdcI8(1), Op::LdcI8(0), Op::Div]),
+            (EvaluationMode::Float, "1 < 2 ? x : y", &[Op::Ldvar(0)]),
+            (EvaluationMode::Float, "1 > 2 ? x : y", &[Op::Ldvar(1)]),
+            (EvaluationMode::Float, "true && x", &[Op::Ldvar(0)]),
+            (EvaluationMode::Float, "false && x", &[Op::LdcBool(false)]),
+            (EvaluationMode::Float, "!false || x", &[Op::LdcBool(true)]),
+            (EvaluationMode::Float, "x || false", &[Op::Ldvar(0), Op::Dup, Op::Brtrue(5), Op::Pop, Op::LdcBool(false)]),
+            (EvaluationMode::Rational, "(1+2)*3", &[Op::LdcI8(9)]),
+            (EvaluationMode::Rational, "x*1", &[Op::Ldvar(0), Op::LdcI8(1), Op::Mul]),
+            (EvaluationMode::Rational, "1/3", &[Op::LdcI8(1), Op::LdcI8(3), Op::Div]),
+            (EvaluationMode::Rational, "0.5 * 4", &[Op::LdcI8(2)]),
+        ];
+
+        for &(mode, input, expected_ops) in test_cases {
+            let mut method_builder = compile(input);
+            Optimizer::new(mode).optimize(&mut method_builder);
+
+            assert_eq!(&method_builder.ops[..], expected_ops, "for input {:?}", input);
+        }
+    }
+
+    #[test]
+    fn optimize_should_not_change_results() {
+        let inputs = [
+            "-(-(2*3))", "2 ^ -1", "0.1 + 0.2", "7 / 2 * 2", "7 % 0", "1 / 0", "9223372036854775807 + 1",
+            "y + 0", "y * 1", "y - 0", "y / 1", "-0.0 + 0", "-0.0 * 1", "0 / 0.0", "(0 / 0.0) == (0 / 0.0)",
+            "x / 1 + n * 1", "-(-n) ^ 1", "sqrt(16) * 1", "min(1, 2) + 0", "undefined * 1",
+            "true ? 1 : 1 / 0", "false ? 1 / 0 : 2", "flag && n > 3", "!(1 < 2) || x >= 2.5", "1 ? 2 : 3",
+            "(1 < 2) + 1", "\"a\" + \"b\"", "z = 3 * 4 - 1", "n > 5 ? -(-n) : 1 * x",
+            "true * 1", "true * 2", "\"a\" - 0", "-(-\"a\")", "s * 1", "1 * s", "-(-s)", "-(-(-s))", "s ^ 1",
+            "-(-flag)", "flag / 1", "(s + s) * 1", "-(-(-n))", "(n - 1) * 1", "(n ^ 2) ^ 1", "(n % 4) / 1",
+            "(x - y) - 0", "-(-(-big))", "-(big - 1) - 1", "(v * 2) ^ 1", "(v - 1) * 1", "-(-(-v))", "(dist * 2) - 0",
+            "(dist * 2) * 1", "(dist * 2) ^ 1", "-(-(-dist))", "(dist % dist) ^ 1", "(dist % dist) * 1", "-c * 1", "-c / 1",
+            "(c - 1) * 1", "(c * 2) ^ 1", "(c % 2) / 1",
+        ];
+
+        for mode in [EvaluationMode::Float, EvaluationMode::Rational] {
+            let mut interpreter = Interpreter::new();
+            interpreter.set_mode(mode);
+
+            for input in inputs {
+                let evaluate = |method_builder: &MethodBuilder| {
+                    let mut environment = Environment::new();
+                    environment.set_variable("x", Value::Float(2.5));
+                    environment.set_variable("y", Value::Float(-0.0));
+                    environment.set_variable("n", Value::Integer(7));
+                    environment.set_variable("flag", Value::Boolean(true));
+                    environment.set_variable("s", Value::String("a".to_owned()));
+                    environment.set_variable("big", Value::Integer(i64::MIN));
+                    environment.set_variable("v", Value::Vector(vec![Value::Integer(1), Value::Float(2.5)]));
+                    environment.set_variable("dist", Quantity::new(3.0, Unit::lookup("km").unwrap()).into_value());
+                    environment.set_variable("c", Value::from(Complex64::new(0.0, f64::INFINITY)));
+
+                    match interpreter.evaluate_method(method_builder, &mut environment) {
+                        Ok(val) => format!("{:?}", val),
+                        Err(err) => format!("Error: {}", err)
+                    }
+                };
+
+                let method_builder = compile(input);
+                let mut optimized_method_builder = compile(input);
+                Optimizer::new(mode).optimize(&mut optimized_method_builder);
+
+                assert_eq!(evaluate(&optimized_method_builder), evaluate(&method_builder), "for input {:?} in {} mode", input, mode);
+            }
+        }
+    }
+}
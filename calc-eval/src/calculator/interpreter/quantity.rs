@@ -0,0 +1,97 @@
+use std::{cmp::Ordering, fmt::Display};
+use crate::calculator::error::{runtime_error, Result};
+use super::{Unit, Value};
+
+/// A number measured in some unit. The value is kept in that unit, so results read the way they were written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quantity {
+    pub value: f64,
+    pub unit: Unit
+}
+
+impl Quantity {
+    pub fn new(value: f64, unit: Unit) -> Self {
+        Self {
+            value,
+            unit
+        }
+    }
+
+    /// Quantities whose units cancel out, like a ratio of two lengths, become plain floats.
+    pub fn into_value(self) -> Value {
+        if self.unit.is_dimensionless() {
+            Value::Float(self.value * self.unit.scale)
+        }
+        else {
+            Value::Quantity(self)
+        }
+    }
+
+    pub fn convert_to(&self, unit: &Unit) -> Result<Quantity> {
+        if self.unit.dimension != unit.dimension {
+            return Err(runtime_error!("Cannot convert {} to {}", self.unit, unit));
+        }
+        Ok(Quantity::new(self.value * self.unit.scale / unit.scale, unit.clone()))
+    }
+
+    //Expresses rhs in the unit of self, so that the result of adding them is in the left operand's unit
+    fn rescale(&self, rhs: &Quantity, op: &str) -> Result<f64> {
+        if self.unit.dimension != rhs.unit.dimension {
+            return Err(runtime_error!("Cannot apply '{}' to {} and {}", op, self.unit, rhs.unit));
+        }
+        Ok(rhs.value * rhs.unit.scale / self.unit.scale)
+    }
+
+    pub fn neg(&self) -> Value {
+        Value::Quantity(Quantity::new(-self.value, self.unit.clone()))
+    }
+
+    pub fn add(&self, rhs: &Quantity) -> Result<Value> {
+        Ok(Quantity::new(self.value + self.rescale(rhs, "+")?, self.unit.clone()).into_value())
+    }
+
+    pub fn sub(&self, rhs: &Quantity) -> Result<Value> {
+        Ok(Quantity::new(self.value - self.rescale(rhs, "-")?, self.unit.clone()).into_value())
+    }
+
+    pub fn rem(&self, rhs: &Quantity) -> Result<Value> {
+        Ok(Quantity::new(self.value % self.rescale(rhs, "%")?, self.unit.clone()).into_value())
+    }
+
+    pub fn mul(&self, rhs: &Quantity) -> Result<Value> {
+        Ok(Quantity::new(self.value * rhs.value, self.unit.mul(&rhs.unit)?).into_value())
+    }
+
+    pub fn div(&self, rhs: &Quantity) -> Result<Value> {
+        Ok(Quantity::new(self.value / rhs.value, self.unit.div(&rhs.unit)?).into_value())
+    }
+
+    pub fn pow(&self, exponent: &Value) -> Result<Value> {
+        let exponent = match exponent {
+            Value::Quantity(_) => Err(runtime_error!("Cannot apply '^' to {} and {}", self.unit, exponent.get_type_name())),
+            _ => exponent.to_f64()
+        }?;
+        if exponent.fract() != 0.0 || exponent.abs() > i32::MAX as f64 {
+            return Err(runtime_error!("Quantities can only be raised to whole powers"));
+        }
+
+        let exponent = exponent as i32;
+        Ok(Quantity::new(self.value.powi(exponent), self.unit.pow(exponent)?).into_value())
+    }
+
+    /// Returns `None` when the values are unordered, which only happens when one of them is NaN.
+    pub fn compare(&self, rhs: &Quantity, op: &str) -> Result<Option<Ordering>> {
+        Ok(self.value.partial_cmp(&self.rescale(rhs, op)?))
+    }
+}
+
+impl Display for Quantity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.unit.name.is_empty() {
+            write!(f, "{}", self.value)
+        }
+        else {
+            write!(f, "{} {}", self.value, self.unit.name)
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use std::{fmt::Display, str::FromStr};
+use num_bigint::{BigUint, Sign};
+use crate::calculator::error::CalcError;
+use super::Value;
+
+/// The base whole numbers are displayed in. It only changes how results are shown, never how they're computed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Radix {
+    #[default]
+    Decimal,
+    /// Whole numbers are shown like `0xFF`.
+    Hex,
+    /// Whole numbers are shown like `0b1010`.
+    Binary
+}
+
+impl Radix {
+    /// Formats `val` for display. Whole numbers, including whole rationals and the ones inside vectors, are written in
+    /// this base with a prefix the tokenizer reads back, and a minus sign in front if they're negative. Everything
+    /// else is written as usual.
+    pub fn format(self, val: &Value) -> String {
+        match (self, val) {
+            (Self::Decimal, _) => val.to_string(),
+            (_, Value::Integer(num)) => self.format_whole(num.is_negative(), &BigUint::from(num.unsigned_abs())),
+            (_, Value::Rational(num)) if num.is_integer() => self.format_whole(num.numer().sign() == Sign::Minus, num.numer().magnitude()),
+            (_, Value::Vector(vals)) => {
+                let vals = vals.iter().map(|val| self.format(val)).collect::<Vec<_>>();
+                format!("[{}]", vals.join(", "))
+            },
+            _ => val.to_string()
+        }
+    }
+
+    fn format_whole(self, is_negative: bool, magnitude: &BigUint) -> String {
+        let sign = if is_negative { "-" } else { "" };
+        match self {
+            Self::Decimal => format!("{}{}", sign, magnitude),
+            Self::Hex => format!("{}{:#X}", sign, magnitude),
+            Self::Binary => format!("{}{:#b}", sign, magnitude)
+        }
+    }
+}
+
+impl Display for Radix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Decimal => write!(f, "decimal"),
+            Self::Hex => write!(f, "hex"),
+            Self::Binary => write!(f, "binary")
+        }
+    }
+}
+
+impl FromStr for Radix {
+    type Err = CalcError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "decimal" => Ok(Self::Decimal),
+            "hex" => Ok(Self::Hex),
+            "binary" => Ok(Self::Binary),
+            _ => Err(CalcError::UnknownRadix(s.to_owned()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigInt;
+    use num_rational::BigRational;
+    use super::*;
+
+    #[test]
+    fn format_should_write_whole_numbers_in_the_radix() {
+        let vector = Value::Vector(vec![Value::Integer(5), Value::Vector(vec![Value::Integer(-1)])]);
+        let rational = Value::Rational(BigRational::from_integer(BigInt::from(-255)));
+
+        let test_cases: &[(Radix, Value, &str)] = &[
+            (Radix::Hex, Value::Integer(255), "0xFF"),
+            (Radix::Hex, Value::Integer(-255), "-0xFF"),
+            (Radix::Hex, Value::Integer(i64::MIN), "-0x8000000000000000"),
+            (Radix::Hex, rational.clone(), "-0xFF"),
+            (Radix::Binary, Value::Integer(10), "0b1010"),
+            (Radix::Binary, Value::Integer(0), "0b0"),
+            (Radix::Binary, vector.clone(), "[0b101, [-0b1]]"),
+            (Radix::Hex, Value::Float(2.5), "2.5"),
+            (Radix::Hex, Value::Boolean(true), "true"),
+            (Radix::Decimal, Value::Integer(255), "255"),
+            (Radix::Decimal, rational, "-255"),
+        ];
+
+        for (radix, val, expected) in test_cases {
+            assert_eq!(radix.format(val), *expected, "for {:?} in {}", val, radix);
+        }
+    }
+}
@@ -0,0 +1,443 @@
+use crate::calculator::{
+    error::{CalcError, Result},
+    tokenizer::Span
+};
+use super::{unit::Dimension, verify, MethodBuilder, Op, Unit, UserFunction};
+
+/// Identifies serialized bytecode, so unrelated data is rejected before anything else is read.
+const MAGIC: &[u8; 4] = b"CALC";
+
+/// Bumped whenever the layout changes. Payloads from any other version are refused rather than misread.
+pub const BYTECODE_VERSION: u16 = 4;
+
+/// How deeply function definitions may nest inside each other, which keeps a malicious payload from overflowing
+/// the stack while it is decoded.
+const MAX_NESTING_DEPTH: usize = 32;
+
+impl MethodBuilder {
+    /// Encodes the method, and every function it defines, so it can be loaded again with `from_bytes`.
+    ///
+    /// The layout is a header (the magic bytes "CALC" and a little-endian `u16` version), followed by the method:
+    /// its parameters, then its constant pool of names, strings, units and functions, then the op stream and the
+    /// source spans of the ops. Counts, indices and span offsets are `u32`; everything is little-endian.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer { bytes: vec![] };
+        writer.bytes.extend_from_slice(MAGIC);
+        writer.bytes.extend_from_slice(&BYTECODE_VERSION.to_le_bytes());
+        writer.write_method(self);
+        writer.bytes
+    }
+
+    /// Decodes bytecode written by `to_bytes`, refusing it if it is truncated, corrupted, from another version, or
+    /// fails verification. Only the current layout can be decoded, so older payloads have to be compiled again.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader { bytes, pos: 0 };
+
+        if reader.read_bytes(MAGIC.len())? != MAGIC {
+            return Err(CalcError::InvalidBytecode("The data doesn't start with the bytecode header".to_owned()));
+        }
+        let version = u16::from_le_bytes(reader.read_array()?);
+        if version > BYTECODE_VERSION {
+            return Err(CalcError::InvalidBytecode(format!("Version {} is newer than the supported version {}", version, BYTECODE_VERSION)));
+        }
+        if version < BYTECODE_VERSION {
+            return Err(CalcError::InvalidBytecode(format!("Version {} is older than the supported version {}, so it has to be compiled again", version, BYTECODE_VERSION)));
+        }
+
+        let method = reader.read_method(0)?;
+        if reader.pos != bytes.len() {
+            return Err(CalcError::InvalidBytecode(format!("Unexpected data after the method at offset {}", reader.pos)));
+        }
+
+        verify(&method)?;
+        Ok(method)
+    }
+}
+
+struct Writer {
+    bytes: Vec<u8>
+}
+
+impl Writer {
+    fn write_u8(&mut self, val: u8) {
+        self.bytes.push(val);
+    }
+
+    fn write_u32(&mut self, val: usize) {
+        let val = u32::try_from(val).expect("bytecode tables never hold more than u32::MAX entries");
+        self.bytes.extend_from_slice(&val.to_le_bytes());
+    }
+
+    fn write_string(&mut self, val: &str) {
+        self.write_u32(val.len());
+        self.bytes.extend_from_slice(val.as_bytes());
+    }
+
+    fn write_strings(&mut self, vals: &[String]) {
+        self.write_u32(vals.len());
+        for val in vals.iter() {
+            self.write_string(val);
+        }
+    }
+
+    fn write_method(&mut self, method: &MethodBuilder) {
+        self.write_strings(&method.parameters);
+        self.write_strings(&method.names);
+        self.write_strings(&method.strings);
+
+        self.write_u32(method.units.len());
+        for unit in method.units.iter() {
+            self.write_string(&unit.name);
+            self.bytes.extend_from_slice(&unit.scale.to_le_bytes());
+            for exponent in unit.dimension.0 {
+                self.bytes.extend_from_slice(&exponent.to_le_bytes());
+            }
+        }
+
+        self.write_u32(method.functions.len());
+        for function in method.functions.iter() {
+            self.write_string(&function.name);
+            self.write_method(&function.method);
+        }
+
+        self.write_u32(method.ops.len());
+        for op in method.ops.iter() {
+            self.write_op(op);
+        }
+
+        self.write_u32(method.spans.len());
+        for &(op_idx, span) in method.spans.iter() {
+            self.write_u32(op_idx);
+            self.write_u32(span.start);
+            self.write_u32(span.end);
+        }
+    }
+
+    fn write_op(&mut self, op: &Op) {
+        self.write_u8(get_op_tag(op));
+
+        match *op {
+            Op::LdcI8(num) => self.bytes.extend_from_slice(&num.to_le_bytes()),
+            Op::LdcF8(num) |
+            Op::LdcImag(num) => self.bytes.extend_from_slice(&num.to_le_bytes()),
+            Op::LdcBool(val) => self.write_u8(val as u8),
+            Op::Ldstr(idx) |
+            Op::Ldvar(idx) |
+            Op::Stvar(idx) |
+            Op::Ldarg(idx) |
+            Op::Starg(idx) |
+            Op::Defun(idx) |
+            Op::Unit(idx) |
+            Op::Convert(idx) |
+            Op::Newvec(idx) |
+            Op::Br(idx) |
+            Op::Brtrue(idx) |
+            Op::Brfalse(idx) => self.write_u32(idx),
+            Op::Call(name_idx, arity) => {
+                self.write_u32(name_idx);
+                self.write_u32(arity);
+            },
+            Op::Apply(name_idx, function_idx, arity) => {
+                self.write_u32(name_idx);
+                self.write_u32(function_idx);
+                self.write_u32(arity);
+            },
+            _ => { }
+        }
+    }
+}
+
+fn get_op_tag(op: &Op) -> u8 {
+    match op {
+        Op::LdcI8(_) => 0,
+        Op::LdcF8(_) => 1,
+        Op::LdcBool(_) => 2,
+        Op::Ldstr(_) => 3,
+        Op::Ldvar(_) => 4,
+        Op::Stvar(_) => 5,
+        Op::Ldarg(_) => 6,
+        Op::Starg(_) => 7,
+        Op::Defun(_) => 8,
+        Op::Call(_, _) => 9,
+        Op::Unit(_) => 10,
+        Op::Convert(_) => 11,
+        Op::Dup => 12,
+        Op::Pop => 13,
+        Op::Br(_) => 14,
+        Op::Brtrue(_) => 15,
+        Op::Brfalse(_) => 16,
+        Op::Neg => 17,
+        Op::Not => 18,
+        Op::Pow => 19,
+        Op::Mul => 20,
+        Op::Div => 21,
+        Op::Rem => 22,
+        Op::Add => 23,
+        Op::Sub => 24,
+        Op::Ceq => 25,
+        Op::Cne => 26,
+        Op::Clt => 27,
+        Op::Cle => 28,
+        Op::Cgt => 29,
+        Op::Cge => 30,
+        Op::Apply(_, _, _) => 31,
+        Op::LdcImag(_) => 32,
+        Op::Newvec(_) => 33,
+        Op::Ldelem => 34
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize
+}
+
+impl<'a> Reader<'a> {
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.bytes.len() - self.pos < len {
+            return Err(CalcError::InvalidBytecode(format!("Unexpected end of data at offset {}", self.pos)));
+        }
+
+        let bytes = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.read_bytes(N)?.try_into().unwrap())
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u32(&mut self) -> Result<usize> {
+        Ok(u32::from_le_bytes(self.read_array()?) as usize)
+    }
+
+    /// Reads a count of entries that each take at least `min_entry_len` bytes, refusing counts the rest of the data
+    /// couldn't possibly hold so a corrupted count can't cause a huge allocation.
+    fn read_count(&mut self, min_entry_len: usize) -> Result<usize> {
+        let count = self.read_u32()?;
+        if count.saturating_mul(min_entry_len) > self.bytes.len() - self.pos {
+            return Err(CalcError::InvalidBytecode(format!("Unexpected end of data at offset {}", self.pos)));
+        }
+        Ok(count)
+    }
+
+    fn read_string(&mut self) -> Result<String> {
+        let len = self.read_u32()?;
+        let offset = self.pos;
+        let bytes = self.read_bytes(len)?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| CalcError::InvalidBytecode(format!("Invalid UTF-8 in the string at offset {}", offset)))
+    }
+
+    fn read_strings(&mut self) -> Result<Vec<String>> {
+        let count = self.read_count(4)?;
+        (0..count).map(|_| self.read_string()).collect()
+    }
+
+    fn read_method(&mut self, nesting_depth: usize) -> Result<MethodBuilder> {
+        if nesting_depth > MAX_NESTING_DEPTH {
+            return Err(CalcError::InvalidBytecode("Functions are nested too deeply".to_owned()));
+        }
+
+        let mut method = MethodBuilder::with_parameters(self.read_strings()?);
+        method.names = self.read_strings()?;
+        method.strings = self.read_strings()?;
+
+        let count = self.read_count(40)?;
+        for _ in 0..count {
+            let name = self.read_string()?;
+            let scale = f64::from_le_bytes(self.read_array()?);
+            let mut dimension = Dimension::default();
+            for exponent in dimension.0.iter_mut() {
+                *exponent = i32::from_le_bytes(self.read_array()?);
+            }
+            method.units.push(Unit { name, scale, dimension });
+        }
+
+        let count = self.read_count(4)?;
+        for _ in 0..count {
+            let name = self.read_string()?;
+            let function_method = self.read_method(nesting_depth + 1)?;
+            method.add_function(UserFunction {
+                name,
+                parameters: function_method.parameters.clone(),
+                method: function_method
+            });
+        }
+
+        let count = self.read_count(1)?;
+        for _ in 0..count {
+            let op = self.read_op()?;
+            method.ops.push(op);
+        }
+
+        let count = self.read_count(12)?;
+        for _ in 0..count {
+            let op_idx = self.read_u32()?;
+            let span = Span::new(self.read_u32()?, self.read_u32()?);
+            if op_idx >= method.ops.len() || method.spans.last().is_some_and(|&(last_idx, _)| last_idx >= op_idx) {
+                return Err(CalcError::InvalidBytecode(format!("Invalid span for op {} at offset {}", op_idx, self.pos)));
+            }
+            method.spans.push((op_idx, span));
+        }
+
+        Ok(method)
+    }
+
+    fn read_op(&mut self) -> Result<Op> {
+        let offset = self.pos;
+        let op = match self.read_u8()? {
+            0 => Op::LdcI8(i64::from_le_bytes(self.read_array()?)),
+            1 => Op::LdcF8(f64::from_le_bytes(self.read_array()?)),
+            2 => match self.read_u8()? {
+                0 => Op::LdcBool(false),
+                1 => Op::LdcBool(true),
+                val => return Err(CalcError::InvalidBytecode(format!("Invalid boolean {} at offset {}", val, offset + 1)))
+            },
+            3 => Op::Ldstr(self.read_u32()?),
+            4 => Op::Ldvar(self.read_u32()?),
+            5 => Op::Stvar(self.read_u32()?),
+            6 => Op::Ldarg(self.read_u32()?),
+            7 => Op::Starg(self.read_u32()?),
+            8 => Op::Defun(self.read_u32()?),
+            9 => Op::Call(self.read_u32()?, self.read_u32()?),
+            10 => Op::Unit(self.read_u32()?),
+            11 => Op::Convert(self.read_u32()?),
+            12 => Op::Dup,
+            13 => Op::Pop,
+            14 => Op::Br(self.read_u32()?),
+            15 => Op::Brtrue(self.read_u32()?),
+            16 => Op::Brfalse(self.read_u32()?),
+            17 => Op::Neg,
+            18 => Op::Not,
+            19 => Op::Pow,
+            20 => Op::Mul,
+            21 => Op::Div,
+            22 => Op::Rem,
+            23 => Op::Add,
+            24 => Op::Sub,
+            25 => Op::Ceq,
+            26 => Op::Cne,
+            27 => Op::Clt,
+            28 => Op::Cle,
+            29 => Op::Cgt,
+            30 => Op::Cge,
+            31 => Op::Apply(self.read_u32()?, self.read_u32()?, self.read_u32()?),
+            32 => Op::LdcImag(f64::from_le_bytes(self.read_array()?)),
+            33 => Op::Newvec(self.read_u32()?),
+            34 => Op::Ldelem,
+            tag => return Err(CalcError::InvalidBytecode(format!("Unknown op {} at offset {}", tag, offset)))
+        };
+
+        Ok(op)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calculator::Calculator;
+    use super::*;
+
+    #[test]
+    fn from_bytes_should_round_trip_every_op() {
+        let calc = Calculator::new();
+
+        let test_cases: &[(&str, &[&str])] = &[
+            ("x = -y + 3 * y / z % w - y ^ 2.5", &["LdcF8", "LdcI8", "Ldvar", "Stvar", "Dup", "Neg", "Add", "Sub", "Mul", "Div", "Rem", "Pow"]),
+            ("a && b || !c ? \"yes\" : false", &["Ldstr", "LdcBool", "Pop", "Br", "Brtrue", "Brfalse", "Not"]),
+            ("a == b != (a < b) == (a <= b) != (a > b) == (a >= b)", &["Ceq", "Cne", "Clt", "Cle", "Cgt", "Cge"]),
+            ("f(x, y) = x = max(x, y) * 1 km", &["Defun", "Call", "Ldarg", "Starg", "Unit"]),
+            ("d in m", &["Convert"]),
+            ("solve(x ^ 2 - a, x, 1)", &["Apply"]),
+            ("x * 2.5i", &["LdcImag"]),
+            ("[x, [1, 2]][1][0]", &["Newvec", "Ldelem"]),
+        ];
+
+        let mut seen_ops = vec![];
+        for &(input, expected_ops) in test_cases {
+            let method = calc.emit(input).unwrap();
+            let decoded = MethodBuilder::from_bytes(&method.to_bytes()).unwrap();
+
+            assert_eq!(decoded.ops, method.ops, "for input {:?}", input);
+            assert_eq!(decoded.names, method.names);
+            assert_eq!(decoded.strings, method.strings);
+            assert_eq!(decoded.units, method.units);
+            assert_eq!(decoded.spans, method.spans);
+            assert_eq!(decoded.functions.len(), method.functions.len());
+            for (decoded_function, function) in decoded.functions.iter().zip(method.functions.iter()) {
+                assert_eq!(decoded_function.to_string(), function.to_string());
+                assert_eq!(decoded_function.method.ops, function.method.ops);
+                seen_ops.extend(function.method.ops.iter().map(Op::get_name));
+            }
+
+            seen_ops.extend(method.ops.iter().map(Op::get_name));
+            for expected_op in expected_ops.iter() {
+                assert!(seen_ops.contains(expected_op), "{} wasn't emitted for {:?}", expected_op, input);
+            }
+        }
+
+        //Every tag the loader knows has to come from an op that was round-tripped above
+        for tag in 0..=34 {
+            let mut bytes = vec![tag];
+            bytes.resize(13, 0);
+            let op = Reader { bytes: &bytes, pos: 0 }.read_op().unwrap();
+            assert_eq!(get_op_tag(&op), tag);
+            assert!(seen_ops.contains(&op.get_name()), "{} wasn't round-tripped", op.get_name());
+        }
+    }
+
+    #[test]
+    fn from_bytes_should_reject_invalid_data() {
+        let calc = Calculator::new();
+        let bytes = calc.emit("x * 2 + 1").unwrap().to_bytes();
+
+        let mut newer_version = bytes.clone();
+        newer_version[4..6].copy_from_slice(&(BYTECODE_VERSION + 1).to_le_bytes());
+        let mut older_version = bytes.clone();
+        older_version[4..6].copy_from_slice(&(BYTECODE_VERSION - 1).to_le_bytes());
+
+        //The last op, `Add`, comes right before the spans of `x`, `x * 2` and `x * 2 + 1`
+        let mut unknown_op = bytes.clone();
+        let op_offset = bytes.len() - 4 - 3 * 12 - 1;
+        unknown_op[op_offset] = 200;
+
+        let mut bad_jump = MethodBuilder::new();
+        bad_jump.ops = vec![Op::LdcBool(true), Op::Brtrue(7), Op::LdcI8(1)];
+
+        let test_cases: &[(Vec<u8>, &str)] = &[
+            (vec![], "Invalid bytecode: Unexpected end of data at offset 0"),
+            (b"JUNK\x01\x00".to_vec(), "Invalid bytecode: The data doesn't start with the bytecode header"),
+            (newer_version, "Invalid bytecode: Version 5 is newer than the supported version 4"),
+            (older_version, "Invalid bytecode: Version 3 is older than the supported version 4, so it has to be compiled again"),
+            (bytes[..bytes.len() - 1].to_vec(), "Invalid bytecode: Unexpected end of data at offset 64"),
+            ([bytes.as_slice(), &[0]].concat(), "Invalid bytecode: Unexpected data after the method at offset 100"),
+            (unknown_op, "Invalid bytecode: Unknown op 200 at offset 59"),
+            (bad_jump.to_bytes(), "Invalid bytecode: Op 1 (Brtrue) jumps to 7, which isn't later in the method"),
+        ];
+
+        for (bytes, expected_message) in test_cases {
+            let err = MethodBuilder::from_bytes(bytes).err().unwrap();
+            assert_eq!(err.to_string(), *expected_message);
+        }
+    }
+
+    #[test]
+    fn from_bytes_should_reject_bytecode_from_older_versions() {
+        let calc = Calculator::new();
+        let bytes = calc.emit("x * 2 + 1").unwrap().to_bytes();
+
+        //The layout is the current one, so decoding it as an older version would otherwise succeed
+        for version in 1..BYTECODE_VERSION {
+            let mut older_version = bytes.clone();
+            older_version[4..6].copy_from_slice(&version.to_le_bytes());
+
+            let err = MethodBuilder::from_bytes(&older_version).err().unwrap();
+            assert!(matches!(err, CalcError::InvalidBytecode(_)), "for version {}", version);
+            assert!(err.to_string().contains("is older than the supported version"), "for version {}", version);
+        }
+    }
+}
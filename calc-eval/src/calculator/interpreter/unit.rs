@@ -0,0 +1,231 @@
+use std::fmt::Display;
+use crate::calculator::error::{runtime_error, Result};
+
+/// Exponents of the SI base dimensions: length, mass, time, current, temperature, amount of substance and luminous intensity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Dimension(pub [i32; 7]);
+
+impl Dimension {
+    pub fn is_dimensionless(&self) -> bool {
+        self.0.iter().all(|&exponent| exponent == 0)
+    }
+
+    fn combine(self, rhs: Dimension, combine: fn(i32, i32) -> Option<i32>) -> Result<Dimension> {
+        let mut result = self;
+        for (exponent, rhs_exponent) in result.0.iter_mut().zip(rhs.0) {
+            *exponent = combine(*exponent, rhs_exponent).ok_or(runtime_error!("Unit exponent overflow"))?;
+        }
+        Ok(result)
+    }
+}
+
+const LENGTH: [i32; 7] = [1, 0, 0, 0, 0, 0, 0];
+const MASS: [i32; 7] = [0, 1, 0, 0, 0, 0, 0];
+const TIME: [i32; 7] = [0, 0, 1, 0, 0, 0, 0];
+const CURRENT: [i32; 7] = [0, 0, 0, 1, 0, 0, 0];
+const TEMPERATURE: [i32; 7] = [0, 0, 0, 0, 1, 0, 0];
+const AMOUNT: [i32; 7] = [0, 0, 0, 0, 0, 1, 0];
+const LUMINOSITY: [i32; 7] = [0, 0, 0, 0, 0, 0, 1];
+const AREA: [i32; 7] = [2, 0, 0, 0, 0, 0, 0];
+const VOLUME: [i32; 7] = [3, 0, 0, 0, 0, 0, 0];
+const FREQUENCY: [i32; 7] = [0, 0, -1, 0, 0, 0, 0];
+const FORCE: [i32; 7] = [1, 1, -2, 0, 0, 0, 0];
+const PRESSURE: [i32; 7] = [-1, 1, -2, 0, 0, 0, 0];
+const ENERGY: [i32; 7] = [2, 1, -2, 0, 0, 0, 0];
+const POWER: [i32; 7] = [2, 1, -3, 0, 0, 0, 0];
+const CHARGE: [i32; 7] = [0, 0, 1, 1, 0, 0, 0];
+const VOLTAGE: [i32; 7] = [2, 1, -3, -1, 0, 0, 0];
+
+//Temperature scales with an offset, like Celsius, can't be expressed as a plain factor, so only kelvin is supported
+const UNITS: &[(&str, f64, [i32; 7])] = &[
+    ("m", 1.0, LENGTH),
+    ("km", 1e3, LENGTH),
+    ("cm", 1e-2, LENGTH),
+    ("mm", 1e-3, LENGTH),
+    ("um", 1e-6, LENGTH),
+    ("nm", 1e-9, LENGTH),
+    ("mi", 1609.344, LENGTH),
+    ("yd", 0.9144, LENGTH),
+    ("ft", 0.3048, LENGTH),
+    ("kg", 1.0, MASS),
+    ("g", 1e-3, MASS),
+    ("mg", 1e-6, MASS),
+    ("t", 1e3, MASS),
+    ("lb", 0.45359237, MASS),
+    ("oz", 0.028349523125, MASS),
+    ("s", 1.0, TIME),
+    ("ms", 1e-3, TIME),
+    ("us", 1e-6, TIME),
+    ("ns", 1e-9, TIME),
+    ("min", 60.0, TIME),
+    ("h", 3600.0, TIME),
+    ("day", 86400.0, TIME),
+    ("A", 1.0, CURRENT),
+    ("K", 1.0, TEMPERATURE),
+    ("mol", 1.0, AMOUNT),
+    ("cd", 1.0, LUMINOSITY),
+    ("ha", 1e4, AREA),
+    ("L", 1e-3, VOLUME),
+    ("mL", 1e-6, VOLUME),
+    ("Hz", 1.0, FREQUENCY),
+    ("N", 1.0, FORCE),
+    ("Pa", 1.0, PRESSURE),
+    ("J", 1.0, ENERGY),
+    ("kWh", 3.6e6, ENERGY),
+    ("W", 1.0, POWER),
+    ("kW", 1e3, POWER),
+    ("C", 1.0, CHARGE),
+    ("V", 1.0, VOLTAGE)
+];
+
+/// A unit of measure, along with the factor that converts a value in it to SI base units.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unit {
+    pub name: String,
+    pub scale: f64,
+    pub dimension: Dimension
+}
+
+impl Unit {
+    /// The unit of plain numbers. It has an empty name, so it disappears when combined with other units.
+    pub fn dimensionless() -> Self {
+        Self {
+            name: String::new(),
+            scale: 1.0,
+            dimension: Dimension::default()
+        }
+    }
+
+    pub fn lookup(name: &str) -> Option<Self> {
+        UNITS.iter()
+            .find(|&&(unit_name, _, _)| unit_name == name)
+            .map(|&(unit_name, scale, dimension)| Self {
+                name: unit_name.to_owned(),
+                scale,
+                dimension: Dimension(dimension)
+            })
+    }
+
+    pub fn is_dimensionless(&self) -> bool {
+        self.dimension.is_dimensionless()
+    }
+
+    pub fn mul(&self, rhs: &Unit) -> Result<Unit> {
+        if rhs.name.is_empty() {
+            return Ok(self.clone());
+        }
+        if self.name.is_empty() {
+            return Ok(rhs.clone());
+        }
+        if self.name == rhs.name {
+            return self.pow(2);
+        }
+
+        Ok(Unit {
+            name: format!("{}*{}", self.name, rhs.name),
+            scale: self.scale * rhs.scale,
+            dimension: self.dimension.combine(rhs.dimension, i32::checked_add)?
+        })
+    }
+
+    pub fn div(&self, rhs: &Unit) -> Result<Unit> {
+        if rhs.name.is_empty() {
+            return Ok(self.clone());
+        }
+
+        let lhs_name = if self.name.is_empty() { "1" } else { &self.name };
+        Ok(Unit {
+            name: format!("{}/{}", lhs_name, wrap_compound_name(&rhs.name, &['*', '/'])),
+            scale: self.scale / rhs.scale,
+            dimension: self.dimension.combine(rhs.dimension, i32::checked_sub)?
+        })
+    }
+
+    pub fn pow(&self, exponent: i32) -> Result<Unit> {
+        let mut dimension = self.dimension;
+        for dimension_exponent in dimension.0.iter_mut() {
+            *dimension_exponent = dimension_exponent.checked_mul(exponent).ok_or(runtime_error!("Exponent is too large"))?;
+        }
+
+        Ok(Unit {
+            name: if self.name.is_empty() { String::new() } else { format!("{}^{}", wrap_compound_name(&self.name, &['*', '/', '^']), exponent) },
+            scale: self.scale.powi(exponent),
+            dimension
+        })
+    }
+}
+
+fn wrap_compound_name(name: &str, separators: &[char]) -> String {
+    if name.contains(separators) {
+        format!("({})", name)
+    }
+    else {
+        name.to_owned()
+    }
+}
+
+impl Display for Unit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.name.is_empty() {
+            write!(f, "dimensionless")
+        }
+        else {
+            write!(f, "{}", self.name)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn units_should_combine_names_scales_and_dimensions() -> Result<()> {
+        let m = Unit::lookup("m").unwrap();
+        let km = Unit::lookup("km").unwrap();
+        let s = Unit::lookup("s").unwrap();
+        let h = Unit::lookup("h").unwrap();
+
+        let speed = km.div(&h)?;
+        assert_eq!(speed.name, "km/h");
+        assert_eq!(speed.dimension, m.div(&s)?.dimension);
+        assert_eq!(speed.scale, 1000.0 / 3600.0);
+
+        let acceleration = m.div(&s.pow(2)?)?;
+        assert_eq!(acceleration.name, "m/s^2");
+        assert_eq!(acceleration.dimension, Dimension([1, 0, -2, 0, 0, 0, 0]));
+
+        assert_eq!(km.mul(&km)?.name, "km^2");
+        assert_eq!(m.mul(&speed)?.name, "m*km/h");
+        assert_eq!(m.div(&m.mul(&s)?)?.name, "m/(m*s)");
+        assert_eq!(speed.pow(3)?.name, "(km/h)^3");
+        assert_eq!(Unit::dimensionless().div(&s)?.name, "1/s");
+        assert_eq!(Unit::dimensionless().mul(&s)?, s);
+
+        assert!(km.div(&m)?.is_dimensionless());
+        assert!(Unit::lookup("parsec").is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn units_should_report_exponent_overflow() -> Result<()> {
+        let m = Unit::lookup("m").unwrap();
+        let s = Unit::lookup("s").unwrap();
+        let high = m.pow(2000000000)?;
+        let low = m.pow(-2000000000)?;
+
+        let test_cases: &[(Result<Unit>, &str)] = &[
+            (high.mul(&high), "Exponent is too large"),
+            (high.mul(&m.mul(&s)?.pow(2000000000)?), "Unit exponent overflow"),
+            (high.div(&low), "Unit exponent overflow"),
+            (low.div(&high), "Unit exponent overflow"),
+        ];
+
+        for (result, expected_message) in test_cases {
+            assert_eq!(result.as_ref().unwrap_err().to_string(), *expected_message);
+        }
+
+        Ok(())
+    }
+}
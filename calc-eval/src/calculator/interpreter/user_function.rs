@@ -0,0 +1,16 @@
+use std::fmt::Display;
+use super::MethodBuilder;
+
+/// A function defined in the calculator itself, such as `f(x, y) = x * x + y`.
+pub struct UserFunction {
+    pub name: String,
+    pub parameters: Vec<String>,
+    /// The body, which refers to the parameters by their argument slot.
+    pub method: MethodBuilder
+}
+
+impl Display for UserFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}({})", self.name, self.parameters.join(", "))
+    }
+}
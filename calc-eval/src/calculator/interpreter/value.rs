@@ -0,0 +1,571 @@
+use std::{cmp::Ordering, fmt::Display};
+use num_bigint::BigInt;
+use num_complex::Complex64;
+use num_rational::BigRational;
+use num_traits::{Pow, ToPrimitive, Zero};
+use crate::calculator::error::{runtime_error, Result};
+use super::{Quantity, Unit};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Integer(i64),
+    Float(f64),
+    Rational(BigRational),
+    /// A number with a non-zero imaginary part. Results whose imaginary part is zero become floats.
+    Complex(Complex64),
+    Boolean(bool),
+    String(String),
+    Quantity(Quantity),
+    /// The elements of `[1, 2, 3]`. A matrix is a vector of rows.
+    Vector(Vec<Value>)
+}
+
+enum NumericPair {
+    Integers(i64, i64),
+    Rationals(BigRational, BigRational),
+    Floats(f64, f64),
+    Complexes(Complex64, Complex64)
+}
+
+impl Value {
+    pub fn get_type_name(&self) -> &'static str {
+        match self {
+            Self::Integer(_) => "integer",
+            Self::Float(_) => "float",
+            Self::Rational(_) => "rational",
+            Self::Complex(_) => "complex",
+            Self::Boolean(_) => "boolean",
+            Self::String(_) => "string",
+            Self::Quantity(_) => "quantity",
+            Self::Vector(_) => "vector"
+        }
+    }
+
+    pub fn to_f64(&self) -> Result<f64> {
+        match self {
+            Self::Integer(val) => Ok(*val as f64),
+            Self::Float(val) => Ok(*val),
+            Self::Rational(val) => Ok(val.to_f64().unwrap_or(f64::NAN)),
+            Self::Complex(_) => Err(runtime_error!("Expected a real number, found complex")),
+            _ => Err(runtime_error!("Expected a number, found {}", self.get_type_name()))
+        }
+    }
+
+    /// Integers, floats and rationals as a float, or `None` for anything else.
+    fn to_real(&self) -> Option<f64> {
+        match self {
+            Self::Integer(_) | Self::Float(_) | Self::Rational(_) => self.to_f64().ok(),
+            _ => None
+        }
+    }
+
+    pub fn to_complex(&self) -> Result<Complex64> {
+        match self {
+            Self::Complex(val) => Ok(*val),
+            _ => Ok(Complex64::new(self.to_f64()?, 0.0))
+        }
+    }
+
+    pub fn to_rational(&self) -> Result<BigRational> {
+        match self {
+            Self::Integer(val) => Ok(BigRational::from_integer(BigInt::from(*val))),
+            Self::Rational(val) => Ok(val.clone()),
+            //Going through the shortest round-trip decimal representation means 0.1 becomes exactly 1/10,
+            //rather than the binary fraction the f64 actually holds
+            Self::Float(val) if val.is_finite() => {
+                let repr = val.to_string();
+                let (int_part, frac_part) = repr.split_once('.').unwrap_or((&repr, ""));
+                let numer = format!("{}{}", int_part, frac_part).parse::<BigInt>().map_err(|err| runtime_error!("{}", err))?;
+                let denom = BigInt::from(10).pow(frac_part.len());
+                Ok(BigRational::new(numer, denom))
+            },
+            Self::Float(val) => Err(runtime_error!("{} can't be represented as a rational", val)),
+            _ => Err(runtime_error!("Expected a number, found {}", self.get_type_name()))
+        }
+    }
+
+    pub fn to_bool(&self) -> Result<bool> {
+        match self {
+            Self::Boolean(val) => Ok(*val),
+            _ => Err(runtime_error!("Expected a boolean, found {}", self.get_type_name()))
+        }
+    }
+
+    /// Plain numbers are treated as dimensionless quantities.
+    pub fn to_quantity(&self) -> Result<Quantity> {
+        match self {
+            Self::Quantity(val) => Ok(val.clone()),
+            _ => Ok(Quantity::new(self.to_f64()?, Unit::dimensionless()))
+        }
+    }
+
+    pub fn convert_to(&self, unit: &Unit) -> Result<Value> {
+        let quantity = self.to_quantity().map_err(|_| runtime_error!("Cannot convert {} to {}", self.get_type_name(), unit))?;
+        Ok(quantity.convert_to(unit)?.into_value())
+    }
+
+    //Only returns quantities when at least one side is a quantity, so plain arithmetic keeps its exact types
+    fn to_quantity_pair(&self, rhs: &Value, op: &str) -> Result<Option<(Quantity, Quantity)>> {
+        if !matches!(self, Self::Quantity(_)) && !matches!(rhs, Self::Quantity(_)) {
+            return Ok(None);
+        }
+
+        match (self.to_quantity(), rhs.to_quantity()) {
+            (Result::Ok(lhs), Result::Ok(rhs)) => Ok(Some((lhs, rhs))),
+            _ => Err(runtime_error!("Cannot apply '{}' to {} and {}", op, self.get_type_name(), rhs.get_type_name()))
+        }
+    }
+
+    //Vectors apply `op` to each pair of elements. When one side is nested less deeply, like a scalar or a row added to
+    //a matrix, it's applied to each element of the other side instead. Returns `None` when neither side is a vector.
+    fn broadcast(&self, rhs: &Value, op: &str, apply: fn(&Value, &Value) -> Result<Value>) -> Result<Option<Value>> {
+        let vals: Result<Vec<Value>> = match (self, rhs) {
+            (Self::Vector(lhs_vals), _) if self.get_depth() > rhs.get_depth() => lhs_vals.iter().map(|lhs| apply(lhs, rhs)).collect(),
+            (_, Self::Vector(rhs_vals)) if self.get_depth() < rhs.get_depth() => rhs_vals.iter().map(|rhs| apply(self, rhs)).collect(),
+            (Self::Vector(lhs), Self::Vector(rhs)) if lhs.len() != rhs.len() => {
+                return Err(runtime_error!("Cannot apply '{}' to vectors of length {} and {}", op, lhs.len(), rhs.len()));
+            },
+            (Self::Vector(lhs), Self::Vector(rhs)) => lhs.iter().zip(rhs.iter()).map(|(lhs, rhs)| apply(lhs, rhs)).collect(),
+            _ => return Ok(None)
+        };
+        Ok(Some(Self::Vector(vals?)))
+    }
+
+    //How many vectors are nested inside each other, going by the first element: 0 for a scalar, 2 for a matrix
+    fn get_depth(&self) -> usize {
+        match self {
+            Self::Vector(vals) => 1 + vals.first().map_or(0, Value::get_depth),
+            _ => 0
+        }
+    }
+
+    //Integers are promoted to rationals, rationals to floats and floats to complex numbers, but only as far as the other
+    //operand requires
+    fn to_numeric_pair(&self, rhs: &Value, op: &str) -> Result<NumericPair> {
+        match (self, rhs) {
+            (Self::Integer(lhs), Self::Integer(rhs)) => Ok(NumericPair::Integers(*lhs, *rhs)),
+            (Self::Integer(_) | Self::Rational(_), Self::Integer(_) | Self::Rational(_)) => Ok(NumericPair::Rationals(self.to_rational()?, rhs.to_rational()?)),
+            (Self::Complex(_), Self::Integer(_) | Self::Float(_) | Self::Rational(_) | Self::Complex(_)) |
+            (Self::Integer(_) | Self::Float(_) | Self::Rational(_), Self::Complex(_)) => Ok(NumericPair::Complexes(self.to_complex()?, rhs.to_complex()?)),
+            (Self::Integer(_) | Self::Float(_) | Self::Rational(_), Self::Integer(_) | Self::Float(_) | Self::Rational(_)) => Ok(NumericPair::Floats(self.to_f64()?, rhs.to_f64()?)),
+            _ => Err(runtime_error!("Cannot apply '{}' to {} and {}", op, self.get_type_name(), rhs.get_type_name()))
+        }
+    }
+
+    pub fn neg(&self) -> Result<Value> {
+        match self {
+            Self::Integer(val) => Ok(Self::Integer(val.checked_neg().ok_or(runtime_error!("Integer overflow"))?)),
+            Self::Float(val) => Ok(Self::Float(-val)),
+            Self::Rational(val) => Ok(Self::Rational(-val)),
+            Self::Complex(val) => Ok(Self::Complex(-val)),
+            Self::Quantity(val) => Ok(val.neg()),
+            Self::Vector(vals) => Ok(Self::Vector(vals.iter().map(Value::neg).collect::<Result<_>>()?)),
+            _ => Err(runtime_error!("Cannot apply '-' to {}", self.get_type_name()))
+        }
+    }
+
+    pub fn not(&self) -> Result<Value> {
+        match self {
+            Self::Boolean(val) => Ok(Self::Boolean(!val)),
+            _ => Err(runtime_error!("Cannot apply '!' to {}", self.get_type_name()))
+        }
+    }
+
+    pub fn add(&self, rhs: &Value) -> Result<Value> {
+        if let Some(val) = self.broadcast(rhs, "+", Value::add)? {
+            return Ok(val);
+        }
+        if let (Self::String(lhs), Self::String(rhs)) = (self, rhs) {
+            return Ok(Self::String(format!("{}{}", lhs, rhs)));
+        }
+
+        if let Some((lhs, rhs)) = self.to_quantity_pair(rhs, "+")? {
+            return lhs.add(&rhs);
+        }
+
+        match self.to_numeric_pair(rhs, "+")? {
+            NumericPair::Integers(lhs, rhs) => Ok(Self::Integer(lhs.checked_add(rhs).ok_or(runtime_error!("Integer overflow"))?)),
+            NumericPair::Rationals(lhs, rhs) => Ok(Self::Rational(lhs + rhs)),
+            NumericPair::Floats(lhs, rhs) => Ok(Self::Float(lhs + rhs)),
+            NumericPair::Complexes(lhs, rhs) => Ok(Self::from(lhs + rhs))
+        }
+    }
+
+    pub fn sub(&self, rhs: &Value) -> Result<Value> {
+        if let Some(val) = self.broadcast(rhs, "-", Value::sub)? {
+            return Ok(val);
+        }
+        if let Some((lhs, rhs)) = self.to_quantity_pair(rhs, "-")? {
+            return lhs.sub(&rhs);
+        }
+
+        match self.to_numeric_pair(rhs, "-")? {
+            NumericPair::Integers(lhs, rhs) => Ok(Self::Integer(lhs.checked_sub(rhs).ok_or(runtime_error!("Integer overflow"))?)),
+            NumericPair::Rationals(lhs, rhs) => Ok(Self::Rational(lhs - rhs)),
+            NumericPair::Floats(lhs, rhs) => Ok(Self::Float(lhs - rhs)),
+            NumericPair::Complexes(lhs, rhs) => Ok(Self::from(lhs - rhs))
+        }
+    }
+
+    pub fn mul(&self, rhs: &Value) -> Result<Value> {
+        if let Some(val) = self.broadcast(rhs, "*", Value::mul)? {
+            return Ok(val);
+        }
+        if let Some((lhs, rhs)) = self.to_quantity_pair(rhs, "*")? {
+            return lhs.mul(&rhs);
+        }
+        //Promoting the real operand would multiply an infinite part by its zero imaginary part, so it scales both parts
+        if let (Self::Complex(val), scale) | (scale, Self::Complex(val)) = (self, rhs) {
+            if let Some(scale) = scale.to_real() {
+                return Ok(Self::from(val * scale));
+            }
+        }
+
+        match self.to_numeric_pair(rhs, "*")? {
+            NumericPair::Integers(lhs, rhs) => Ok(Self::Integer(lhs.checked_mul(rhs).ok_or(runtime_error!("Integer overflow"))?)),
+            NumericPair::Rationals(lhs, rhs) => Ok(Self::Rational(lhs * rhs)),
+            NumericPair::Floats(lhs, rhs) => Ok(Self::Float(lhs * rhs)),
+            NumericPair::Complexes(lhs, rhs) => Ok(Self::from(lhs * rhs))
+        }
+    }
+
+    pub fn div(&self, rhs: &Value) -> Result<Value> {
+        if let Some(val) = self.broadcast(rhs, "/", Value::div)? {
+            return Ok(val);
+        }
+        if let Some((lhs, rhs)) = self.to_quantity_pair(rhs, "/")? {
+            return lhs.div(&rhs);
+        }
+        if let (Self::Complex(val), Some(divisor)) = (self, rhs.to_real()) {
+            return Ok(Self::from(val / divisor));
+        }
+
+        match self.to_numeric_pair(rhs, "/")? {
+            NumericPair::Integers(_, 0) => Err(runtime_error!("Division by zero")),
+            NumericPair::Integers(lhs, rhs) => Ok(Self::Integer(lhs.checked_div(rhs).ok_or(runtime_error!("Integer overflow"))?)),
+            NumericPair::Rationals(_, rhs) if rhs.is_zero() => Err(runtime_error!("Division by zero")),
+            NumericPair::Rationals(lhs, rhs) => Ok(Self::Rational(lhs / rhs)),
+            NumericPair::Floats(lhs, rhs) => Ok(Self::Float(lhs / rhs)),
+            NumericPair::Complexes(lhs, rhs) => Ok(Self::from(lhs / rhs))
+        }
+    }
+
+    pub fn rem(&self, rhs: &Value) -> Result<Value> {
+        if let Some((lhs, rhs)) = self.to_quantity_pair(rhs, "%")? {
+            return lhs.rem(&rhs);
+        }
+
+        match self.to_numeric_pair(rhs, "%")? {
+            NumericPair::Integers(_, 0) => Err(runtime_error!("Division by zero")),
+            NumericPair::Integers(lhs, rhs) => Ok(Self::Integer(lhs.checked_rem(rhs).ok_or(runtime_error!("Integer overflow"))?)),
+            NumericPair::Rationals(_, rhs) if rhs.is_zero() => Err(runtime_error!("Division by zero")),
+            NumericPair::Rationals(lhs, rhs) => Ok(Self::Rational(lhs % rhs)),
+            NumericPair::Floats(lhs, rhs) => Ok(Self::Float(lhs % rhs)),
+            NumericPair::Complexes(_, _) => Err(runtime_error!("Cannot apply '%' to complex numbers"))
+        }
+    }
+
+    pub fn pow(&self, rhs: &Value) -> Result<Value> {
+        if let Self::Quantity(lhs) = self {
+            return lhs.pow(rhs);
+        }
+
+        match self.to_numeric_pair(rhs, "^")? {
+            NumericPair::Integers(lhs, rhs) if rhs >= 0 => {
+                let exponent = u32::try_from(rhs).map_err(|_| runtime_error!("Integer overflow"))?;
+                Ok(Self::Integer(lhs.checked_pow(exponent).ok_or(runtime_error!("Integer overflow"))?))
+            },
+            NumericPair::Integers(lhs, rhs) => Ok(Self::Float((lhs as f64).powf(rhs as f64))),
+            //Only whole exponents keep a rational exact; anything else has to fall back to a float
+            NumericPair::Rationals(lhs, rhs) if rhs.is_integer() => {
+                let exponent = rhs.to_integer().to_i32().ok_or(runtime_error!("Exponent is too large"))?;
+                if lhs.is_zero() && exponent < 0 {
+                    return Err(runtime_error!("Division by zero"));
+                }
+                Ok(Self::Rational(lhs.pow(exponent)))
+            },
+            NumericPair::Rationals(lhs, rhs) => Ok(real_pow(lhs.to_f64().unwrap_or(f64::NAN), rhs.to_f64().unwrap_or(f64::NAN))),
+            NumericPair::Floats(lhs, rhs) => Ok(real_pow(lhs, rhs)),
+            //Whole exponents multiply out, which keeps i ^ 2 at exactly -1
+            NumericPair::Complexes(lhs, rhs) if rhs.im == 0.0 && rhs.re.fract() == 0.0 && rhs.re.abs() <= i32::MAX as f64 => {
+                Ok(Self::from(lhs.powi(rhs.re as i32)))
+            },
+            NumericPair::Complexes(lhs, rhs) => Ok(Self::from(lhs.powc(rhs)))
+        }
+    }
+
+    /// Indices start at zero, and have to be whole numbers.
+    pub fn index(&self, idx: &Value) -> Result<Value> {
+        let Self::Vector(vals) = self else {
+            return Err(runtime_error!("Cannot index into {}", self.get_type_name()));
+        };
+
+        let idx = idx.to_f64().map_err(|_| runtime_error!("Expected a whole number as the index, found {}", idx.get_type_name()))?;
+        if idx.fract() != 0.0 {
+            return Err(runtime_error!("Expected a whole number as the index, found {}", idx));
+        }
+        if idx < 0.0 || idx >= vals.len() as f64 {
+            return Err(runtime_error!("Index {} is out of range for a vector of length {}", idx, vals.len()));
+        }
+        Ok(vals[idx as usize].clone())
+    }
+
+    /// Values of different types are never equal, except for numbers which are compared numerically.
+    pub fn equals(&self, rhs: &Value) -> bool {
+        match (self, rhs) {
+            (Self::Boolean(lhs), Self::Boolean(rhs)) => lhs == rhs,
+            (Self::String(lhs), Self::String(rhs)) => lhs == rhs,
+            (Self::Vector(lhs), Self::Vector(rhs)) => lhs.len() == rhs.len() && lhs.iter().zip(rhs.iter()).all(|(lhs, rhs)| lhs.equals(rhs)),
+            (Self::Complex(_), _) | (_, Self::Complex(_)) => matches!(self.to_numeric_pair(rhs, "=="), Result::Ok(NumericPair::Complexes(lhs, rhs)) if lhs == rhs),
+            _ => matches!(self.compare(rhs, "=="), Result::Ok(Some(Ordering::Equal)))
+        }
+    }
+
+    /// Returns `None` when the values are unordered, which only happens when one of them is NaN.
+    pub fn compare(&self, rhs: &Value, op: &str) -> Result<Option<Ordering>> {
+        if let (Self::String(lhs), Self::String(rhs)) = (self, rhs) {
+            return Ok(Some(lhs.cmp(rhs)));
+        }
+        if let Some((lhs, rhs)) = self.to_quantity_pair(rhs, op)? {
+            return lhs.compare(&rhs, op);
+        }
+
+        match self.to_numeric_pair(rhs, op)? {
+            NumericPair::Integers(lhs, rhs) => Ok(Some(lhs.cmp(&rhs))),
+            NumericPair::Rationals(lhs, rhs) => Ok(Some(lhs.cmp(&rhs))),
+            NumericPair::Floats(lhs, rhs) => Ok(lhs.partial_cmp(&rhs)),
+            NumericPair::Complexes(_, _) => Err(runtime_error!("Cannot apply '{}' to complex numbers", op))
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Integer(val) => write!(f, "{}", val),
+            Self::Float(val) => write!(f, "{}", val),
+            Self::Rational(val) => write_rational(f, val),
+            Self::Complex(val) => write_complex(f, val),
+            Self::Boolean(val) => write!(f, "{}", val),
+            Self::String(val) => write!(f, "{}", val),
+            Self::Quantity(val) => write!(f, "{}", val),
+            Self::Vector(vals) => {
+                let vals = vals.iter().map(Value::to_string).collect::<Vec<_>>();
+                write!(f, "[{}]", vals.join(", "))
+            }
+        }
+    }
+}
+
+/// Whether `lhs ^ rhs` has no real result, because a negative number is raised to a fractional power.
+pub fn is_complex_power(lhs: f64, rhs: f64) -> bool {
+    lhs < 0.0 && rhs.is_finite() && rhs.fract() != 0.0
+}
+
+//A negative number raised to a fractional power is worked out over the complex numbers, like sqrt of a negative number.
+//Square roots go through sqrt itself, so (-8) ^ 0.5 is exactly sqrt(-8)
+fn real_pow(lhs: f64, rhs: f64) -> Value {
+    if !is_complex_power(lhs, rhs) {
+        return Value::Float(lhs.powf(rhs));
+    }
+
+    let lhs = Complex64::new(lhs, 0.0);
+    Value::from(if rhs == 0.5 { lhs.sqrt() } else { lhs.powf(rhs) })
+}
+
+//Rationals whose decimal expansion terminates are written out in full, anything else is written as a fraction
+fn write_rational(f: &mut std::fmt::Formatter<'_>, val: &BigRational) -> std::fmt::Result {
+    if val.is_integer() {
+        return write!(f, "{}", val.numer());
+    }
+
+    let mut denom = val.denom().clone();
+    let mut decimal_places = 0;
+    for factor in [2, 5] {
+        let factor = BigInt::from(factor);
+        let mut count = 0;
+        while (&denom % &factor).is_zero() {
+            denom /= &factor;
+            count += 1;
+        }
+        decimal_places = decimal_places.max(count);
+    }
+
+    if denom != BigInt::from(1) {
+        return write!(f, "{}/{}", val.numer(), val.denom());
+    }
+
+    let scaled = (val * BigRational::from_integer(BigInt::from(10).pow(decimal_places as u32))).to_integer();
+    let digits = scaled.magnitude().to_string();
+    let digits = format!("{:0>width$}", digits, width = decimal_places + 1);
+    let (int_digits, frac_digits) = digits.split_at(digits.len() - decimal_places);
+    let sign = if scaled < BigInt::zero() { "-" } else { "" };
+    write!(f, "{}{}.{}", sign, int_digits, frac_digits)
+}
+
+//Written the way they're typed in, like 3 - 4i
+fn write_complex(f: &mut std::fmt::Formatter<'_>, val: &Complex64) -> std::fmt::Result {
+    if val.re == 0.0 {
+        return write!(f, "{}i", val.im);
+    }
+
+    let sign = if val.im.is_sign_negative() { "-" } else { "+" };
+    write!(f, "{} {} {}i", val.re, sign, val.im.abs())
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Self::Integer(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+/// Complex numbers whose imaginary part is zero become floats.
+impl From<Complex64> for Value {
+    fn from(value: Complex64) -> Self {
+        if value.im == 0.0 {
+            Self::Float(value.re)
+        }
+        else {
+            Self::Complex(value)
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Self::Boolean(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arithmetic_should_keep_integers_exact_and_promote_to_float() -> Result<()> {
+        assert_eq!(Value::Integer(9007199254740993).add(&Value::Integer(2))?, Value::Integer(9007199254740995));
+        assert_eq!(Value::Integer(7).div(&Value::Integer(2))?, Value::Integer(3));
+        assert_eq!(Value::Integer(-7).rem(&Value::Integer(2))?, Value::Integer(-1));
+        assert_eq!(Value::Integer(7).div(&Value::Float(2.0))?, Value::Float(3.5));
+        assert_eq!(Value::Integer(2).pow(&Value::Integer(10))?, Value::Integer(1024));
+        assert_eq!(Value::Integer(2).pow(&Value::Integer(-1))?, Value::Float(0.5));
+        assert_eq!(Value::from("ab").add(&Value::from("cd"))?, Value::from("abcd"));
+
+        assert!(Value::Integer(i64::MAX).add(&Value::Integer(1)).is_err());
+        assert!(Value::Integer(i64::MIN).neg().is_err());
+        assert!(Value::Integer(2).pow(&Value::Integer(64)).is_err());
+        assert!(Value::Integer(1).div(&Value::Integer(0)).is_err());
+        assert!(Value::Integer(1).add(&Value::Boolean(true)).is_err());
+        assert!(Value::from("a").sub(&Value::from("b")).is_err());
+
+        assert!(Value::Integer(2).equals(&Value::Float(2.0)));
+        assert!(!Value::Integer(1).equals(&Value::Boolean(true)));
+        assert_eq!(Value::from("a").compare(&Value::from("b"), "<")?, Some(Ordering::Less));
+        assert_eq!(Value::Float(f64::NAN).compare(&Value::Integer(1), "<")?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn vector_arithmetic_should_broadcast() -> Result<()> {
+        let vector = |vals: &[i64]| Value::Vector(vals.iter().map(|&val| Value::Integer(val)).collect());
+        let matrix = Value::Vector(vec![vector(&[1, 2]), vector(&[3, 4])]);
+
+        assert_eq!(vector(&[1, 2]).add(&vector(&[10, 20]))?, vector(&[11, 22]));
+        assert_eq!(vector(&[1, 2]).mul(&Value::Integer(3))?, vector(&[3, 6]));
+        assert_eq!(Value::Integer(12).div(&vector(&[3, 4]))?, vector(&[4, 3]));
+        assert_eq!(matrix.sub(&vector(&[1, 1]))?.to_string(), "[[0, 1], [2, 3]]");
+        assert_eq!(matrix.neg()?.to_string(), "[[-1, -2], [-3, -4]]");
+        assert_eq!(matrix.index(&Value::Integer(1))?.index(&Value::Integer(0))?, Value::Integer(3));
+
+        assert!(vector(&[1, 2]).equals(&vector(&[1, 2])));
+        assert!(!vector(&[1, 2]).equals(&vector(&[1, 2, 3])));
+        assert!(vector(&[1, 2]).add(&vector(&[1, 2, 3])).is_err());
+        assert!(vector(&[1, 2]).index(&Value::Integer(2)).is_err());
+        assert!(vector(&[1, 2]).index(&Value::Float(0.5)).is_err());
+        assert!(Value::Integer(1).index(&Value::Integer(0)).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn rational_arithmetic_should_be_exact() -> Result<()> {
+        let tenth = Value::Rational(Value::Float(0.1).to_rational()?);
+        let fifth = Value::Rational(Value::Float(0.2).to_rational()?);
+        let sum = tenth.add(&fifth)?;
+        assert!(sum.equals(&Value::Rational(Value::Float(0.3).to_rational()?)));
+        assert_eq!(sum.to_string(), "0.3");
+
+        let third = Value::Rational(BigRational::new(1.into(), 3.into()));
+        assert_eq!(third.to_string(), "1/3");
+        assert_eq!(third.mul(&Value::Integer(3))?.to_string(), "1");
+        assert_eq!(third.pow(&Value::Integer(-2))?.to_string(), "9");
+        assert_eq!(Value::Rational(Value::Float(-0.05).to_rational()?).to_string(), "-0.05");
+        assert_eq!(Value::Rational(Value::Float(12.5).to_rational()?).to_string(), "12.5");
+        assert_eq!(third.add(&Value::Float(0.5))?, Value::Float(1.0 / 3.0 + 0.5));
+
+        assert!(third.div(&Value::Integer(0)).is_err());
+        assert!(Value::Float(f64::INFINITY).to_rational().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn complex_arithmetic_should_promote_real_operands() -> Result<()> {
+        let i = Value::Complex(Complex64::i());
+        let z = Value::Integer(3).add(&Value::Integer(4).mul(&i)?)?;
+        assert_eq!(z, Value::Complex(Complex64::new(3.0, 4.0)));
+        assert_eq!(z.to_string(), "3 + 4i");
+        assert_eq!(z.neg()?.sub(&i)?.to_string(), "-3 - 5i");
+        assert_eq!(i.mul(&Value::Float(-2.5))?.to_string(), "-2.5i");
+
+        assert_eq!(i.mul(&i)?, Value::Float(-1.0));
+        assert_eq!(i.pow(&Value::Integer(2))?, Value::Float(-1.0));
+        assert_eq!(z.div(&i)?, Value::Complex(Complex64::new(4.0, -3.0)));
+        let half = Value::Rational(BigRational::new(1.into(), 2.into()));
+        assert_eq!(half.add(&i)?, Value::Complex(Complex64::new(0.5, 1.0)));
+
+        assert!(z.equals(&Value::Complex(Complex64::new(3.0, 4.0))));
+        assert!(!z.equals(&Value::Integer(3)));
+        assert!(z.rem(&Value::Integer(2)).is_err());
+        assert!(z.compare(&Value::Integer(1), "<").is_err());
+        assert!(z.to_f64().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn complex_arithmetic_should_scale_by_real_operands_directly() -> Result<()> {
+        let infinite = Value::Complex(Complex64::new(0.0, f64::INFINITY));
+        let mixed = Value::Complex(Complex64::new(-1.0, f64::NEG_INFINITY));
+        let half = Value::Rational(BigRational::new(1.into(), 2.into()));
+
+        let test_cases: &[(Result<Value>, Complex64)] = &[
+            (infinite.mul(&Value::Integer(1)), Complex64::new(0.0, f64::INFINITY)),
+            (Value::Integer(1).mul(&infinite), Complex64::new(0.0, f64::INFINITY)),
+            (infinite.div(&Value::Integer(1)), Complex64::new(0.0, f64::INFINITY)),
+            (mixed.mul(&Value::Float(2.0)), Complex64::new(-2.0, f64::NEG_INFINITY)),
+            (mixed.div(&Value::Float(-2.0)), Complex64::new(0.5, f64::INFINITY)),
+            (half.mul(&mixed), Complex64::new(-0.5, f64::NEG_INFINITY)),
+            (mixed.div(&half), Complex64::new(-2.0, f64::NEG_INFINITY)),
+        ];
+
+        for (result, expected) in test_cases {
+            assert_eq!(result.as_ref().unwrap(), &Value::Complex(*expected));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,459 @@
+use std::collections::HashMap;
+use crate::calculator::error::{runtime_error, CalcError, Result};
+use super::{value::is_complex_power, verify, Environment, FunctionRegistry, MethodBuilder, Op, Value};
+
+/// One value per row being evaluated.
+#[derive(Clone)]
+enum Column {
+    Integers(Vec<i64>),
+    Numbers(Vec<f64>),
+    Booleans(Vec<bool>),
+    /// Rows that don't all hold the same kind of value, like the results of `max(x, 1)`. Ops over these run one row at
+    /// a time.
+    Values(Vec<Value>)
+}
+
+impl Column {
+    fn broadcast(val: &Value, len: usize) -> Self {
+        Self::from_values(vec![val.clone(); len])
+    }
+
+    /// Builds a column from the result for each row, keeping integers exact.
+    fn from_values(vals: Vec<Value>) -> Self {
+        if vals.iter().all(|val| matches!(val, Value::Integer(_))) {
+            Self::Integers(vals.iter().map(|val| if let Value::Integer(num) = val { *num } else { unreachable!() }).collect())
+        }
+        else if vals.iter().all(|val| matches!(val, Value::Float(_))) {
+            Self::Numbers(vals.iter().map(|val| if let Value::Float(num) = val { *num } else { unreachable!() }).collect())
+        }
+        else if vals.iter().all(|val| matches!(val, Value::Boolean(_))) {
+            Self::Booleans(vals.iter().map(|val| matches!(val, Value::Boolean(true))).collect())
+        }
+        else {
+            Self::Values(vals)
+        }
+    }
+
+    fn get(&self, pos: usize) -> Value {
+        match self {
+            Self::Integers(vals) => Value::Integer(vals[pos]),
+            Self::Numbers(vals) => Value::Float(vals[pos]),
+            Self::Booleans(vals) => Value::Boolean(vals[pos]),
+            Self::Values(vals) => vals[pos].clone()
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Self::Integers(vals) => vals.len(),
+            Self::Numbers(vals) => vals.len(),
+            Self::Booleans(vals) => vals.len(),
+            Self::Values(vals) => vals.len()
+        }
+    }
+
+    /// Keeps only the rows at `positions`.
+    fn select(&self, positions: &[usize]) -> Self {
+        match self {
+            Self::Integers(vals) => Self::Integers(positions.iter().map(|&pos| vals[pos]).collect()),
+            Self::Numbers(vals) => Self::Numbers(positions.iter().map(|&pos| vals[pos]).collect()),
+            Self::Booleans(vals) => Self::Booleans(positions.iter().map(|&pos| vals[pos]).collect()),
+            Self::Values(vals) => Self::Values(positions.iter().map(|&pos| vals[pos].clone()).collect())
+        }
+    }
+
+    /// Integers mixed with floats are converted to floats, the same way the scalar ops convert them.
+    fn to_floats(&self) -> Option<Vec<f64>> {
+        match self {
+            Self::Integers(vals) => Some(vals.iter().map(|&val| val as f64).collect()),
+            Self::Numbers(vals) => Some(vals.clone()),
+            _ => None
+        }
+    }
+}
+
+/// Evaluates a method over whole columns of inputs at once, so each op runs over a slice instead of a single value.
+///
+/// The result for each row is what `Interpreter` in float mode gives when each column is bound to a `Value::Float`, errors
+/// included: integers stay exact, so `7 / 2` is `3` and `1 / 0` fails just as it does there. Ops over floats and
+/// booleans run over whole slices, and anything else falls back to the scalar op one row at a time. Strings, units,
+/// assignments and user functions are rejected before anything runs. Where a branch splits the rows, each side carries
+/// on with just the rows that took it.
+pub struct VectorInterpreter {
+    functions: FunctionRegistry
+}
+
+impl VectorInterpreter {
+    pub fn new() -> Self {
+        Self {
+            functions: FunctionRegistry::with_standard_library()
+        }
+    }
+
+    /// Evaluates `method` once per row of `columns`, which must all be the same length. Variables that aren't columns
+    /// are read from `environment` and have the same value in every row.
+    pub fn evaluate_method(&self, method: &MethodBuilder, columns: &HashMap<&str, &[f64]>, environment: &Environment) -> Result<Vec<f64>> {
+        verify(method)?;
+        self.check_supported(method)?;
+
+        let mut row_count = None;
+        for (name, column) in columns.iter() {
+            match row_count {
+                None => row_count = Some((*name, column.len())),
+                Some((first_name, len)) if len != column.len() => {
+                    return Err(runtime_error!("Column '{}' has {} rows, but column '{}' has {}", name, column.len(), first_name, len));
+                },
+                Some(_) => { }
+            }
+        }
+        let Some((_, row_count)) = row_count else {
+            return Err(runtime_error!("At least one column is needed to know how many rows to evaluate"));
+        };
+
+        let mut output = vec![0.0; row_count];
+        if row_count > 0 {
+            if let Err(err) = self.run(method, 0, vec![], (0..row_count).collect(), columns, environment, &mut output) {
+                //Each op runs over every row, and branches run one side before the other, so a later row can fail
+                //first. Running the rows one at a time finds the error the first row to fail gives
+                for row in 0..row_count {
+                    self.run(method, 0, vec![], vec![row], columns, environment, &mut output)?;
+                }
+                return Err(err);
+            }
+        }
+        Ok(output)
+    }
+
+    fn check_supported(&self, method: &MethodBuilder) -> Result<()> {
+        for (op_idx, op) in method.ops.iter().enumerate() {
+            let unsupported = match *op {
+                Op::Ldstr(_) => "Strings",
+                Op::LdcImag(_) => "Complex numbers",
+                Op::Newvec(_) => "Vectors",
+                Op::Stvar(_) | Op::Starg(_) => "Assignments",
+                Op::Defun(_) => "Function definitions",
+                Op::Apply(_, _, _) => "Higher-order functions",
+                Op::Unit(_) | Op::Convert(_) => "Units",
+                Op::Call(name_idx, _) if self.functions.get(&method.names[name_idx]).is_none() => "User functions",
+                _ => continue
+            };
+
+            let err = runtime_error!("{} can't be evaluated over columns", unsupported);
+            return Err(Self::locate_error(err, method, op_idx));
+        }
+
+        Ok(())
+    }
+
+    fn locate_error(err: CalcError, method: &MethodBuilder, op_idx: usize) -> CalcError {
+        match method.get_span(op_idx) {
+            Some(span) => err.with_span(span),
+            None => err
+        }
+    }
+
+    /// Runs `method` from `ip` for the rows in `rows`, writing each row's result into `output`.
+    #[allow(clippy::too_many_arguments)]
+    fn run(&self, method: &MethodBuilder, mut ip: usize, mut stack: Vec<Column>, mut rows: Vec<usize>, columns: &HashMap<&str, &[f64]>, environment: &Environment, output: &mut [f64]) -> Result<()> {
+        while ip < method.ops.len() {
+            let op_idx = ip;
+            ip += 1;
+
+            match method.ops[op_idx] {
+                Op::Br(target) => ip = target,
+                op @ (Op::Brtrue(target) | Op::Brfalse(target)) => {
+                    let condition = match pop(&mut stack) {
+                        Column::Booleans(vals) => vals,
+                        column => {
+                            let vals = (0..column.len()).map(|pos| column.get(pos).to_bool()).collect::<Result<Vec<_>>>();
+                            vals.map_err(|err| Self::locate_error(err, method, op_idx))?
+                        }
+                    };
+
+                    let jump_when = matches!(op, Op::Brtrue(_));
+                    let (jumping, staying): (Vec<usize>, Vec<usize>) = (0..rows.len()).partition(|&pos| condition[pos] == jump_when);
+                    if staying.is_empty() {
+                        ip = target;
+                    }
+                    else if !jumping.is_empty() {
+                        let jump_stack = stack.iter().map(|column| column.select(&jumping)).collect();
+                        let jump_rows = jumping.iter().map(|&pos| rows[pos]).collect();
+                        self.run(method, target, jump_stack, jump_rows, columns, environment, output)?;
+
+                        stack = stack.iter().map(|column| column.select(&staying)).collect();
+                        rows = staying.iter().map(|&pos| rows[pos]).collect();
+                    }
+                },
+                op => {
+                    self.execute_op(method, op, &mut stack, &rows, columns, environment)
+                        .map_err(|err| Self::locate_error(err, method, op_idx))?;
+                }
+            }
+        }
+
+        let column = pop(&mut stack);
+        if let Column::Booleans(_) = column {
+            return Err(runtime_error!("Expected the expression to produce numbers, but it produced booleans"));
+        }
+        for (pos, &row) in rows.iter().enumerate() {
+            output[row] = column.get(pos).to_f64()?;
+        }
+        Ok(())
+    }
+
+    fn execute_op(&self, method: &MethodBuilder, op: Op, stack: &mut Vec<Column>, rows: &[usize], columns: &HashMap<&str, &[f64]>, environment: &Environment) -> Result<()> {
+        match op {
+            Op::LdcI8(num) => stack.push(Column::Integers(vec![num; rows.len()])),
+            Op::LdcF8(num) => stack.push(Column::Numbers(vec![num; rows.len()])),
+            Op::LdcBool(val) => stack.push(Column::Booleans(vec![val; rows.len()])),
+            Op::Ldvar(name_idx) => {
+                let name = &method.names[name_idx];
+                match columns.get(name.as_str()) {
+                    Some(column) => stack.push(Column::Numbers(rows.iter().map(|&row| column[row]).collect())),
+                    None => {
+                        let val = environment.get_variable(name).ok_or_else(|| runtime_error!("Unknown variable: {}", name))?;
+                        stack.push(Column::broadcast(val, rows.len()));
+                    }
+                }
+            },
+            Op::Call(name_idx, arity) => {
+                let name = &method.names[name_idx];
+                let function = self.functions.get(name).ok_or_else(|| runtime_error!("Unknown function: {}", name))?;
+                function.check_arity(name, arity)?;
+
+                let args = stack.split_off(stack.len() - arity);
+                let vals = (0..rows.len())
+                    .map(|pos| (function.body)(&args.iter().map(|arg| arg.get(pos)).collect::<Vec<_>>()))
+                    .collect::<Result<Vec<_>>>()?;
+                stack.push(Column::from_values(vals));
+            },
+            Op::Dup => {
+                let column = stack.last().expect("verified bytecode never underflows the stack").clone();
+                stack.push(column);
+            },
+            Op::Pop => {
+                pop(stack);
+            },
+            op if op.is_unary_operator() => {
+                let column = pop(stack);
+                stack.push(match (op, column) {
+                    (Op::Neg, Column::Numbers(vals)) => Column::Numbers(vals.into_iter().map(|val| -val).collect()),
+                    (Op::Not, Column::Booleans(vals)) => Column::Booleans(vals.into_iter().map(|val| !val).collect()),
+                    (op, column) => Column::from_values((0..column.len()).map(|pos| op.apply_unary(&column.get(pos))).collect::<Result<_>>()?)
+                });
+            },
+            op => {
+                let rhs = pop(stack);
+                let lhs = pop(stack);
+                stack.push(apply_binary(op, lhs, rhs)?);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn apply_binary(op: Op, lhs: Column, rhs: Column) -> Result<Column> {
+    fn zip<T: Copy, U>(lhs: &[T], rhs: &[T], f: impl Fn(T, T) -> U) -> Vec<U> {
+        lhs.iter().zip(rhs.iter()).map(|(&lhs, &rhs)| f(lhs, rhs)).collect()
+    }
+
+    if let (Column::Booleans(lhs), Column::Booleans(rhs)) = (&lhs, &rhs) {
+        match op {
+            Op::Ceq => return Ok(Column::Booleans(zip(lhs, rhs, |lhs, rhs| lhs == rhs))),
+            Op::Cne => return Ok(Column::Booleans(zip(lhs, rhs, |lhs, rhs| lhs != rhs))),
+            _ => {}
+        }
+    }
+
+    //Only float arithmetic can't fail, so integers on both sides go through the scalar ops to catch overflow and division
+    //by zero
+    let is_float_op = matches!((&lhs, &rhs), (Column::Numbers(_), Column::Numbers(_) | Column::Integers(_)) | (Column::Integers(_), Column::Numbers(_)));
+    if let (true, Some(lhs), Some(rhs)) = (is_float_op, lhs.to_floats(), rhs.to_floats()) {
+        let column = match op {
+            Op::Add => Some(Column::Numbers(zip(&lhs, &rhs, |lhs, rhs| lhs + rhs))),
+            Op::Sub => Some(Column::Numbers(zip(&lhs, &rhs, |lhs, rhs| lhs - rhs))),
+            Op::Mul => Some(Column::Numbers(zip(&lhs, &rhs, |lhs, rhs| lhs * rhs))),
+            Op::Div => Some(Column::Numbers(zip(&lhs, &rhs, |lhs, rhs| lhs / rhs))),
+            Op::Rem => Some(Column::Numbers(zip(&lhs, &rhs, |lhs, rhs| lhs % rhs))),
+            Op::Pow if !lhs.iter().zip(rhs.iter()).any(|(&lhs, &rhs)| is_complex_power(lhs, rhs)) => Some(Column::Numbers(zip(&lhs, &rhs, f64::powf))),
+            Op::Ceq => Some(Column::Booleans(zip(&lhs, &rhs, |lhs, rhs| lhs == rhs))),
+            Op::Cne => Some(Column::Booleans(zip(&lhs, &rhs, |lhs, rhs| lhs != rhs))),
+            Op::Clt => Some(Column::Booleans(zip(&lhs, &rhs, |lhs, rhs| lhs < rhs))),
+            Op::Cle => Some(Column::Booleans(zip(&lhs, &rhs, |lhs, rhs| lhs <= rhs))),
+            Op::Cgt => Some(Column::Booleans(zip(&lhs, &rhs, |lhs, rhs| lhs > rhs))),
+            Op::Cge => Some(Column::Booleans(zip(&lhs, &rhs, |lhs, rhs| lhs >= rhs))),
+            _ => None
+        };
+        if let Some(column) = column {
+            return Ok(column);
+        }
+    }
+
+    //Anything else runs one row at a time, so it gives the same results and errors as the scalar op
+    let vals = (0..lhs.len()).map(|pos| op.apply_binary(&lhs.get(pos), &rhs.get(pos))).collect::<Result<_>>()?;
+    Ok(Column::from_values(vals))
+}
+
+//Verification guarantees every op finds the columns it needs on the stack
+fn pop(stack: &mut Vec<Column>) -> Column {
+    stack.pop().expect("verified bytecode never underflows the stack")
+}
+
+impl Default for VectorInterpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calculator::{tokenizer::Span, Calculator};
+    use super::*;
+
+    #[test]
+    fn evaluate_columns_should_match_evaluating_each_row() -> Result<()> {
+        let mut calc = Calculator::new();
+        calc.eval("scale = 3")?;
+        calc.eval("limit = 2")?;
+        calc.eval("count = 7")?;
+
+        let x: &[f64] = &[-2.0, -0.5, 0.0, 1.0, 2.5, 4.0, f64::NAN];
+        let y: &[f64] = &[3.0, 0.0, -1.0, 2.0, 2.5, -4.0, 1.0];
+        let columns = HashMap::from([("x", x), ("y", y)]);
+
+        let test_cases = [
+            "x + y * 2 - 1",
+            "-x ^ 2 / y % 3",
+            "x * scale + limit",
+            "x > y ? x - y : y - x",
+            "x < 0 ? -1 : x == 0 ? 0 : 1",
+            "x > 0 && y > 0 ? 1 : 0",
+            "x > limit || !(y != 2.5) ? x : y",
+            "max(x, y) + min(x, 1) * abs(y)",
+            "sqrt(abs(x)) + round(y / 3)",
+            "(x > 0 ? x : y) * 2",
+            "count / 2 + x",
+            "7 / 2 * x + count % limit",
+            "max(x, 1) / 2 + min(y, 2) ^ 2",
+            "x > 0 ? count / limit : x",
+            "x / (count - 7)",
+            "y / 0",
+            "x + 1 / 0",
+            "x > 1 ? x : count / (limit - 2)",
+            "x + count % 0",
+            "x + 9223372036854775807 * count",
+            "x + -count ^ 3",
+            "(x - 5) ^ 0.5",
+            "abs(x) ^ 0.5 + x ^ 2",
+            "x > 0 ? 1 / 0 : 2 % 0",
+            "x < 0 ? 1 / 0 : 2 % 0",
+            "y / (x > 0 ? 0 : count - 7) + count % (x > 3 ? 0 : 1)",
+        ];
+
+        for formula in test_cases {
+            let expr = calc.compile(formula)?;
+            let result = expr.evaluate_columns(&columns);
+
+            //The column result fails if any row does, with the error of the first row to fail, location included
+            let expected = (0..x.len())
+                .map(|row| {
+                    let bindings = HashMap::from([
+                        (String::from("x"), Value::Float(x[row])),
+                        (String::from("y"), Value::Float(y[row]))
+                    ]);
+                    expr.evaluate(&bindings)?.to_f64()
+                })
+                .collect::<Result<Vec<_>>>();
+
+            match (result, expected) {
+                (Ok(vals), Ok(expected)) => {
+                    for (row, (val, expected)) in vals.into_iter().zip(expected).enumerate() {
+                        assert!(val.to_bits() == expected.to_bits() || (val.is_nan() && expected.is_nan()), "{} gave {} for row {}, expected {}", formula, val, row, expected);
+                    }
+                },
+                (Err(err), Err(expected)) => assert_eq!(err.render(formula), expected.render(formula), "for {}", formula),
+                (result, expected) => panic!("{} gave {:?}, expected {:?}", formula, result, expected)
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn evaluate_columns_should_keep_integer_arithmetic_exact() -> Result<()> {
+        let mut calc = Calculator::new();
+        calc.eval("count = 7")?;
+        calc.eval("big = 9007199254740993")?;
+
+        let x: &[f64] = &[0.0, 0.5];
+        let columns = HashMap::from([("x", x)]);
+
+        //Integers divide with truncation and keep every bit, where floats would give 3.5 and lose the last bit of `big`
+        assert_eq!(calc.evaluate_columns("count / 2 + x", &columns)?, [3.0, 3.5]);
+        assert_eq!(calc.evaluate_columns("big - (big - 1) + x", &columns)?, [1.0, 1.5]);
+
+        //Dividing an integer by zero is an error rather than infinity, like it is for a single row
+        let err = calc.evaluate_columns("x + count / 0", &columns).unwrap_err();
+        assert_eq!(err.to_string(), "Division by zero");
+
+        Ok(())
+    }
+
+    #[test]
+    fn evaluate_columns_should_report_the_error_of_the_first_failing_row() -> Result<()> {
+        let calc = Calculator::new();
+        let x: &[f64] = &[1.0, -1.0];
+
+        //The rows that jump to the `else` side run first, but row 0 fails on the `then` side
+        let formula = "x > 0 ? 1 / 0 : 2 % 0";
+        let err = calc.evaluate_columns(formula, &HashMap::from([("x", x)])).unwrap_err();
+        assert_eq!(err.get_span(), Some(Span::new(8, 13)));
+
+        //Row 1 fails at the first op, but row 0 already failed at the second one
+        let formula = "(x < 0 ? 1 / 0 : 1) + (x > 0 ? 2 % 0 : 1)";
+        let err = calc.evaluate_columns(formula, &HashMap::from([("x", x)])).unwrap_err();
+        assert_eq!(err.render(formula), "Division by zero\n  (x < 0 ? 1 / 0 : 1) + (x > 0 ? 2 % 0 : 1)\n                                 ^^^^^");
+
+        Ok(())
+    }
+
+    #[test]
+    fn evaluate_columns_should_reject_what_it_cannot_evaluate() -> Result<()> {
+        let mut calc = Calculator::new();
+        calc.eval("f(a) = a + 1")?;
+
+        let x: &[f64] = &[1.0, 2.0];
+        let short: &[f64] = &[1.0];
+
+        let columns = HashMap::from([("x", x)]);
+        let test_cases: &[(&str, &str)] = &[
+            ("f(x)", "User functions can't be evaluated over columns"),
+            ("x * 1 m in cm", "Units can't be evaluated over columns"),
+            ("x + 1 km", "Units can't be evaluated over columns"),
+            ("z = x", "Assignments can't be evaluated over columns"),
+            ("[x, 1][0]", "Vectors can't be evaluated over columns"),
+            ("solve(t - x, t, 0)", "Higher-order functions can't be evaluated over columns"),
+            ("x + \"a\"", "Strings can't be evaluated over columns"),
+            ("x + z", "Unknown variable: z"),
+            ("x > 1", "Expected the expression to produce numbers, but it produced booleans"),
+            ("x ? 1 : 2", "Expected a boolean, found float"),
+        ];
+
+        for &(formula, expected) in test_cases {
+            let err = match calc.compile(formula) {
+                Ok(expr) => expr.evaluate_columns(&columns).unwrap_err(),
+                Err(err) => err
+            };
+            assert_eq!(err.to_string(), expected, "for {}", formula);
+        }
+
+        let err = calc.evaluate_columns("1 + 2", &HashMap::new()).unwrap_err();
+        assert_eq!(err.to_string(), "At least one column is needed to know how many rows to evaluate");
+
+        let err = calc.evaluate_columns("x + y", &HashMap::from([("x", x), ("y", short)])).unwrap_err().to_string();
+        assert!(err.contains("rows, but column"), "{}", err);
+
+        Ok(())
+    }
+}
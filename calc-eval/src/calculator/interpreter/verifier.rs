@@ -0,0 +1,140 @@
+use crate::calculator::error::{CalcError, Result};
+use super::{MethodBuilder, Op};
+
+/// Checks that `method` and every function it defines can run without checking the stack or operands at each op,
+/// returning the deepest the stack gets while `method` runs.
+///
+/// Jumps may only go forward, which guarantees every method finishes. Each op must be reached with the same stack
+/// depth however it is reached, and the method must end with exactly one value on the stack.
+pub fn verify(method: &MethodBuilder) -> Result<usize> {
+    for function in method.functions.iter() {
+        verify(&function.method).map_err(|err| match err {
+            CalcError::InvalidBytecode(message) => CalcError::InvalidBytecode(format!("{} (in function '{}')", message, function.name)),
+            err => err
+        })?;
+    }
+
+    let mut depths = vec![None; method.ops.len()];
+    let mut max_depth = 0;
+    let mut pending = vec![(0, 0)];
+
+    while let Some((op_idx, depth)) = pending.pop() {
+        if op_idx == method.ops.len() {
+            if depth != 1 {
+                return Err(CalcError::InvalidBytecode(format!("The method ends with {} values on the stack instead of 1", depth)));
+            }
+            continue;
+        }
+
+        match depths[op_idx] {
+            Some(existing_depth) if existing_depth == depth => continue,
+            Some(existing_depth) => {
+                return Err(CalcError::InvalidBytecode(format!("Op {} is reached with both {} and {} values on the stack", op_idx, existing_depth, depth)));
+            },
+            None => depths[op_idx] = Some(depth)
+        }
+
+        let op = method.ops[op_idx];
+        verify_operands(method, op_idx, op)?;
+
+        let (pops, pushes) = op.get_stack_effect();
+        if depth < pops {
+            return Err(CalcError::InvalidBytecode(format!("Op {} ({}) needs {} values but the stack only has {}", op_idx, op.get_name(), pops, depth)));
+        }
+        let next_depth = depth - pops + pushes;
+        max_depth = max_depth.max(next_depth);
+
+        if let Some(target) = op.get_jump_target() {
+            if target <= op_idx || target > method.ops.len() {
+                return Err(CalcError::InvalidBytecode(format!("Op {} ({}) jumps to {}, which isn't later in the method", op_idx, op.get_name(), target)));
+            }
+            pending.push((target, next_depth));
+        }
+        if !matches!(op, Op::Br(_)) {
+            pending.push((op_idx + 1, next_depth));
+        }
+    }
+
+    Ok(max_depth)
+}
+
+fn verify_operands(method: &MethodBuilder, op_idx: usize, op: Op) -> Result<()> {
+    if let Op::Apply(name_idx, function_idx, arity) = op {
+        verify_operand(op_idx, op, name_idx, method.names.len(), "names")?;
+        verify_operand(op_idx, op, function_idx, method.functions.len(), "functions")?;
+
+        //The function's first parameter is supplied by the built-in, and every other one is captured from the stack
+        let parameter_count = method.functions[function_idx].parameters.len();
+        if parameter_count == 0 || parameter_count - 1 > arity {
+            return Err(CalcError::InvalidBytecode(format!("Op {} (Apply) passes {} values to a function with {} parameters", op_idx, arity, parameter_count)));
+        }
+        return Ok(());
+    }
+
+    let (idx, len, table) = match op {
+        Op::Ldstr(string_idx) => (string_idx, method.strings.len(), "strings"),
+        Op::Ldvar(name_idx) |
+        Op::Stvar(name_idx) |
+        Op::Call(name_idx, _) => (name_idx, method.names.len(), "names"),
+        Op::Ldarg(arg_idx) |
+        Op::Starg(arg_idx) => (arg_idx, method.parameters.len(), "parameters"),
+        Op::Defun(function_idx) => (function_idx, method.functions.len(), "functions"),
+        Op::Unit(unit_idx) |
+        Op::Convert(unit_idx) => (unit_idx, method.units.len(), "units"),
+        _ => return Ok(())
+    };
+
+    verify_operand(op_idx, op, idx, len, table)
+}
+
+fn verify_operand(op_idx: usize, op: Op, idx: usize, len: usize, table: &str) -> Result<()> {
+    if idx >= len {
+        return Err(CalcError::InvalidBytecode(format!("Op {} ({}) refers to {} {}, but there are only {}", op_idx, op.get_name(), table, idx, len)));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calculator::Calculator;
+    use super::*;
+
+    #[test]
+    fn verify_should_reject_malformed_methods() {
+        let calc = Calculator::new();
+
+        let test_cases: &[(&str, usize)] = &[
+            ("1", 1),
+            ("1 + 2 * x", 3),
+            ("a ? b : c && d", 2),
+            ("f(x, y) = max(x, y * 2)", 1),
+            ("x = 3 km in m", 2),
+        ];
+
+        for &(input, expected_depth) in test_cases {
+            let method = calc.emit(input).unwrap();
+            assert_eq!(verify(&method), Ok(expected_depth), "for input {:?}", input);
+        }
+
+        let test_cases: &[(&[Op], &str)] = &[
+            (&[], "The method ends with 0 values on the stack instead of 1"),
+            (&[Op::LdcI8(1), Op::LdcI8(2)], "The method ends with 2 values on the stack instead of 1"),
+            (&[Op::LdcI8(1), Op::Add], "Op 1 (Add) needs 2 values but the stack only has 1"),
+            (&[Op::Pop], "Op 0 (Pop) needs 1 values but the stack only has 0"),
+            (&[Op::LdcBool(true), Op::Brtrue(0), Op::LdcI8(1)], "Op 1 (Brtrue) jumps to 0, which isn't later in the method"),
+            (&[Op::LdcI8(1), Op::Br(5)], "Op 1 (Br) jumps to 5, which isn't later in the method"),
+            (&[Op::LdcBool(true), Op::Brtrue(3), Op::LdcI8(1), Op::LdcI8(2), Op::Add], "Op 3 is reached with both 1 and 0 values on the stack"),
+            (&[Op::Ldvar(0)], "Op 0 (Ldvar) refers to names 0, but there are only 0"),
+            (&[Op::Ldarg(1)], "Op 0 (Ldarg) refers to parameters 1, but there are only 0"),
+            (&[Op::LdcI8(1), Op::Apply(0, 0, 1)], "Op 1 (Apply) refers to names 0, but there are only 0"),
+        ];
+
+        for &(ops, expected_message) in test_cases {
+            let mut method = MethodBuilder::new();
+            method.ops = ops.to_vec();
+
+            assert_eq!(verify(&method), Err(CalcError::InvalidBytecode(expected_message.to_owned())), "for ops {:?}", ops);
+        }
+    }
+}
@@ -1,6 +1,12 @@
-mod interpreter;
-mod syntax;
+mod compiled_expression;
+mod diagnostic;
+mod error;
+pub mod interpreter;
+pub mod syntax;
 mod calculator;
-mod tokenizer;
+pub mod tokenizer;
 
 pub use calculator::Calculator;
+pub use compiled_expression::{Backend, CompiledExpression};
+pub use diagnostic::{Diagnostic, Diagnostics};
+pub use error::{CalcError, Result};
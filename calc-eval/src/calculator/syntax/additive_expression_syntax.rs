@@ -1,20 +1,24 @@
 use std::fmt::Display;
 use crate::calculator::{
-    interpreter::{MethodBuilder, Op},
-    tokenizer::Token
+    error::Result,
+    interpreter::{Closure, ClosureCompiler, MethodBuilder, Op},
+    tokenizer::Span
 };
 use super::{
     expression_syntax::{ExpressionPrecedence, ExpressionSyntax},
     multiplicative_expression_syntax::MultiplicativeExpressionSyntax,
+    parser::Parser,
+    simplifier,
     syntax::Syntax
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum AdditiveExpressionKind {
     Add,
     Subtract
 }
 
+#[derive(Clone)]
 pub struct AdditiveExpressionSyntax {
     kind: AdditiveExpressionKind,
     left_expr: Box<dyn ExpressionSyntax>,
@@ -22,15 +26,19 @@ pub struct AdditiveExpressionSyntax {
 }
 
 impl AdditiveExpressionSyntax {
-    pub fn try_parse_expression(tokens: &Vec<Token>, pos: &mut usize) -> Option<Box<dyn ExpressionSyntax>> {
-        let mut expr_opt = MultiplicativeExpressionSyntax::try_parse_expression(tokens, pos);
-        if expr_opt.is_none() {
-            return None
+    pub fn new(kind: AdditiveExpressionKind, left_expr: Box<dyn ExpressionSyntax>, right_expr: Box<dyn ExpressionSyntax>) -> Self {
+        Self {
+            kind,
+            left_expr,
+            right_expr
         }
+    }
+
+    pub fn parse_expression(parser: &mut Parser) -> Box<dyn ExpressionSyntax> {
+        let mut expr = MultiplicativeExpressionSyntax::parse_expression(parser);
 
-        let mut npos = *pos;
-        while npos < tokens.len() - 1 {
-            let token = &tokens[npos];
+        loop {
+            let token = parser.peek();
             let mut kind = None;
             if token.is_operator("+") {
                 kind = Some(AdditiveExpressionKind::Add);
@@ -43,22 +51,17 @@ impl AdditiveExpressionSyntax {
                 break;
             }
 
-            npos += 1;
-
-            let rhs_expr_opt = MultiplicativeExpressionSyntax::try_parse_expression(tokens, &mut npos);
-            if rhs_expr_opt.is_none() {
-                break;
-            }
+            parser.advance();
 
-            *pos = npos;
-            expr_opt = Some(Box::new(AdditiveExpressionSyntax {
+            let rhs_expr = MultiplicativeExpressionSyntax::parse_expression(parser);
+            expr = Box::new(AdditiveExpressionSyntax {
                 kind: kind.unwrap(),
-                left_expr: expr_opt.unwrap(),
-                right_expr: rhs_expr_opt.unwrap()
-            }));
+                left_expr: expr,
+                right_expr: rhs_expr
+            });
         }
 
-        expr_opt
+        expr
     }
 }
 
@@ -67,21 +70,61 @@ impl ExpressionSyntax for AdditiveExpressionSyntax {
         ExpressionPrecedence::Additive
     }
 
-    fn emit_bytecode(&self, method_builder: &mut MethodBuilder) -> anyhow::Result<()> {
+    fn get_span(&self) -> Span {
+        self.left_expr.get_span().merge(self.right_expr.get_span())
+    }
+
+    fn emit_bytecode(&self, method_builder: &mut MethodBuilder) -> Result<()> {
         self.left_expr.emit_bytecode(method_builder)?;
         self.right_expr.emit_bytecode(method_builder)?;
 
         match self.kind {
             AdditiveExpressionKind::Add => {
-                method_builder.ops.push(Op::Add);
+                method_builder.emit_spanned(Op::Add, self.get_span());
             },
             AdditiveExpressionKind::Subtract => {
-                method_builder.ops.push(Op::Sub);
+                method_builder.emit_spanned(Op::Sub, self.get_span());
             }
         }
 
         Ok(())
     }
+
+    fn compile_closure(&self, compiler: &ClosureCompiler) -> Result<Closure> {
+        let op = match self.kind {
+            AdditiveExpressionKind::Add => Op::Add,
+            AdditiveExpressionKind::Subtract => Op::Sub
+        };
+
+        let lhs = self.left_expr.compile_closure(compiler)?;
+        let rhs = self.right_expr.compile_closure(compiler)?;
+        Ok(compiler.binary(op, lhs, rhs, self.get_span()))
+    }
+
+    fn get_label(&self) -> String {
+        match self.kind {
+            AdditiveExpressionKind::Add => "+".to_owned(),
+            AdditiveExpressionKind::Subtract => "-".to_owned()
+        }
+    }
+
+    fn get_children(&self) -> Vec<&dyn ExpressionSyntax> {
+        vec![self.left_expr.as_ref(), self.right_expr.as_ref()]
+    }
+
+    fn derivative(&self, var: &str) -> Result<Box<dyn ExpressionSyntax>> {
+        let left_derivative = self.left_expr.derivative(var)?;
+        let right_derivative = self.right_expr.derivative(var)?;
+
+        match self.kind {
+            AdditiveExpressionKind::Add => Ok(simplifier::add(left_derivative, right_derivative)),
+            AdditiveExpressionKind::Subtract => Ok(simplifier::subtract(left_derivative, right_derivative))
+        }
+    }
+
+    fn clone_expression(&self) -> Box<dyn ExpressionSyntax> {
+        Box::new(self.clone())
+    }
 }
 
 impl Syntax for AdditiveExpressionSyntax { }
@@ -124,38 +167,35 @@ impl Display for AdditiveExpressionSyntax {
 #[cfg(test)]
 mod tests {
     use crate::calculator::{
-        syntax::try_parse_expression,
+        syntax::parse_expression,
         tokenizer::Tokenizer
     };
-    use anyhow::*;
     use super::*;
 
     #[test]
     fn emit_bytecode_should_emit_correct_bytecode() -> Result<()> {
         let test_cases: &[(&str, &[Op])] = &[
-            ("1+2", &[Op::LdcF8(1.0), Op::LdcF8(2.0), Op::Add]),
-            ("3-4", &[Op::LdcF8(3.0), Op::LdcF8(4.0), Op::Sub]),
-            ("1-2-3", &[Op::LdcF8(1.0), Op::LdcF8(2.0), Op::Sub, Op::LdcF8(3.0), Op::Sub]),
-            ("1-(2-3)", &[Op::LdcF8(1.0), Op::LdcF8(2.0), Op::LdcF8(3.0), Op::Sub, Op::Sub]),
-            ("-5+-3", &[Op::LdcF8(5.0), Op::Neg, Op::LdcF8(3.0), Op::Neg, Op::Add]),
-            ("-(4-6)", &[Op::LdcF8(4.0), Op::LdcF8(6.0), Op::Sub, Op::Neg]),
-            ("4+5*6", &[Op::LdcF8(4.0), Op::LdcF8(5.0), Op::LdcF8(6.0), Op::Mul, Op::Add]),
-            ("(4+5)*6", &[Op::LdcF8(4.0), Op::LdcF8(5.0), Op::Add, Op::LdcF8(6.0), Op::Mul]),
+            ("1+2", &[Op::LdcI8(1), Op::LdcI8(2), Op::Add]),
+            ("3-4", &[Op::LdcI8(3), Op::LdcI8(4), Op::Sub]),
+            ("1-2-3", &[Op::LdcI8(1), Op::LdcI8(2), Op::Sub, Op::LdcI8(3), Op::Sub]),
+            ("1-(2-3)", &[Op::LdcI8(1), Op::LdcI8(2), Op::LdcI8(3), Op::Sub, Op::Sub]),
+            ("-5+-3", &[Op::LdcI8(5), Op::Neg, Op::LdcI8(3), Op::Neg, Op::Add]),
+            ("-(4-6)", &[Op::LdcI8(4), Op::LdcI8(6), Op::Sub, Op::Neg]),
+            ("4+5*6", &[Op::LdcI8(4), Op::LdcI8(5), Op::LdcI8(6), Op::Mul, Op::Add]),
+            ("(4+5)*6", &[Op::LdcI8(4), Op::LdcI8(5), Op::Add, Op::LdcI8(6), Op::Mul]),
         ];
 
         let tokenizer = Tokenizer::new();
 
         for &(input, expected_ops) in test_cases {
-            let tokens = tokenizer.tokenize(input).collect();
+            let tokens = tokenizer.tokenize(input).collect::<Vec<_>>();
 
-            let mut pos = 0;
-            let expr = try_parse_expression(&tokens, &mut pos);
+            let result = parse_expression(&tokens);
 
-            assert!(expr.is_some());
-            assert_eq!(pos, tokens.len() - 1);
+            assert!(result.errors.is_empty());
 
             let mut method_builder = MethodBuilder::new();
-            expr.unwrap().emit_bytecode(&mut method_builder)?;
+            result.expr.emit_bytecode(&mut method_builder)?;
 
             assert_eq!(&method_builder.ops[..], expected_ops);
         }
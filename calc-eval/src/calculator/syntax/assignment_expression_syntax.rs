@@ -0,0 +1,142 @@
+use std::fmt::Display;
+use crate::calculator::{
+    error::{runtime_error, Result},
+    interpreter::{Closure, ClosureCompiler, MethodBuilder, Op},
+    tokenizer::{Span, Token}
+};
+use super::{
+    conversion_expression_syntax::ConversionExpressionSyntax,
+    expression_syntax::{ExpressionPrecedence, ExpressionSyntax},
+    parser::Parser,
+    syntax::Syntax
+};
+
+#[derive(Clone)]
+pub struct AssignmentExpressionSyntax {
+    identifier_token: Token,
+    value_expr: Box<dyn ExpressionSyntax>
+}
+
+impl AssignmentExpressionSyntax {
+    pub fn parse_expression(parser: &mut Parser) -> Box<dyn ExpressionSyntax> {
+        if parser.peek().is_identifier() && parser.peek_next().is_operator("=") {
+            let identifier_token = parser.advance().clone();
+            parser.advance();
+
+            let value_expr = parser.parse_nested(AssignmentExpressionSyntax::parse_expression);
+            return Box::new(AssignmentExpressionSyntax {
+                identifier_token,
+                value_expr
+            })
+        }
+
+        ConversionExpressionSyntax::parse_expression(parser)
+    }
+}
+
+impl ExpressionSyntax for AssignmentExpressionSyntax {
+    fn get_expression_precedence(&self) -> ExpressionPrecedence {
+        ExpressionPrecedence::Assignment
+    }
+
+    fn get_span(&self) -> Span {
+        self.identifier_token.span.merge(self.value_expr.get_span())
+    }
+
+    fn emit_bytecode(&self, method_builder: &mut MethodBuilder) -> Result<()> {
+        self.value_expr.emit_bytecode(method_builder)?;
+
+        method_builder.ops.push(Op::Dup);
+        if let Some(arg_idx) = method_builder.get_parameter_index(&self.identifier_token.source) {
+            method_builder.ops.push(Op::Starg(arg_idx));
+        }
+        else {
+            let name_idx = method_builder.intern_name(&self.identifier_token.source);
+            method_builder.ops.push(Op::Stvar(name_idx));
+        }
+
+        Ok(())
+    }
+
+    fn compile_closure(&self, compiler: &ClosureCompiler) -> Result<Closure> {
+        let value = self.value_expr.compile_closure(compiler)?;
+        let name = self.identifier_token.source.clone();
+
+        Ok(Box::new(move |context| {
+            let val = value(context)?;
+            context.environment.assign_variable(&name, val.clone())?;
+            Ok(val)
+        }))
+    }
+
+    fn get_label(&self) -> String {
+        format!("{} =", self.identifier_token.source)
+    }
+
+    fn get_children(&self) -> Vec<&dyn ExpressionSyntax> {
+        vec![self.value_expr.as_ref()]
+    }
+
+    fn derivative(&self, _var: &str) -> Result<Box<dyn ExpressionSyntax>> {
+        Err(runtime_error!("Assignments can't be differentiated").with_span(self.get_span()))
+    }
+
+    fn clone_expression(&self) -> Box<dyn ExpressionSyntax> {
+        Box::new(self.clone())
+    }
+}
+
+impl Syntax for AssignmentExpressionSyntax { }
+
+impl Display for AssignmentExpressionSyntax {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.identifier_token.repr(f)?;
+        write!(f, " = ")?;
+
+        let needs_parenthesis = self.value_expr.get_expression_precedence() > self.get_expression_precedence();
+        if needs_parenthesis {
+            write!(f, "({})", self.value_expr)?;
+        }
+        else {
+            write!(f, "{}", self.value_expr)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calculator::{
+        syntax::parse_expression,
+        tokenizer::Tokenizer
+    };
+    use super::*;
+
+    #[test]
+    fn emit_bytecode_should_emit_correct_bytecode() -> Result<()> {
+        let test_cases: &[(&str, &[Op])] = &[
+            ("x = 5", &[Op::LdcI8(5), Op::Dup, Op::Stvar(0)]),
+            ("x = x + 1", &[Op::Ldvar(0), Op::LdcI8(1), Op::Add, Op::Dup, Op::Stvar(0)]),
+            ("x = y = 2", &[Op::LdcI8(2), Op::Dup, Op::Stvar(0), Op::Dup, Op::Stvar(1)]),
+            ("(x = 3) * 4", &[Op::LdcI8(3), Op::Dup, Op::Stvar(0), Op::LdcI8(4), Op::Mul]),
+        ];
+
+        let tokenizer = Tokenizer::new();
+
+        for &(input, expected_ops) in test_cases {
+            let tokens = tokenizer.tokenize(input).collect::<Vec<_>>();
+
+            let result = parse_expression(&tokens);
+
+            assert!(result.errors.is_empty());
+
+            let mut method_builder = MethodBuilder::new();
+            result.expr.emit_bytecode(&mut method_builder)?;
+
+            assert_eq!(&method_builder.ops[..], expected_ops);
+        }
+
+        Ok(())
+    }
+}
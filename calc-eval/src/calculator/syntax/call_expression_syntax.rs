@@ -0,0 +1,303 @@
+use std::{fmt::Display, rc::Rc};
+use crate::calculator::{
+    error::{runtime_error, Result},
+    interpreter::{check_higher_order_arity, is_higher_order_function, verify, Closure, ClosureCompiler, MethodBuilder, Op, UserFunction},
+    tokenizer::{Span, Token, TokenKind}
+};
+use super::{
+    expression_syntax::{ExpressionPrecedence, ExpressionSyntax},
+    parser::Parser,
+    power_expression_syntax::PowerExpressionSyntax,
+    simplifier,
+    syntax::Syntax
+};
+
+#[derive(Clone)]
+pub struct CallExpressionSyntax {
+    identifier_token: Token,
+    argument_exprs: Vec<Box<dyn ExpressionSyntax>>,
+    span: Span
+}
+
+impl CallExpressionSyntax {
+    pub fn new(name: &str, argument_exprs: Vec<Box<dyn ExpressionSyntax>>, span: Span) -> Self {
+        Self {
+            identifier_token: Token {
+                source: name.to_owned(),
+                token_kind: TokenKind::Identifier,
+                span
+            },
+            argument_exprs,
+            span
+        }
+    }
+
+    pub fn parse_expression(parser: &mut Parser) -> Box<dyn ExpressionSyntax> {
+        debug_assert!(parser.peek().is_identifier() && parser.peek_next().is_operator("("));
+
+        let identifier_token = parser.advance().clone();
+        parser.advance();
+
+        let mut argument_exprs = vec![];
+        if parser.try_consume_operator(")").is_none() {
+            loop {
+                argument_exprs.push(parser.parse_expression());
+
+                if parser.try_consume_operator(",").is_some() {
+                    continue;
+                }
+                if parser.try_consume_operator(")").is_some() {
+                    break;
+                }
+
+                //Skip the rest of a malformed argument and pick up again at the next one
+                parser.report_unexpected("',' or ')'");
+                parser.skip_until(&[",", ")"]);
+                if parser.try_consume_operator(",").is_some() {
+                    continue;
+                }
+                if parser.try_consume_operator(")").is_none() {
+                    parser.report_unexpected("')'");
+                }
+                break;
+            }
+        }
+
+        let span = identifier_token.span.merge(parser.previous().span);
+
+        Box::new(CallExpressionSyntax {
+            identifier_token,
+            argument_exprs,
+            span
+        })
+    }
+}
+
+impl ExpressionSyntax for CallExpressionSyntax {
+    fn get_expression_precedence(&self) -> ExpressionPrecedence {
+        ExpressionPrecedence::Primary
+    }
+
+    fn get_span(&self) -> Span {
+        self.span
+    }
+
+    fn emit_bytecode(&self, method_builder: &mut MethodBuilder) -> Result<()> {
+        if is_higher_order_function(&self.identifier_token.source) {
+            return self.emit_higher_order_bytecode(method_builder);
+        }
+
+        for argument_expr in self.argument_exprs.iter() {
+            argument_expr.emit_bytecode(method_builder)?;
+        }
+
+        let name_idx = method_builder.intern_name(&self.identifier_token.source);
+        method_builder.emit_spanned(Op::Call(name_idx, self.argument_exprs.len()), self.span);
+
+        Ok(())
+    }
+
+    fn compile_closure(&self, compiler: &ClosureCompiler) -> Result<Closure> {
+        if is_higher_order_function(&self.identifier_token.source) {
+            return self.compile_higher_order_closure(compiler);
+        }
+
+        let arguments = self.argument_exprs.iter()
+            .map(|argument_expr| argument_expr.compile_closure(compiler))
+            .collect::<Result<Vec<_>>>()?;
+        let name = self.identifier_token.source.clone();
+        let span = self.span;
+
+        Ok(Box::new(move |context| {
+            let args = arguments.iter()
+                .map(|argument| argument(context))
+                .collect::<Result<Vec<_>>>()?;
+            context.interpreter.call_function(&name, args, context.environment).map_err(|err| err.with_span(span))
+        }))
+    }
+
+    fn get_label(&self) -> String {
+        format!("call {}", self.identifier_token.source)
+    }
+
+    fn get_children(&self) -> Vec<&dyn ExpressionSyntax> {
+        self.argument_exprs.iter().map(|argument_expr| argument_expr.as_ref()).collect()
+    }
+
+    fn derivative(&self, var: &str) -> Result<Box<dyn ExpressionSyntax>> {
+        let name = self.identifier_token.source.as_str();
+        if name == "pow" && self.argument_exprs.len() == 2 {
+            let power_expr = PowerExpressionSyntax::new(self.argument_exprs[0].clone(), self.argument_exprs[1].clone());
+            return power_expr.derivative(var);
+        }
+
+        let outer_derivative = match &self.argument_exprs[..] {
+            [argument_expr] => self.get_outer_derivative(argument_expr.as_ref()),
+            _ => None
+        };
+        let Some(outer_derivative) = outer_derivative else {
+            return Err(runtime_error!("Function '{}' can't be differentiated", name).with_span(self.span));
+        };
+
+        //Chain rule: d(f(u)) = f'(u) * du
+        Ok(simplifier::multiply(outer_derivative, self.argument_exprs[0].derivative(var)?))
+    }
+
+    fn clone_expression(&self) -> Box<dyn ExpressionSyntax> {
+        Box::new(self.clone())
+    }
+}
+
+impl CallExpressionSyntax {
+    /// Compiles the expression passed to a higher-order built-in into a function of its variable. The parameters of
+    /// the enclosing method become extra parameters, so the expression can still use them once they're passed along.
+    fn build_higher_order_function(&self, enclosing_parameters: &[String]) -> Result<UserFunction> {
+        let name = &self.identifier_token.source;
+        check_higher_order_arity(name, self.argument_exprs.len()).map_err(|err| err.with_span(self.span))?;
+
+        let variable_expr = &self.argument_exprs[1];
+        let Some(variable) = variable_expr.get_variable_name() else {
+            return Err(runtime_error!("The second argument of '{}' must be a variable", name).with_span(variable_expr.get_span()));
+        };
+
+        let mut parameters = vec![variable.to_owned()];
+        parameters.extend(enclosing_parameters.iter().filter(|parameter| *parameter != variable).cloned());
+
+        let mut body_builder = MethodBuilder::with_parameters(parameters.clone());
+        self.argument_exprs[0].emit_bytecode(&mut body_builder)?;
+
+        Ok(UserFunction {
+            name: name.clone(),
+            parameters,
+            method: body_builder
+        })
+    }
+
+    fn emit_higher_order_bytecode(&self, method_builder: &mut MethodBuilder) -> Result<()> {
+        let function = self.build_higher_order_function(&method_builder.parameters)?;
+        let captured = function.parameters[1..].iter()
+            .map(|parameter| method_builder.get_parameter_index(parameter).unwrap())
+            .collect::<Vec<_>>();
+        let function_idx = method_builder.add_function(function);
+
+        for &arg_idx in captured.iter() {
+            method_builder.ops.push(Op::Ldarg(arg_idx));
+        }
+        for argument_expr in self.argument_exprs[2..].iter() {
+            argument_expr.emit_bytecode(method_builder)?;
+        }
+
+        let name_idx = method_builder.intern_name(&self.identifier_token.source);
+        let arity = captured.len() + self.argument_exprs.len() - 2;
+        method_builder.emit_spanned(Op::Apply(name_idx, function_idx, arity), self.span);
+
+        Ok(())
+    }
+
+    fn compile_higher_order_closure(&self, compiler: &ClosureCompiler) -> Result<Closure> {
+        //Closures are only compiled outside of function bodies, so there are never any parameters to capture
+        let function = Rc::new(self.build_higher_order_function(&[])?);
+        verify(&function.method)?;
+
+        let arguments = self.argument_exprs[2..].iter()
+            .map(|argument_expr| argument_expr.compile_closure(compiler))
+            .collect::<Result<Vec<_>>>()?;
+        let name = self.identifier_token.source.clone();
+        let span = self.span;
+
+        Ok(Box::new(move |context| {
+            let args = arguments.iter()
+                .map(|argument| argument(context))
+                .collect::<Result<Vec<_>>>()?;
+            context.interpreter.apply_function(&name, &function, vec![], &args, context.environment, 0).map_err(|err| err.with_span(span))
+        }))
+    }
+
+    /// The derivative of a built-in function of one argument with respect to that argument, `f'(u)`.
+    fn get_outer_derivative(&self, argument_expr: &dyn ExpressionSyntax) -> Option<Box<dyn ExpressionSyntax>> {
+        use simplifier::{add, call, divide, multiply, negate, number, power, subtract};
+
+        let span = self.span;
+        let u = || argument_expr.clone_expression();
+        let one = || number(1.0, span);
+        let square = |expr| power(expr, number(2.0, span));
+
+        let outer_derivative = match self.identifier_token.source.as_str() {
+            "sqrt" => divide(one(), multiply(number(2.0, span), call("sqrt", vec![u()], span))),
+            "cbrt" => divide(one(), multiply(number(3.0, span), square(call("cbrt", vec![u()], span)))),
+            "exp" => call("exp", vec![u()], span),
+            "ln" => divide(one(), u()),
+            "log2" => divide(one(), multiply(u(), call("ln", vec![number(2.0, span)], span))),
+            "log10" => divide(one(), multiply(u(), call("ln", vec![number(10.0, span)], span))),
+            "sin" => call("cos", vec![u()], span),
+            "cos" => negate(call("sin", vec![u()], span), span),
+            "tan" => divide(one(), square(call("cos", vec![u()], span))),
+            "asin" => divide(one(), call("sqrt", vec![subtract(one(), square(u()))], span)),
+            "acos" => negate(divide(one(), call("sqrt", vec![subtract(one(), square(u()))], span)), span),
+            "atan" => divide(one(), add(one(), square(u()))),
+            "sinh" => call("cosh", vec![u()], span),
+            "cosh" => call("sinh", vec![u()], span),
+            "tanh" => divide(one(), square(call("cosh", vec![u()], span))),
+            "abs" => divide(u(), call("abs", vec![u()], span)),
+            _ => return None
+        };
+
+        Some(outer_derivative)
+    }
+}
+
+impl Syntax for CallExpressionSyntax { }
+
+impl Display for CallExpressionSyntax {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.identifier_token.repr(f)?;
+        write!(f, "(")?;
+
+        for (idx, argument_expr) in self.argument_exprs.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", argument_expr)?;
+        }
+
+        write!(f, ")")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calculator::{
+        syntax::parse_expression,
+        tokenizer::Tokenizer
+    };
+    use super::*;
+
+    #[test]
+    fn emit_bytecode_should_emit_correct_bytecode() -> Result<()> {
+        let test_cases: &[(&str, &[Op])] = &[
+            ("pi()", &[Op::Call(0, 0)]),
+            ("sqrt(4)", &[Op::LdcI8(4), Op::Call(0, 1)]),
+            ("min(3.5, 2.7)", &[Op::LdcF8(3.5), Op::LdcF8(2.7), Op::Call(0, 2)]),
+            ("max(1, x, 2 * 3)", &[Op::LdcI8(1), Op::Ldvar(0), Op::LdcI8(2), Op::LdcI8(3), Op::Mul, Op::Call(1, 3)]),
+            ("pow(sin(x), 2)", &[Op::Ldvar(0), Op::Call(1, 1), Op::LdcI8(2), Op::Call(2, 2)]),
+            ("-abs(-1)", &[Op::LdcI8(1), Op::Neg, Op::Call(0, 1), Op::Neg]),
+        ];
+
+        let tokenizer = Tokenizer::new();
+
+        for &(input, expected_ops) in test_cases {
+            let tokens = tokenizer.tokenize(input).collect::<Vec<_>>();
+
+            let result = parse_expression(&tokens);
+
+            assert!(result.errors.is_empty());
+
+            let mut method_builder = MethodBuilder::new();
+            result.expr.emit_bytecode(&mut method_builder)?;
+
+            assert_eq!(&method_builder.ops[..], expected_ops);
+        }
+
+        Ok(())
+    }
+}
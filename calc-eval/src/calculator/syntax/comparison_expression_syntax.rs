@@ -0,0 +1,262 @@
+use std::fmt::Display;
+use crate::calculator::{
+    error::{runtime_error, Result},
+    interpreter::{Closure, ClosureCompiler, MethodBuilder, Op},
+    tokenizer::Span
+};
+use super::{
+    additive_expression_syntax::AdditiveExpressionSyntax,
+    expression_syntax::{ExpressionPrecedence, ExpressionSyntax},
+    parser::Parser,
+    syntax::Syntax
+};
+
+#[derive(Debug, Clone, Copy)]
+pub enum ComparisonExpressionKind {
+    Equal,
+    NotEqual,
+    LessThan,
+    LessThanOrEqual,
+    GreaterThan,
+    GreaterThanOrEqual
+}
+
+impl ComparisonExpressionKind {
+    pub fn get_expression_precedence(&self) -> ExpressionPrecedence {
+        match self {
+            Self::Equal |
+            Self::NotEqual => ExpressionPrecedence::Equality,
+            _ => ExpressionPrecedence::Relational
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ComparisonExpressionSyntax {
+    kind: ComparisonExpressionKind,
+    left_expr: Box<dyn ExpressionSyntax>,
+    right_expr: Box<dyn ExpressionSyntax>
+}
+
+impl ComparisonExpressionSyntax {
+    pub fn parse_expression(parser: &mut Parser) -> Box<dyn ExpressionSyntax> {
+        Self::parse_expression_with_precedence(parser, ExpressionPrecedence::Equality)
+    }
+
+    fn parse_operand(parser: &mut Parser, precedence: ExpressionPrecedence) -> Box<dyn ExpressionSyntax> {
+        if precedence == ExpressionPrecedence::Equality {
+            Self::parse_expression_with_precedence(parser, ExpressionPrecedence::Relational)
+        }
+        else {
+            AdditiveExpressionSyntax::parse_expression(parser)
+        }
+    }
+
+    fn parse_expression_with_precedence(parser: &mut Parser, precedence: ExpressionPrecedence) -> Box<dyn ExpressionSyntax> {
+        let mut expr = Self::parse_operand(parser, precedence);
+
+        loop {
+            let token = parser.peek();
+            let mut kind = None;
+            if token.is_operator("==") {
+                kind = Some(ComparisonExpressionKind::Equal);
+            }
+            else if token.is_operator("!=") {
+                kind = Some(ComparisonExpressionKind::NotEqual);
+            }
+            else if token.is_operator("<") {
+                kind = Some(ComparisonExpressionKind::LessThan);
+            }
+            else if token.is_operator("<=") {
+                kind = Some(ComparisonExpressionKind::LessThanOrEqual);
+            }
+            else if token.is_operator(">") {
+                kind = Some(ComparisonExpressionKind::GreaterThan);
+            }
+            else if token.is_operator(">=") {
+                kind = Some(ComparisonExpressionKind::GreaterThanOrEqual);
+            }
+
+            if kind.as_ref().is_none_or(|kind| kind.get_expression_precedence() != precedence) {
+                break;
+            }
+
+            parser.advance();
+
+            let rhs_expr = Self::parse_operand(parser, precedence);
+            expr = Box::new(ComparisonExpressionSyntax {
+                kind: kind.unwrap(),
+                left_expr: expr,
+                right_expr: rhs_expr
+            });
+        }
+
+        expr
+    }
+}
+
+impl ExpressionSyntax for ComparisonExpressionSyntax {
+    fn get_expression_precedence(&self) -> ExpressionPrecedence {
+        self.kind.get_expression_precedence()
+    }
+
+    fn get_span(&self) -> Span {
+        self.left_expr.get_span().merge(self.right_expr.get_span())
+    }
+
+    fn emit_bytecode(&self, method_builder: &mut MethodBuilder) -> Result<()> {
+        self.left_expr.emit_bytecode(method_builder)?;
+        self.right_expr.emit_bytecode(method_builder)?;
+
+        match self.kind {
+            ComparisonExpressionKind::Equal => {
+                method_builder.emit_spanned(Op::Ceq, self.get_span());
+            },
+            ComparisonExpressionKind::NotEqual => {
+                method_builder.emit_spanned(Op::Cne, self.get_span());
+            },
+            ComparisonExpressionKind::LessThan => {
+                method_builder.emit_spanned(Op::Clt, self.get_span());
+            },
+            ComparisonExpressionKind::LessThanOrEqual => {
+                method_builder.emit_spanned(Op::Cle, self.get_span());
+            },
+            ComparisonExpressionKind::GreaterThan => {
+                method_builder.emit_spanned(Op::Cgt, self.get_span());
+            },
+            ComparisonExpressionKind::GreaterThanOrEqual => {
+                method_builder.emit_spanned(Op::Cge, self.get_span());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn compile_closure(&self, compiler: &ClosureCompiler) -> Result<Closure> {
+        let op = match self.kind {
+            ComparisonExpressionKind::Equal => Op::Ceq,
+            ComparisonExpressionKind::NotEqual => Op::Cne,
+            ComparisonExpressionKind::LessThan => Op::Clt,
+            ComparisonExpressionKind::LessThanOrEqual => Op::Cle,
+            ComparisonExpressionKind::GreaterThan => Op::Cgt,
+            ComparisonExpressionKind::GreaterThanOrEqual => Op::Cge
+        };
+
+        let lhs = self.left_expr.compile_closure(compiler)?;
+        let rhs = self.right_expr.compile_closure(compiler)?;
+        Ok(compiler.binary(op, lhs, rhs, self.get_span()))
+    }
+
+    fn get_label(&self) -> String {
+        match self.kind {
+            ComparisonExpressionKind::Equal => "==".to_owned(),
+            ComparisonExpressionKind::NotEqual => "!=".to_owned(),
+            ComparisonExpressionKind::LessThan => "<".to_owned(),
+            ComparisonExpressionKind::LessThanOrEqual => "<=".to_owned(),
+            ComparisonExpressionKind::GreaterThan => ">".to_owned(),
+            ComparisonExpressionKind::GreaterThanOrEqual => ">=".to_owned()
+        }
+    }
+
+    fn get_children(&self) -> Vec<&dyn ExpressionSyntax> {
+        vec![self.left_expr.as_ref(), self.right_expr.as_ref()]
+    }
+
+    fn derivative(&self, _var: &str) -> Result<Box<dyn ExpressionSyntax>> {
+        Err(runtime_error!("Comparisons can't be differentiated").with_span(self.get_span()))
+    }
+
+    fn clone_expression(&self) -> Box<dyn ExpressionSyntax> {
+        Box::new(self.clone())
+    }
+}
+
+impl Syntax for ComparisonExpressionSyntax { }
+
+impl Display for ComparisonExpressionSyntax {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        {
+            let needs_parenthesis = self.left_expr.get_expression_precedence() > self.get_expression_precedence();
+            if needs_parenthesis {
+                write!(f, "({})", self.left_expr)?;
+            }
+            else {
+                write!(f, "{}", self.left_expr)?;
+            }
+        }
+
+        match self.kind {
+            ComparisonExpressionKind::Equal => {
+                write!(f, " == ")?;
+            },
+            ComparisonExpressionKind::NotEqual => {
+                write!(f, " != ")?;
+            },
+            ComparisonExpressionKind::LessThan => {
+                write!(f, " < ")?;
+            },
+            ComparisonExpressionKind::LessThanOrEqual => {
+                write!(f, " <= ")?;
+            },
+            ComparisonExpressionKind::GreaterThan => {
+                write!(f, " > ")?;
+            },
+            ComparisonExpressionKind::GreaterThanOrEqual => {
+                write!(f, " >= ")?;
+            }
+        };
+
+        {
+            let needs_parenthesis = self.right_expr.get_expression_precedence() >= self.get_expression_precedence();
+            if needs_parenthesis {
+                write!(f, "({})", self.right_expr)?;
+            }
+            else {
+                write!(f, "{}", self.right_expr)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calculator::{
+        syntax::parse_expression,
+        tokenizer::Tokenizer
+    };
+    use super::*;
+
+    #[test]
+    fn emit_bytecode_should_emit_correct_bytecode() -> Result<()> {
+        let test_cases: &[(&str, &[Op])] = &[
+            ("1==2", &[Op::LdcI8(1), Op::LdcI8(2), Op::Ceq]),
+            ("1!=2", &[Op::LdcI8(1), Op::LdcI8(2), Op::Cne]),
+            ("1<2", &[Op::LdcI8(1), Op::LdcI8(2), Op::Clt]),
+            ("1<=2", &[Op::LdcI8(1), Op::LdcI8(2), Op::Cle]),
+            ("1>2", &[Op::LdcI8(1), Op::LdcI8(2), Op::Cgt]),
+            ("1>=2", &[Op::LdcI8(1), Op::LdcI8(2), Op::Cge]),
+            ("1+2<3*4", &[Op::LdcI8(1), Op::LdcI8(2), Op::Add, Op::LdcI8(3), Op::LdcI8(4), Op::Mul, Op::Clt]),
+            ("1<2==3<4", &[Op::LdcI8(1), Op::LdcI8(2), Op::Clt, Op::LdcI8(3), Op::LdcI8(4), Op::Clt, Op::Ceq]),
+            ("1<2<3", &[Op::LdcI8(1), Op::LdcI8(2), Op::Clt, Op::LdcI8(3), Op::Clt]),
+        ];
+
+        let tokenizer = Tokenizer::new();
+
+        for &(input, expected_ops) in test_cases {
+            let tokens = tokenizer.tokenize(input).collect::<Vec<_>>();
+
+            let result = parse_expression(&tokens);
+
+            assert!(result.errors.is_empty());
+
+            let mut method_builder = MethodBuilder::new();
+            result.expr.emit_bytecode(&mut method_builder)?;
+
+            assert_eq!(&method_builder.ops[..], expected_ops);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,176 @@
+use std::fmt::Display;
+use crate::calculator::{
+    error::Result,
+    interpreter::{Closure, ClosureCompiler, MethodBuilder, Op},
+    tokenizer::Span
+};
+use super::{
+    error_expression_syntax::ErrorExpressionSyntax,
+    expression_syntax::{ExpressionPrecedence, ExpressionSyntax},
+    logical_expression_syntax::LogicalExpressionSyntax,
+    parser::Parser,
+    syntax::Syntax
+};
+
+#[derive(Clone)]
+pub struct ConditionalExpressionSyntax {
+    condition_expr: Box<dyn ExpressionSyntax>,
+    true_expr: Box<dyn ExpressionSyntax>,
+    false_expr: Box<dyn ExpressionSyntax>
+}
+
+impl ConditionalExpressionSyntax {
+    pub fn parse_expression(parser: &mut Parser) -> Box<dyn ExpressionSyntax> {
+        let condition_expr = LogicalExpressionSyntax::parse_expression(parser);
+
+        if parser.try_consume_operator("?").is_none() {
+            return condition_expr;
+        }
+
+        let true_expr = parser.parse_expression();
+
+        let false_expr = if parser.try_consume_operator(":").is_some() {
+            parser.parse_nested(ConditionalExpressionSyntax::parse_expression)
+        }
+        else {
+            parser.report_unexpected("':'");
+            ErrorExpressionSyntax::at(parser.peek().span)
+        };
+
+        Box::new(ConditionalExpressionSyntax {
+            condition_expr,
+            true_expr,
+            false_expr
+        })
+    }
+}
+
+impl ExpressionSyntax for ConditionalExpressionSyntax {
+    fn get_expression_precedence(&self) -> ExpressionPrecedence {
+        ExpressionPrecedence::Conditional
+    }
+
+    fn get_span(&self) -> Span {
+        self.condition_expr.get_span().merge(self.false_expr.get_span())
+    }
+
+    fn emit_bytecode(&self, method_builder: &mut MethodBuilder) -> Result<()> {
+        self.condition_expr.emit_bytecode(method_builder)?;
+        let false_jump_idx = method_builder.emit_jump(Op::Brfalse(0));
+        method_builder.set_span(false_jump_idx, self.condition_expr.get_span());
+
+        self.true_expr.emit_bytecode(method_builder)?;
+        let end_jump_idx = method_builder.emit_jump(Op::Br(0));
+
+        method_builder.patch_jump(false_jump_idx);
+        self.false_expr.emit_bytecode(method_builder)?;
+        method_builder.patch_jump(end_jump_idx);
+
+        Ok(())
+    }
+
+    fn compile_closure(&self, compiler: &ClosureCompiler) -> Result<Closure> {
+        let condition = self.condition_expr.compile_closure(compiler)?;
+        let true_value = self.true_expr.compile_closure(compiler)?;
+        let false_value = self.false_expr.compile_closure(compiler)?;
+        let condition_span = self.condition_expr.get_span();
+
+        Ok(Box::new(move |context| {
+            if condition(context)?.to_bool().map_err(|err| err.with_span(condition_span))? {
+                true_value(context)
+            }
+            else {
+                false_value(context)
+            }
+        }))
+    }
+
+    fn get_label(&self) -> String {
+        "? :".to_owned()
+    }
+
+    fn get_children(&self) -> Vec<&dyn ExpressionSyntax> {
+        vec![self.condition_expr.as_ref(), self.true_expr.as_ref(), self.false_expr.as_ref()]
+    }
+
+    //The derivative is taken piecewise, so the condition is kept as it is and only the branches are differentiated
+    fn derivative(&self, var: &str) -> Result<Box<dyn ExpressionSyntax>> {
+        Ok(Box::new(ConditionalExpressionSyntax {
+            condition_expr: self.condition_expr.clone(),
+            true_expr: self.true_expr.derivative(var)?,
+            false_expr: self.false_expr.derivative(var)?
+        }))
+    }
+
+    fn clone_expression(&self) -> Box<dyn ExpressionSyntax> {
+        Box::new(self.clone())
+    }
+}
+
+impl Syntax for ConditionalExpressionSyntax { }
+
+impl Display for ConditionalExpressionSyntax {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        {
+            let needs_parenthesis = self.condition_expr.get_expression_precedence() >= self.get_expression_precedence();
+            if needs_parenthesis {
+                write!(f, "({})", self.condition_expr)?;
+            }
+            else {
+                write!(f, "{}", self.condition_expr)?;
+            }
+        }
+
+        write!(f, " ? {} : ", self.true_expr)?;
+
+        {
+            let needs_parenthesis = self.false_expr.get_expression_precedence() > self.get_expression_precedence();
+            if needs_parenthesis {
+                write!(f, "({})", self.false_expr)?;
+            }
+            else {
+                write!(f, "{}", self.false_expr)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calculator::{
+        syntax::parse_expression,
+        tokenizer::Tokenizer
+    };
+    use super::*;
+
+    #[test]
+    fn emit_bytecode_should_emit_correct_bytecode() -> Result<()> {
+        let test_cases: &[(&str, &[Op])] = &[
+            ("a?1:2", &[Op::Ldvar(0), Op::Brfalse(4), Op::LdcI8(1), Op::Br(5), Op::LdcI8(2)]),
+            ("a?1:b?2:3", &[
+                Op::Ldvar(0), Op::Brfalse(4), Op::LdcI8(1), Op::Br(9),
+                Op::Ldvar(1), Op::Brfalse(8), Op::LdcI8(2), Op::Br(9), Op::LdcI8(3)
+            ]),
+            ("(a?1:2)+3", &[Op::Ldvar(0), Op::Brfalse(4), Op::LdcI8(1), Op::Br(5), Op::LdcI8(2), Op::LdcI8(3), Op::Add]),
+        ];
+
+        let tokenizer = Tokenizer::new();
+
+        for &(input, expected_ops) in test_cases {
+            let tokens = tokenizer.tokenize(input).collect::<Vec<_>>();
+
+            let result = parse_expression(&tokens);
+
+            assert!(result.errors.is_empty());
+
+            let mut method_builder = MethodBuilder::new();
+            result.expr.emit_bytecode(&mut method_builder)?;
+
+            assert_eq!(&method_builder.ops[..], expected_ops);
+        }
+
+        Ok(())
+    }
+}
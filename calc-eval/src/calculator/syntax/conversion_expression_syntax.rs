@@ -0,0 +1,136 @@
+use std::fmt::Display;
+use crate::calculator::{
+    error::{runtime_error, Result},
+    interpreter::{Closure, ClosureCompiler, MethodBuilder, Op},
+    tokenizer::Span
+};
+use super::{
+    conditional_expression_syntax::ConditionalExpressionSyntax,
+    expression_syntax::{ExpressionPrecedence, ExpressionSyntax},
+    parser::Parser,
+    syntax::Syntax,
+    unit_syntax::UnitSyntax
+};
+
+#[derive(Clone)]
+pub struct ConversionExpressionSyntax {
+    value_expr: Box<dyn ExpressionSyntax>,
+    unit_syntax: UnitSyntax
+}
+
+impl ConversionExpressionSyntax {
+    pub fn parse_expression(parser: &mut Parser) -> Box<dyn ExpressionSyntax> {
+        let mut expr = ConditionalExpressionSyntax::parse_expression(parser);
+
+        while parser.peek().is_identifier() && parser.peek().source == "in" {
+            parser.advance();
+
+            let Some(unit_syntax) = UnitSyntax::parse(parser) else {
+                break;
+            };
+            expr = Box::new(ConversionExpressionSyntax {
+                value_expr: expr,
+                unit_syntax
+            });
+        }
+
+        expr
+    }
+}
+
+impl ExpressionSyntax for ConversionExpressionSyntax {
+    fn get_expression_precedence(&self) -> ExpressionPrecedence {
+        ExpressionPrecedence::Conversion
+    }
+
+    fn get_span(&self) -> Span {
+        self.value_expr.get_span().merge(self.unit_syntax.get_span())
+    }
+
+    fn emit_bytecode(&self, method_builder: &mut MethodBuilder) -> Result<()> {
+        self.value_expr.emit_bytecode(method_builder)?;
+
+        let unit_idx = method_builder.intern_unit(self.unit_syntax.get_unit());
+        method_builder.emit_spanned(Op::Convert(unit_idx), self.get_span());
+
+        Ok(())
+    }
+
+    fn compile_closure(&self, compiler: &ClosureCompiler) -> Result<Closure> {
+        let value = self.value_expr.compile_closure(compiler)?;
+        let unit = self.unit_syntax.get_unit().clone();
+        let span = self.get_span();
+
+        Ok(Box::new(move |context| {
+            value(context)?.convert_to(&unit).map_err(|err| err.with_span(span))
+        }))
+    }
+
+    fn get_label(&self) -> String {
+        format!("in {}", self.unit_syntax)
+    }
+
+    fn get_children(&self) -> Vec<&dyn ExpressionSyntax> {
+        vec![self.value_expr.as_ref()]
+    }
+
+    fn derivative(&self, _var: &str) -> Result<Box<dyn ExpressionSyntax>> {
+        Err(runtime_error!("Unit conversions can't be differentiated").with_span(self.get_span()))
+    }
+
+    fn clone_expression(&self) -> Box<dyn ExpressionSyntax> {
+        Box::new(self.clone())
+    }
+}
+
+impl Syntax for ConversionExpressionSyntax { }
+
+impl Display for ConversionExpressionSyntax {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let needs_parenthesis = self.value_expr.get_expression_precedence() > self.get_expression_precedence();
+        if needs_parenthesis {
+            write!(f, "({})", self.value_expr)?;
+        }
+        else {
+            write!(f, "{}", self.value_expr)?;
+        }
+
+        write!(f, " in {}", self.unit_syntax)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calculator::{
+        syntax::parse_expression,
+        tokenizer::Tokenizer
+    };
+    use super::*;
+
+    #[test]
+    fn emit_bytecode_should_emit_correct_bytecode() -> Result<()> {
+        let test_cases: &[(&str, &[Op])] = &[
+            ("60 mi/h in m/s", &[Op::LdcI8(60), Op::Unit(0), Op::Convert(1)]),
+            ("x in km", &[Op::Ldvar(0), Op::Convert(0)]),
+            ("1 km + 2 m in m", &[Op::LdcI8(1), Op::Unit(0), Op::LdcI8(2), Op::Unit(1), Op::Add, Op::Convert(1)]),
+            ("x = 2 h in min", &[Op::LdcI8(2), Op::Unit(0), Op::Convert(1), Op::Dup, Op::Stvar(0)]),
+        ];
+
+        let tokenizer = Tokenizer::new();
+
+        for &(input, expected_ops) in test_cases {
+            let tokens = tokenizer.tokenize(input).collect::<Vec<_>>();
+
+            let result = parse_expression(&tokens);
+
+            assert!(result.errors.is_empty());
+
+            let mut method_builder = MethodBuilder::new();
+            result.expr.emit_bytecode(&mut method_builder)?;
+
+            assert_eq!(&method_builder.ops[..], expected_ops);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,163 @@
+use std::fmt::Display;
+use crate::calculator::{
+    error::Result,
+    interpreter::{Closure, ClosureCompiler, MethodBuilder},
+    tokenizer::{Span, Token}
+};
+use super::{
+    expression_syntax::{ExpressionPrecedence, ExpressionSyntax},
+    parser::Parser,
+    syntax::Syntax
+};
+
+/// The derivative of an expression with respect to a variable, written `d/dx (expr)`. It evaluates to the derivative
+/// at the current value of the variable.
+#[derive(Clone)]
+pub struct DerivativeExpressionSyntax {
+    /// The variable, which is the name after the second `d`.
+    variable: String,
+    nested_expr: Box<dyn ExpressionSyntax>,
+    span: Span
+}
+
+impl DerivativeExpressionSyntax {
+    /// Looks ahead for `d/dx (`. Without the parentheses it's an ordinary division, like `d / dx`.
+    pub fn is_at_derivative(parser: &Parser) -> bool {
+        let is_variable_token = |token: &Token| token.is_identifier() && token.source.len() > 1 && token.source.starts_with('d');
+
+        parser.peek().is_identifier() && parser.peek().source == "d"
+            && parser.peek_at(1).is_operator("/")
+            && is_variable_token(parser.peek_at(2))
+            && parser.peek_at(3).is_operator("(")
+    }
+
+    pub fn parse_expression(parser: &mut Parser) -> Box<dyn ExpressionSyntax> {
+        debug_assert!(Self::is_at_derivative(parser));
+
+        let start_span = parser.advance().span;
+        parser.advance();
+        let variable = parser.advance().source[1..].to_owned();
+        parser.advance();
+
+        let nested_expr = parser.parse_expression();
+        if parser.try_consume_operator(")").is_none() {
+            parser.report_unexpected("')'");
+            parser.skip_until(&[")"]);
+            parser.try_consume_operator(")");
+        }
+
+        Box::new(DerivativeExpressionSyntax {
+            variable,
+            nested_expr,
+            span: start_span.merge(parser.previous().span)
+        })
+    }
+}
+
+impl ExpressionSyntax for DerivativeExpressionSyntax {
+    fn get_expression_precedence(&self) -> ExpressionPrecedence {
+        ExpressionPrecedence::Primary
+    }
+
+    fn get_span(&self) -> Span {
+        self.span
+    }
+
+    fn emit_bytecode(&self, method_builder: &mut MethodBuilder) -> Result<()> {
+        self.nested_expr.derivative(&self.variable)?.emit_bytecode(method_builder)
+    }
+
+    fn compile_closure(&self, compiler: &ClosureCompiler) -> Result<Closure> {
+        self.nested_expr.derivative(&self.variable)?.compile_closure(compiler)
+    }
+
+    fn get_label(&self) -> String {
+        format!("d/d{}", self.variable)
+    }
+
+    fn get_children(&self) -> Vec<&dyn ExpressionSyntax> {
+        vec![self.nested_expr.as_ref()]
+    }
+
+    fn derivative(&self, var: &str) -> Result<Box<dyn ExpressionSyntax>> {
+        self.nested_expr.derivative(&self.variable)?.derivative(var)
+    }
+
+    fn clone_expression(&self) -> Box<dyn ExpressionSyntax> {
+        Box::new(self.clone())
+    }
+}
+
+impl Syntax for DerivativeExpressionSyntax { }
+
+impl Display for DerivativeExpressionSyntax {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "d/d{} ({})", self.variable, self.nested_expr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calculator::{
+        syntax::parse_expression,
+        tokenizer::Tokenizer
+    };
+    use super::*;
+
+    #[test]
+    fn derivative_should_differentiate_and_simplify() -> Result<()> {
+        let test_cases: &[(&str, &str)] = &[
+            //Constants and variables
+            ("7", "0"),
+            ("x", "1"),
+            ("y", "0"),
+            ("3 * y + 1", "0"),
+
+            //Sums, products and quotients
+            ("x + x", "2"),
+            ("3 * x - 5", "3"),
+            ("-x", "-1"),
+            ("x * y", "y"),
+            ("x ^ 2 * sin(x)", "2 * x * sin(x) + x ^ 2 * cos(x)"),
+            ("1 / x", "-1 / x ^ 2"),
+            ("x / 4", "1 / 4"),
+            ("(x + 1) / (x - 1)", "(x - 1 - (x + 1)) / (x - 1) ^ 2"),
+
+            //Powers
+            ("x ^ 2", "2 * x"),
+            ("x ^ 3", "3 * x ^ 2"),
+            ("x ^ 0.5", "0.5 * x ^ -0.5"),
+            ("x ^ n", "n * x ^ (n - 1)"),
+            ("2 ^ x", "2 ^ x * ln(2)"),
+            ("x ^ x", "x ^ x * (ln(x) + x / x)"),
+            ("pow(x, 3)", "3 * x ^ 2"),
+
+            //The chain rule
+            ("sin(2 * x)", "2 * cos(2 * x)"),
+            ("cos(x)", "-sin(x)"),
+            ("exp(x ^ 2)", "exp(x ^ 2) * (2 * x)"),
+            ("ln(x)", "1 / x"),
+            ("sqrt(x)", "1 / (2 * sqrt(x))"),
+            ("atan(x)", "1 / (1 + x ^ 2)"),
+
+            //Branches are differentiated separately
+            ("x > 0 ? x ^ 2 : -x", "x > 0 ? 2 * x : -1"),
+
+            //Derivatives of derivatives
+            ("d/dx (x ^ 3)", "3 * (2 * x)"),
+        ];
+
+        let tokenizer = Tokenizer::new();
+
+        for &(input, expected_output) in test_cases {
+            let tokens = tokenizer.tokenize(input).collect::<Vec<_>>();
+
+            let result = parse_expression(&tokens);
+
+            assert!(result.errors.is_empty());
+            assert_eq!(result.expr.derivative("x")?.to_string(), expected_output, "for input {:?}", input);
+        }
+
+        Ok(())
+    }
+}
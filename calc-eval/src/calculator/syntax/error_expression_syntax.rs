@@ -0,0 +1,67 @@
+use std::fmt::Display;
+use crate::calculator::{
+    diagnostic::Diagnostic,
+    error::{runtime_error, Result},
+    interpreter::{Closure, ClosureCompiler, MethodBuilder},
+    tokenizer::Span
+};
+use super::{
+    expression_syntax::{ExpressionPrecedence, ExpressionSyntax},
+    syntax::Syntax
+};
+
+/// Stands in for an expression that failed to parse, so the parser can keep going and report later errors too.
+#[derive(Clone)]
+pub struct ErrorExpressionSyntax {
+    span: Span
+}
+
+impl ErrorExpressionSyntax {
+    pub fn at(span: Span) -> Box<dyn ExpressionSyntax> {
+        Box::new(ErrorExpressionSyntax {
+            span
+        })
+    }
+}
+
+impl ExpressionSyntax for ErrorExpressionSyntax {
+    fn get_expression_precedence(&self) -> ExpressionPrecedence {
+        ExpressionPrecedence::Primary
+    }
+
+    fn get_span(&self) -> Span {
+        self.span
+    }
+
+    fn emit_bytecode(&self, _method_builder: &mut MethodBuilder) -> Result<()> {
+        Err(Diagnostic::new("Cannot emit bytecode for an expression that failed to parse.", self.span).into())
+    }
+
+    fn compile_closure(&self, _compiler: &ClosureCompiler) -> Result<Closure> {
+        Err(Diagnostic::new("Cannot emit bytecode for an expression that failed to parse.", self.span).into())
+    }
+
+    fn get_label(&self) -> String {
+        "<error>".to_owned()
+    }
+
+    fn get_children(&self) -> Vec<&dyn ExpressionSyntax> {
+        vec![]
+    }
+
+    fn derivative(&self, _var: &str) -> Result<Box<dyn ExpressionSyntax>> {
+        Err(runtime_error!("Expressions with syntax errors can't be differentiated").with_span(self.get_span()))
+    }
+
+    fn clone_expression(&self) -> Box<dyn ExpressionSyntax> {
+        Box::new(self.clone())
+    }
+}
+
+impl Syntax for ErrorExpressionSyntax { }
+
+impl Display for ErrorExpressionSyntax {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<error>")
+    }
+}
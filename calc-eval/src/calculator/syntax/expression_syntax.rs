@@ -1,36 +1,95 @@
-use anyhow::*;
 use crate::calculator::{
-    interpreter::MethodBuilder,
-    tokenizer::Token
+    error::Result,
+    interpreter::{Closure, ClosureCompiler, MethodBuilder},
+    tokenizer::{Span, Token}
 };
 use super::{
-    additive_expression_syntax::AdditiveExpressionSyntax,
+    parser::{ParseResult, Parser},
     syntax::Syntax
 };
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone, PartialOrd)]
+#[derive(Debug, Eq, PartialEq, Copy, Clone, PartialOrd, Ord)]
 #[repr(u8)]
 pub enum ExpressionPrecedence {
     Primary = 0,
-    Unary = 1,
-    Multiplicative = 2,
-    Additive = 3
-}
-
-impl Ord for ExpressionPrecedence {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        (*self as u8).cmp(&(*other as u8))
-    }
+    Power = 1,
+    Unary = 2,
+    Multiplicative = 3,
+    Additive = 4,
+    Relational = 5,
+    Equality = 6,
+    LogicalAnd = 7,
+    LogicalOr = 8,
+    Conditional = 9,
+    Conversion = 10,
+    Assignment = 11
 }
 
 pub trait ExpressionSyntax: Syntax {
     fn get_expression_precedence(&self) -> ExpressionPrecedence;
 
+    fn get_span(&self) -> Span;
+
     fn emit_bytecode(&self, method_builder: &mut MethodBuilder) -> Result<()>;
+
+    /// Compiles the expression into closures that evaluate it the same way its bytecode would.
+    fn compile_closure(&self, compiler: &ClosureCompiler) -> Result<Closure>;
+
+    /// Describes this node without its children, like the operator it applies or the literal it holds.
+    fn get_label(&self) -> String;
+
+    /// The expressions directly beneath this one, in the order they're evaluated.
+    fn get_children(&self) -> Vec<&dyn ExpressionSyntax>;
+
+    /// Differentiates the expression with respect to the variable `var`, treating every other variable as a constant.
+    /// The result is simplified as it's built, so it doesn't fill up with terms like `0 * x` and `1 * x`.
+    fn derivative(&self, var: &str) -> Result<Box<dyn ExpressionSyntax>>;
+
+    fn clone_expression(&self) -> Box<dyn ExpressionSyntax>;
+
+    /// The value of the expression if it's a plain number, which lets the simplifier fold constants.
+    fn get_number(&self) -> Option<f64> {
+        None
+    }
+
+    /// The name of the variable if the expression is nothing but a variable.
+    fn get_variable_name(&self) -> Option<&str> {
+        None
+    }
+}
+
+impl Clone for Box<dyn ExpressionSyntax> {
+    fn clone(&self) -> Self {
+        self.clone_expression()
+    }
 }
 
-pub fn try_parse_expression(tokens: &Vec<Token>, pos: &mut usize) -> Option<Box<dyn ExpressionSyntax>> {
-    AdditiveExpressionSyntax::try_parse_expression(tokens, pos)
+/// Parses `tokens`, which must end with an EOF token, collecting every syntax error along the way.
+pub fn parse_expression(tokens: &[Token]) -> ParseResult {
+    Parser::new(tokens).parse()
+}
+
+/// Renders `expr` as an indented tree with the precedence and span of every node, which shows how the input was
+/// grouped without relying on parentheses.
+pub fn format_syntax_tree(expr: &dyn ExpressionSyntax) -> String {
+    let mut output = String::new();
+    write_syntax_tree(expr, "", "", &mut output);
+    output
+}
+
+fn write_syntax_tree(expr: &dyn ExpressionSyntax, prefix: &str, child_prefix: &str, output: &mut String) {
+    let span = expr.get_span();
+    output.push_str(&format!("{}{}  [{:?}, {}..{}]\n", prefix, expr.get_label(), expr.get_expression_precedence(), span.start, span.end));
+
+    let children = expr.get_children();
+    for (idx, child) in children.iter().enumerate() {
+        if idx + 1 < children.len() {
+            write_syntax_tree(*child, &format!("{}├── ", child_prefix), &format!("{}│   ", child_prefix), output);
+        }
+        else {
+            write_syntax_tree(*child, &format!("{}└── ", child_prefix), &format!("{}    ", child_prefix), output);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -39,18 +98,37 @@ mod tests {
     use super::*;
 
     #[test]
-    fn try_parse_expression_should_correctly_parse_expressions() {
+    fn parse_expression_should_correctly_parse_expressions() {
         let test_cases: &[(&str, &str)] = &[
             //Primary
             ("25", "25"),
             ("2.5", "2.5"),
             ("(((1)))", "1"),
+            ("min(1,2)", "min(1, 2)"),
+            ("f()", "f()"),
+            ("max((1),-2,3*4)", "max(1, -2, 3 * 4)"),
+            ("d/dx(x^2)", "d/dx (x ^ 2)"),
+            ("d/dx", "d / dx"),
+            ("[1,[2,x],[]]", "[1, [2, x], []]"),
+            ("(a+b)[0]", "(a + b)[0]"),
+            ("m[i-1][0]^2", "m[i - 1][0] ^ 2"),
+            ("f(x)[1]", "f(x)[1]"),
 
             //Unary
             ("-42", "-42"),
             ("-(42)", "-42"),
             ("+-+-+42", "+-+-+42"),
 
+            //Power
+            ("2^3^2", "2 ^ 3 ^ 2"),
+            ("(2^3)^2", "(2 ^ 3) ^ 2"),
+            ("-2^2", "-2 ^ 2"),
+            ("(-2)^2", "(-2) ^ 2"),
+            ("2^-1", "2 ^ -1"),
+            ("2^(1+1)", "2 ^ (1 + 1)"),
+            ("2*3^2", "2 * 3 ^ 2"),
+            ("(2*3)^2", "(2 * 3) ^ 2"),
+
             //Multiplicative
             ("1*2/3%4", "1 * 2 / 3 % 4"),
             ("1*(2/(3%4))", "1 * (2 / (3 % 4))"),
@@ -66,19 +144,90 @@ mod tests {
             ("1*(2+3)*4", "1 * (2 + 3) * 4"),
             ("1+2/3-4", "1 + 2 / 3 - 4"),
             ("(1+2)/(3-4)", "(1 + 2) / (3 - 4)"),
+
+            //Comparison
+            ("1<2", "1 < 2"),
+            ("1+2>=3*4", "1 + 2 >= 3 * 4"),
+            ("1<2==3>4", "1 < 2 == 3 > 4"),
+            ("1<(2==3)", "1 < (2 == 3)"),
+            ("1!=(2!=3)", "1 != (2 != 3)"),
+
+            //Logical
+            ("!a", "!a"),
+            ("!(a&&b)", "!(a && b)"),
+            ("a&&b||c&&d", "a && b || c && d"),
+            ("a&&(b||c)", "a && (b || c)"),
+            ("a>3&&b!=0", "a > 3 && b != 0"),
+
+            //Conditional
+            ("a?1:2", "a ? 1 : 2"),
+            ("a>3&&b!=0?a/b:0", "a > 3 && b != 0 ? a / b : 0"),
+            ("a?b?1:2:c?3:4", "a ? b ? 1 : 2 : c ? 3 : 4"),
+            ("(a?1:2)?3:4", "(a ? 1 : 2) ? 3 : 4"),
+            ("a?1:(x=2)", "a ? 1 : (x = 2)"),
+
+            //Units and conversions
+            ("3km+250m", "3 km + 250 m"),
+            ("60 mi / h in m / s", "60 mi/h in m/s"),
+            ("(3 km)^2", "(3 km) ^ 2"),
+            ("3 km^2", "3 km^2"),
+            ("-9.81 kg*m/s^-2", "-9.81 kg*m/s^-2"),
+            ("2 m * x", "2 m * x"),
+            ("a ? 1 km : 2 m in cm", "a ? 1 km : 2 m in cm"),
+            ("(x = 1 h) in s", "(x = 1 h) in s"),
+            ("x = 1 h in s", "x = 1 h in s"),
+
+            //Assignment
+            ("x=1", "x = 1"),
+            ("x=y=2*3", "x = y = 2 * 3"),
+            ("x=(y=2)*3", "x = (y = 2) * 3"),
+            ("total+x", "total + x"),
+        ];
+
+        let tokenizer = Tokenizer::new();
+
+        for &(input, expected_output) in test_cases {
+            let tokens = tokenizer.tokenize(input).collect::<Vec<_>>();
+
+            let result = parse_expression(&tokens);
+
+            assert!(result.errors.is_empty());
+            assert_eq!(result.expr.to_string().as_str(), expected_output);
+        }
+    }
+
+    #[test]
+    fn format_syntax_tree_should_show_grouping_and_precedence() {
+        let test_cases: &[(&str, &str)] = &[
+            ("1 + 2 * -x", "\
++  [Additive, 0..10]
+├── 1  [Primary, 0..1]
+└── *  [Multiplicative, 4..10]
+    ├── 2  [Primary, 4..5]
+    └── -  [Unary, 8..10]
+        └── x  [Primary, 9..10]
+"),
+            ("y = a ? max(1, 2) : 3 km in m", "\
+y =  [Assignment, 0..29]
+└── in m  [Conversion, 4..29]
+    └── ? :  [Conditional, 4..24]
+        ├── a  [Primary, 4..5]
+        ├── call max  [Primary, 8..17]
+        │   ├── 1  [Primary, 12..13]
+        │   └── 2  [Primary, 15..16]
+        └── 3 km  [Power, 20..24]
+"),
         ];
 
         let tokenizer = Tokenizer::new();
 
         for &(input, expected_output) in test_cases {
-            let tokens = tokenizer.tokenize(input).collect();
+            let tokens = tokenizer.tokenize(input).collect::<Vec<_>>();
 
-            let mut pos = 0;
-            let expr = try_parse_expression(&tokens, &mut pos);
+            let result = parse_expression(&tokens);
 
-            assert!(expr.is_some());
-            assert_eq!(pos, tokens.len() - 1);
-            assert_eq!(expr.unwrap().to_string().as_str(), expected_output);
+            assert!(result.errors.is_empty());
+            assert_eq!(format_syntax_tree(result.expr.as_ref()), expected_output, "for input {:?}", input);
         }
     }
 }
@@ -0,0 +1,196 @@
+use std::{fmt::Display, rc::Rc};
+use crate::calculator::{
+    diagnostic::Diagnostic,
+    error::{runtime_error, Result},
+    interpreter::{verify, Closure, ClosureCompiler, MethodBuilder, Op, UserFunction},
+    tokenizer::{Span, Token}
+};
+use super::{
+    expression_syntax::{ExpressionPrecedence, ExpressionSyntax},
+    parser::Parser,
+    syntax::Syntax
+};
+
+#[derive(Clone)]
+pub struct FunctionDefinitionSyntax {
+    identifier_token: Token,
+    parameter_tokens: Vec<Token>,
+    body_expr: Box<dyn ExpressionSyntax>
+}
+
+impl FunctionDefinitionSyntax {
+    /// Looks ahead for `name(a, b, ...) =`, which starts out looking just like a call.
+    pub fn is_at_definition(parser: &Parser) -> bool {
+        if !parser.peek().is_identifier() || !parser.peek_next().is_operator("(") {
+            return false;
+        }
+
+        let mut offset = 2;
+        if !parser.peek_at(offset).is_operator(")") {
+            loop {
+                if !parser.peek_at(offset).is_identifier() {
+                    return false;
+                }
+                offset += 1;
+
+                if parser.peek_at(offset).is_operator(",") {
+                    offset += 1;
+                }
+                else {
+                    break;
+                }
+            }
+        }
+
+        parser.peek_at(offset).is_operator(")") && parser.peek_at(offset + 1).is_operator("=")
+    }
+
+    pub fn parse_expression(parser: &mut Parser) -> Box<dyn ExpressionSyntax> {
+        debug_assert!(Self::is_at_definition(parser));
+
+        let identifier_token = parser.advance().clone();
+        parser.advance();
+
+        let mut parameter_tokens = vec![];
+        while parser.try_consume_operator(")").is_none() {
+            parameter_tokens.push(parser.advance().clone());
+            parser.try_consume_operator(",");
+        }
+        parser.advance();
+
+        let body_expr = parser.parse_expression();
+
+        Box::new(FunctionDefinitionSyntax {
+            identifier_token,
+            parameter_tokens,
+            body_expr
+        })
+    }
+}
+
+impl ExpressionSyntax for FunctionDefinitionSyntax {
+    fn get_expression_precedence(&self) -> ExpressionPrecedence {
+        ExpressionPrecedence::Assignment
+    }
+
+    fn get_span(&self) -> Span {
+        self.identifier_token.span.merge(self.body_expr.get_span())
+    }
+
+    fn emit_bytecode(&self, method_builder: &mut MethodBuilder) -> Result<()> {
+        let function_idx = method_builder.add_function(self.build_function()?);
+        method_builder.emit_spanned(Op::Defun(function_idx), self.get_span());
+
+        Ok(())
+    }
+
+    fn compile_closure(&self, _compiler: &ClosureCompiler) -> Result<Closure> {
+        //The body is compiled to bytecode all the same, since that is how user functions are stored and called
+        let function = Rc::new(self.build_function()?);
+        verify(&function.method)?;
+        let span = self.get_span();
+
+        Ok(Box::new(move |context| {
+            context.interpreter.define_function(&function, context.environment).map_err(|err| err.with_span(span))
+        }))
+    }
+
+    fn get_label(&self) -> String {
+        let parameters = self.parameter_tokens.iter().map(|parameter_token| parameter_token.source.as_str()).collect::<Vec<_>>();
+        format!("define {}({})", self.identifier_token.source, parameters.join(", "))
+    }
+
+    fn get_children(&self) -> Vec<&dyn ExpressionSyntax> {
+        vec![self.body_expr.as_ref()]
+    }
+
+    fn derivative(&self, _var: &str) -> Result<Box<dyn ExpressionSyntax>> {
+        Err(runtime_error!("Function definitions can't be differentiated").with_span(self.get_span()))
+    }
+
+    fn clone_expression(&self) -> Box<dyn ExpressionSyntax> {
+        Box::new(self.clone())
+    }
+}
+
+impl FunctionDefinitionSyntax {
+    fn build_function(&self) -> Result<UserFunction> {
+        let mut parameters: Vec<String> = vec![];
+        for parameter_token in self.parameter_tokens.iter() {
+            if parameters.contains(&parameter_token.source) {
+                return Err(Diagnostic::new(format!("Duplicate parameter: {}", parameter_token.source), parameter_token.span).into());
+            }
+            parameters.push(parameter_token.source.clone());
+        }
+
+        let mut body_builder = MethodBuilder::with_parameters(parameters.clone());
+        self.body_expr.emit_bytecode(&mut body_builder)?;
+
+        Ok(UserFunction {
+            name: self.identifier_token.source.clone(),
+            parameters,
+            method: body_builder
+        })
+    }
+}
+
+impl Syntax for FunctionDefinitionSyntax { }
+
+impl Display for FunctionDefinitionSyntax {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.identifier_token.repr(f)?;
+        write!(f, "(")?;
+
+        for (idx, parameter_token) in self.parameter_tokens.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+            parameter_token.repr(f)?;
+        }
+
+        write!(f, ") = {}", self.body_expr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calculator::{
+        syntax::parse_expression,
+        tokenizer::Tokenizer
+    };
+    use super::*;
+
+    #[test]
+    fn emit_bytecode_should_emit_correct_bytecode() -> Result<()> {
+        let test_cases: &[(&str, &str, &[Op])] = &[
+            ("f(x,y)=x*x+y", "f(x, y) = x * x + y", &[Op::Ldarg(0), Op::Ldarg(0), Op::Mul, Op::Ldarg(1), Op::Add]),
+            ("two()=2", "two() = 2", &[Op::LdcI8(2)]),
+            ("g(x)=x*k", "g(x) = x * k", &[Op::Ldarg(0), Op::Ldvar(0), Op::Mul]),
+            ("h(n)=n<=1?1:n*h(n-1)", "h(n) = n <= 1 ? 1 : n * h(n - 1)", &[
+                Op::Ldarg(0), Op::LdcI8(1), Op::Cle, Op::Brfalse(6),
+                Op::LdcI8(1), Op::Br(12),
+                Op::Ldarg(0), Op::Ldarg(0), Op::LdcI8(1), Op::Sub, Op::Call(0, 1), Op::Mul,
+            ]),
+            ("inc(x)=x=x+1", "inc(x) = x = x + 1", &[Op::Ldarg(0), Op::LdcI8(1), Op::Add, Op::Dup, Op::Starg(0)]),
+        ];
+
+        let tokenizer = Tokenizer::new();
+
+        for &(input, expected_output, expected_ops) in test_cases {
+            let tokens = tokenizer.tokenize(input).collect::<Vec<_>>();
+
+            let result = parse_expression(&tokens);
+
+            assert!(result.errors.is_empty());
+            assert_eq!(result.expr.to_string(), expected_output);
+
+            let mut method_builder = MethodBuilder::new();
+            result.expr.emit_bytecode(&mut method_builder)?;
+
+            assert_eq!(&method_builder.ops[..], &[Op::Defun(0)]);
+            assert_eq!(&method_builder.functions[0].method.ops[..], expected_ops);
+        }
+
+        Ok(())
+    }
+}
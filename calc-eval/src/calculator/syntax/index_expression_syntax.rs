@@ -0,0 +1,105 @@
+use std::fmt::Display;
+use crate::calculator::{
+    error::Result,
+    interpreter::{Closure, ClosureCompiler, MethodBuilder, Op},
+    tokenizer::Span
+};
+use super::{
+    expression_syntax::{ExpressionPrecedence, ExpressionSyntax},
+    parser::Parser,
+    primary_expression_syntax::PrimaryExpressionSyntax,
+    syntax::Syntax
+};
+
+/// An element of a vector, like `v[0]`. Rows of a matrix are elements too, so `m[1][0]` is the first element of the
+/// second row.
+#[derive(Clone)]
+pub struct IndexExpressionSyntax {
+    target_expr: Box<dyn ExpressionSyntax>,
+    index_expr: Box<dyn ExpressionSyntax>,
+    span: Span
+}
+
+impl IndexExpressionSyntax {
+    /// Parses a primary expression followed by any number of indices.
+    pub fn parse_expression(parser: &mut Parser) -> Box<dyn ExpressionSyntax> {
+        let mut expr = PrimaryExpressionSyntax::parse_expression(parser);
+
+        while parser.try_consume_operator("[").is_some() {
+            let index_expr = parser.parse_expression();
+            if parser.try_consume_operator("]").is_none() {
+                parser.report_unexpected("']'");
+                parser.skip_until(&["]"]);
+                parser.try_consume_operator("]");
+            }
+
+            let span = expr.get_span().merge(parser.previous().span);
+            expr = Box::new(IndexExpressionSyntax {
+                target_expr: expr,
+                index_expr,
+                span
+            });
+        }
+
+        expr
+    }
+}
+
+impl ExpressionSyntax for IndexExpressionSyntax {
+    fn get_expression_precedence(&self) -> ExpressionPrecedence {
+        ExpressionPrecedence::Primary
+    }
+
+    fn get_span(&self) -> Span {
+        self.span
+    }
+
+    fn emit_bytecode(&self, method_builder: &mut MethodBuilder) -> Result<()> {
+        self.target_expr.emit_bytecode(method_builder)?;
+        self.index_expr.emit_bytecode(method_builder)?;
+
+        method_builder.emit_spanned(Op::Ldelem, self.span);
+
+        Ok(())
+    }
+
+    fn compile_closure(&self, compiler: &ClosureCompiler) -> Result<Closure> {
+        let target = self.target_expr.compile_closure(compiler)?;
+        let index = self.index_expr.compile_closure(compiler)?;
+        Ok(compiler.binary(Op::Ldelem, target, index, self.span))
+    }
+
+    fn get_label(&self) -> String {
+        "index".to_owned()
+    }
+
+    fn get_children(&self) -> Vec<&dyn ExpressionSyntax> {
+        vec![self.target_expr.as_ref(), self.index_expr.as_ref()]
+    }
+
+    fn derivative(&self, var: &str) -> Result<Box<dyn ExpressionSyntax>> {
+        Ok(Box::new(IndexExpressionSyntax {
+            target_expr: self.target_expr.derivative(var)?,
+            index_expr: self.index_expr.clone(),
+            span: self.span
+        }))
+    }
+
+    fn clone_expression(&self) -> Box<dyn ExpressionSyntax> {
+        Box::new(self.clone())
+    }
+}
+
+impl Syntax for IndexExpressionSyntax { }
+
+impl Display for IndexExpressionSyntax {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let needs_parenthesis = self.target_expr.get_expression_precedence() > self.get_expression_precedence();
+        if needs_parenthesis {
+            write!(f, "({})[{}]", self.target_expr, self.index_expr)
+        }
+        else {
+            write!(f, "{}[{}]", self.target_expr, self.index_expr)
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use std::fmt::Display;
+use crate::calculator::{
+    error::{runtime_error, Result},
+    interpreter::{Closure, ClosureCompiler, MethodBuilder, Op},
+    tokenizer::Span
+};
+use super::{
+    comparison_expression_syntax::ComparisonExpressionSyntax,
+    expression_syntax::{ExpressionPrecedence, ExpressionSyntax},
+    parser::Parser,
+    syntax::Syntax
+};
+
+#[derive(Debug, Clone, Copy)]
+pub enum LogicalExpressionKind {
+    And,
+    Or
+}
+
+impl LogicalExpressionKind {
+    pub fn get_expression_precedence(&self) -> ExpressionPrecedence {
+        match self {
+            Self::And => ExpressionPrecedence::LogicalAnd,
+            Self::Or => ExpressionPrecedence::LogicalOr
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct LogicalExpressionSyntax {
+    kind: LogicalExpressionKind,
+    left_expr: Box<dyn ExpressionSyntax>,
+    right_expr: Box<dyn ExpressionSyntax>
+}
+
+impl LogicalExpressionSyntax {
+    pub fn parse_expression(parser: &mut Parser) -> Box<dyn ExpressionSyntax> {
+        Self::parse_expression_with_precedence(parser, ExpressionPrecedence::LogicalOr)
+    }
+
+    fn parse_operand(parser: &mut Parser, precedence: ExpressionPrecedence) -> Box<dyn ExpressionSyntax> {
+        if precedence == ExpressionPrecedence::LogicalOr {
+            Self::parse_expression_with_precedence(parser, ExpressionPrecedence::LogicalAnd)
+        }
+        else {
+            ComparisonExpressionSyntax::parse_expression(parser)
+        }
+    }
+
+    fn parse_expression_with_precedence(parser: &mut Parser, precedence: ExpressionPrecedence) -> Box<dyn ExpressionSyntax> {
+        let mut expr = Self::parse_operand(parser, precedence);
+
+        loop {
+            let token = parser.peek();
+            let mut kind = None;
+            if token.is_operator("&&") {
+                kind = Some(LogicalExpressionKind::And);
+            }
+            else if token.is_operator("||") {
+                kind = Some(LogicalExpressionKind::Or);
+            }
+
+            if kind.as_ref().is_none_or(|kind| kind.get_expression_precedence() != precedence) {
+                break;
+            }
+
+            parser.advance();
+
+            let rhs_expr = Self::parse_operand(parser, precedence);
+            expr = Box::new(LogicalExpressionSyntax {
+                kind: kind.unwrap(),
+                left_expr: expr,
+                right_expr: rhs_expr
+            });
+        }
+
+        expr
+    }
+}
+
+impl ExpressionSyntax for LogicalExpressionSyntax {
+    fn get_expression_precedence(&self) -> ExpressionPrecedence {
+        self.kind.get_expression_precedence()
+    }
+
+    fn get_span(&self) -> Span {
+        self.left_expr.get_span().merge(self.right_expr.get_span())
+    }
+
+    fn emit_bytecode(&self, method_builder: &mut MethodBuilder) -> Result<()> {
+        //The left value is kept on the stack as the result if it decides the outcome, otherwise it's replaced by the right value
+        self.left_expr.emit_bytecode(method_builder)?;
+        method_builder.ops.push(Op::Dup);
+
+        let jump_idx = match self.kind {
+            LogicalExpressionKind::And => method_builder.emit_jump(Op::Brfalse(0)),
+            LogicalExpressionKind::Or => method_builder.emit_jump(Op::Brtrue(0))
+        };
+        method_builder.set_span(jump_idx, self.left_expr.get_span());
+
+        method_builder.ops.push(Op::Pop);
+        self.right_expr.emit_bytecode(method_builder)?;
+        method_builder.patch_jump(jump_idx);
+
+        Ok(())
+    }
+
+    fn compile_closure(&self, compiler: &ClosureCompiler) -> Result<Closure> {
+        let left = self.left_expr.compile_closure(compiler)?;
+        let right = self.right_expr.compile_closure(compiler)?;
+        let left_span = self.left_expr.get_span();
+        let is_and = matches!(self.kind, LogicalExpressionKind::And);
+
+        Ok(Box::new(move |context| {
+            let val = left(context)?;
+            if val.to_bool().map_err(|err| err.with_span(left_span))? != is_and {
+                return Ok(val);
+            }
+            right(context)
+        }))
+    }
+
+    fn get_label(&self) -> String {
+        match self.kind {
+            LogicalExpressionKind::And => "&&".to_owned(),
+            LogicalExpressionKind::Or => "||".to_owned()
+        }
+    }
+
+    fn get_children(&self) -> Vec<&dyn ExpressionSyntax> {
+        vec![self.left_expr.as_ref(), self.right_expr.as_ref()]
+    }
+
+    fn derivative(&self, _var: &str) -> Result<Box<dyn ExpressionSyntax>> {
+        Err(runtime_error!("Logical operators can't be differentiated").with_span(self.get_span()))
+    }
+
+    fn clone_expression(&self) -> Box<dyn ExpressionSyntax> {
+        Box::new(self.clone())
+    }
+}
+
+impl Syntax for LogicalExpressionSyntax { }
+
+impl Display for LogicalExpressionSyntax {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        {
+            let needs_parenthesis = self.left_expr.get_expression_precedence() > self.get_expression_precedence();
+            if needs_parenthesis {
+                write!(f, "({})", self.left_expr)?;
+            }
+            else {
+                write!(f, "{}", self.left_expr)?;
+            }
+        }
+
+        match self.kind {
+            LogicalExpressionKind::And => {
+                write!(f, " && ")?;
+            },
+            LogicalExpressionKind::Or => {
+                write!(f, " || ")?;
+            }
+        };
+
+        {
+            let needs_parenthesis = self.right_expr.get_expression_precedence() >= self.get_expression_precedence();
+            if needs_parenthesis {
+                write!(f, "({})", self.right_expr)?;
+            }
+            else {
+                write!(f, "{}", self.right_expr)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calculator::{
+        syntax::parse_expression,
+        tokenizer::Tokenizer
+    };
+    use super::*;
+
+    #[test]
+    fn emit_bytecode_should_emit_correct_bytecode() -> Result<()> {
+        let test_cases: &[(&str, &[Op])] = &[
+            ("a&&b", &[Op::Ldvar(0), Op::Dup, Op::Brfalse(5), Op::Pop, Op::Ldvar(1)]),
+            ("a||b", &[Op::Ldvar(0), Op::Dup, Op::Brtrue(5), Op::Pop, Op::Ldvar(1)]),
+            ("a||b&&c", &[
+                Op::Ldvar(0), Op::Dup, Op::Brtrue(9), Op::Pop,
+                Op::Ldvar(1), Op::Dup, Op::Brfalse(9), Op::Pop, Op::Ldvar(2)
+            ]),
+            ("a>1&&b!=0", &[
+                Op::Ldvar(0), Op::LdcI8(1), Op::Cgt, Op::Dup, Op::Brfalse(9), Op::Pop,
+                Op::Ldvar(1), Op::LdcI8(0), Op::Cne
+            ]),
+        ];
+
+        let tokenizer = Tokenizer::new();
+
+        for &(input, expected_ops) in test_cases {
+            let tokens = tokenizer.tokenize(input).collect::<Vec<_>>();
+
+            let result = parse_expression(&tokens);
+
+            assert!(result.errors.is_empty());
+
+            let mut method_builder = MethodBuilder::new();
+            result.expr.emit_bytecode(&mut method_builder)?;
+
+            assert_eq!(&method_builder.ops[..], expected_ops);
+        }
+
+        Ok(())
+    }
+}
@@ -1,8 +1,27 @@
 mod additive_expression_syntax;
+mod assignment_expression_syntax;
+mod call_expression_syntax;
+mod comparison_expression_syntax;
+mod conditional_expression_syntax;
+mod conversion_expression_syntax;
+mod derivative_expression_syntax;
+mod error_expression_syntax;
 mod expression_syntax;
+mod function_definition_syntax;
+mod index_expression_syntax;
+mod logical_expression_syntax;
 mod multiplicative_expression_syntax;
+mod parse_error;
+mod parser;
+mod power_expression_syntax;
 mod primary_expression_syntax;
+mod simplifier;
 mod syntax;
 mod unary_expression_syntax;
+mod unit_syntax;
+mod vector_expression_syntax;
 
-pub use expression_syntax::try_parse_expression;
+pub use expression_syntax::{format_syntax_tree, parse_expression, ExpressionPrecedence, ExpressionSyntax};
+pub use parse_error::ParseError;
+pub use parser::ParseResult;
+pub use syntax::Syntax;
@@ -1,21 +1,25 @@
 use std::fmt::Display;
 use crate::calculator::{
-    interpreter::{MethodBuilder, Op},
-    tokenizer::Token
+    error::{runtime_error, Result},
+    interpreter::{Closure, ClosureCompiler, MethodBuilder, Op},
+    tokenizer::Span
 };
 use super::{
     expression_syntax::{ExpressionPrecedence, ExpressionSyntax},
+    parser::Parser,
+    simplifier,
     syntax::Syntax,
     unary_expression_syntax::UnaryExpressionSyntax
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum MultiplicativeExpressionKind {
     Multiply,
     Divide,
     Modulus
 }
 
+#[derive(Clone)]
 pub struct MultiplicativeExpressionSyntax {
     kind: MultiplicativeExpressionKind,
     left_expr: Box<dyn ExpressionSyntax>,
@@ -23,15 +27,19 @@ pub struct MultiplicativeExpressionSyntax {
 }
 
 impl MultiplicativeExpressionSyntax {
-    pub fn try_parse_expression(tokens: &Vec<Token>, pos: &mut usize) -> Option<Box<dyn ExpressionSyntax>> {
-        let mut expr_opt = UnaryExpressionSyntax::try_parse_expression(tokens, pos);
-        if expr_opt.is_none() {
-            return None
+    pub fn new(kind: MultiplicativeExpressionKind, left_expr: Box<dyn ExpressionSyntax>, right_expr: Box<dyn ExpressionSyntax>) -> Self {
+        Self {
+            kind,
+            left_expr,
+            right_expr
         }
+    }
+
+    pub fn parse_expression(parser: &mut Parser) -> Box<dyn ExpressionSyntax> {
+        let mut expr = UnaryExpressionSyntax::parse_expression(parser);
 
-        let mut npos = *pos;
-        while npos < tokens.len() - 1 {
-            let token = &tokens[npos];
+        loop {
+            let token = parser.peek();
             let mut kind = None;
             if token.is_operator("*") {
                 kind = Some(MultiplicativeExpressionKind::Multiply);
@@ -47,22 +55,17 @@ impl MultiplicativeExpressionSyntax {
                 break;
             }
 
-            npos += 1;
+            parser.advance();
 
-            let rhs_expr_opt = UnaryExpressionSyntax::try_parse_expression(tokens, &mut npos);
-            if rhs_expr_opt.is_none() {
-                break;
-            }
-
-            *pos = npos;
-            expr_opt = Some(Box::new(MultiplicativeExpressionSyntax {
+            let rhs_expr = UnaryExpressionSyntax::parse_expression(parser);
+            expr = Box::new(MultiplicativeExpressionSyntax {
                 kind: kind.unwrap(),
-                left_expr: expr_opt.unwrap(),
-                right_expr: rhs_expr_opt.unwrap()
-            }));
+                left_expr: expr,
+                right_expr: rhs_expr
+            });
         }
 
-        expr_opt
+        expr
     }
 }
 
@@ -71,24 +74,80 @@ impl ExpressionSyntax for MultiplicativeExpressionSyntax {
         ExpressionPrecedence::Multiplicative
     }
 
-    fn emit_bytecode(&self, method_builder: &mut MethodBuilder) -> anyhow::Result<()> {
+    fn get_span(&self) -> Span {
+        self.left_expr.get_span().merge(self.right_expr.get_span())
+    }
+
+    fn emit_bytecode(&self, method_builder: &mut MethodBuilder) -> Result<()> {
         self.left_expr.emit_bytecode(method_builder)?;
         self.right_expr.emit_bytecode(method_builder)?;
 
         match self.kind {
             MultiplicativeExpressionKind::Multiply => {
-                method_builder.ops.push(Op::Mul);
+                method_builder.emit_spanned(Op::Mul, self.get_span());
             },
             MultiplicativeExpressionKind::Divide => {
-                method_builder.ops.push(Op::Div);
+                method_builder.emit_spanned(Op::Div, self.get_span());
             },
             MultiplicativeExpressionKind::Modulus => {
-                method_builder.ops.push(Op::Rem);
+                method_builder.emit_spanned(Op::Rem, self.get_span());
             }
         }
 
         Ok(())
     }
+
+    fn compile_closure(&self, compiler: &ClosureCompiler) -> Result<Closure> {
+        let op = match self.kind {
+            MultiplicativeExpressionKind::Multiply => Op::Mul,
+            MultiplicativeExpressionKind::Divide => Op::Div,
+            MultiplicativeExpressionKind::Modulus => Op::Rem
+        };
+
+        let lhs = self.left_expr.compile_closure(compiler)?;
+        let rhs = self.right_expr.compile_closure(compiler)?;
+        Ok(compiler.binary(op, lhs, rhs, self.get_span()))
+    }
+
+    fn get_label(&self) -> String {
+        match self.kind {
+            MultiplicativeExpressionKind::Multiply => "*".to_owned(),
+            MultiplicativeExpressionKind::Divide => "/".to_owned(),
+            MultiplicativeExpressionKind::Modulus => "%".to_owned()
+        }
+    }
+
+    fn get_children(&self) -> Vec<&dyn ExpressionSyntax> {
+        vec![self.left_expr.as_ref(), self.right_expr.as_ref()]
+    }
+
+    fn derivative(&self, var: &str) -> Result<Box<dyn ExpressionSyntax>> {
+        let left_derivative = self.left_expr.derivative(var)?;
+        let right_derivative = self.right_expr.derivative(var)?;
+
+        match self.kind {
+            MultiplicativeExpressionKind::Multiply => Ok(simplifier::add(
+                simplifier::multiply(left_derivative, self.right_expr.clone()),
+                simplifier::multiply(self.left_expr.clone(), right_derivative)
+            )),
+            //The quotient rule only needs the square of the divisor when the divisor itself depends on `var`
+            MultiplicativeExpressionKind::Divide if simplifier::is_zero(right_derivative.as_ref()) => {
+                Ok(simplifier::divide(left_derivative, self.right_expr.clone()))
+            },
+            MultiplicativeExpressionKind::Divide => Ok(simplifier::divide(
+                simplifier::subtract(
+                    simplifier::multiply(left_derivative, self.right_expr.clone()),
+                    simplifier::multiply(self.left_expr.clone(), right_derivative)
+                ),
+                simplifier::power(self.right_expr.clone(), simplifier::number(2.0, self.right_expr.get_span()))
+            )),
+            MultiplicativeExpressionKind::Modulus => Err(runtime_error!("'%' can't be differentiated").with_span(self.get_span()))
+        }
+    }
+
+    fn clone_expression(&self) -> Box<dyn ExpressionSyntax> {
+        Box::new(self.clone())
+    }
 }
 
 impl Syntax for MultiplicativeExpressionSyntax { }
@@ -134,37 +193,34 @@ impl Display for MultiplicativeExpressionSyntax {
 #[cfg(test)]
 mod tests {
     use crate::calculator::{
-        syntax::try_parse_expression,
+        syntax::parse_expression,
         tokenizer::Tokenizer
     };
-    use anyhow::*;
     use super::*;
 
     #[test]
     fn emit_bytecode_should_emit_correct_bytecode() -> Result<()> {
         let test_cases: &[(&str, &[Op])] = &[
-            ("1*2", &[Op::LdcF8(1.0), Op::LdcF8(2.0), Op::Mul]),
-            ("3/4", &[Op::LdcF8(3.0), Op::LdcF8(4.0), Op::Div]),
-            ("5%6", &[Op::LdcF8(5.0), Op::LdcF8(6.0), Op::Rem]),
-            ("1/2/3", &[Op::LdcF8(1.0), Op::LdcF8(2.0), Op::Div, Op::LdcF8(3.0), Op::Div]),
-            ("1/(2/3)", &[Op::LdcF8(1.0), Op::LdcF8(2.0), Op::LdcF8(3.0), Op::Div, Op::Div]),
-            ("-5*-3", &[Op::LdcF8(5.0), Op::Neg, Op::LdcF8(3.0), Op::Neg, Op::Mul]),
-            ("-(4*6)", &[Op::LdcF8(4.0), Op::LdcF8(6.0), Op::Mul, Op::Neg]),
+            ("1*2", &[Op::LdcI8(1), Op::LdcI8(2), Op::Mul]),
+            ("3/4", &[Op::LdcI8(3), Op::LdcI8(4), Op::Div]),
+            ("5%6", &[Op::LdcI8(5), Op::LdcI8(6), Op::Rem]),
+            ("1/2/3", &[Op::LdcI8(1), Op::LdcI8(2), Op::Div, Op::LdcI8(3), Op::Div]),
+            ("1/(2/3)", &[Op::LdcI8(1), Op::LdcI8(2), Op::LdcI8(3), Op::Div, Op::Div]),
+            ("-5*-3", &[Op::LdcI8(5), Op::Neg, Op::LdcI8(3), Op::Neg, Op::Mul]),
+            ("-(4*6)", &[Op::LdcI8(4), Op::LdcI8(6), Op::Mul, Op::Neg]),
         ];
 
         let tokenizer = Tokenizer::new();
 
         for &(input, expected_ops) in test_cases {
-            let tokens = tokenizer.tokenize(input).collect();
+            let tokens = tokenizer.tokenize(input).collect::<Vec<_>>();
 
-            let mut pos = 0;
-            let expr = try_parse_expression(&tokens, &mut pos);
+            let result = parse_expression(&tokens);
 
-            assert!(expr.is_some());
-            assert_eq!(pos, tokens.len() - 1);
+            assert!(result.errors.is_empty());
 
             let mut method_builder = MethodBuilder::new();
-            expr.unwrap().emit_bytecode(&mut method_builder)?;
+            result.expr.emit_bytecode(&mut method_builder)?;
 
             assert_eq!(&method_builder.ops[..], expected_ops);
         }
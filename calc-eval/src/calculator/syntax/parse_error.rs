@@ -0,0 +1,93 @@
+use std::fmt::Display;
+use crate::calculator::{
+    diagnostic::{Diagnostic, Diagnostics},
+    error::CalcError,
+    tokenizer::{Span, Token, TokenKind}
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The tokenizer could not make sense of part of the input.
+    Lexical {
+        source: String,
+        span: Span
+    },
+    /// A literal was tokenized but can't be represented, like a hex literal that doesn't fit in 64 bits.
+    InvalidLiteral {
+        message: String,
+        span: Span
+    },
+    /// The input is well formed but can't be handled, like a unit whose exponents overflow or nesting that goes too deep.
+    Invalid {
+        message: String,
+        span: Span
+    },
+    /// The parser found a token where it expected something else.
+    Unexpected {
+        expected: String,
+        found: String,
+        span: Span
+    }
+}
+
+impl ParseError {
+    /// Describes `token` appearing where `expected` was required.
+    pub fn unexpected(expected: &str, token: &Token) -> Self {
+        match token.get_kind() {
+            TokenKind::Error => Self::Lexical {
+                source: token.source.clone(),
+                span: token.span
+            },
+            TokenKind::EOF => Self::Unexpected {
+                expected: expected.to_string(),
+                found: String::from("end of input"),
+                span: token.span
+            },
+            _ => Self::Unexpected {
+                expected: expected.to_string(),
+                found: format!("'{}'", token.source),
+                span: token.span
+            }
+        }
+    }
+
+    pub fn get_span(&self) -> Span {
+        match self {
+            Self::Lexical { span, .. } |
+            Self::InvalidLiteral { span, .. } |
+            Self::Invalid { span, .. } |
+            Self::Unexpected { span, .. } => *span
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Lexical { source, .. } => write!(f, "Unrecognized input: '{}'.", source),
+            Self::InvalidLiteral { message, .. } |
+            Self::Invalid { message, .. } => write!(f, "{}.", message),
+            Self::Unexpected { expected, found, .. } => write!(f, "Expected {}, found {}.", expected, found)
+        }
+    }
+}
+
+impl From<ParseError> for Diagnostic {
+    fn from(value: ParseError) -> Self {
+        Diagnostic::new(value.to_string(), value.get_span())
+    }
+}
+
+/// Input that only went wrong because part of it couldn't be tokenized is reported as a lexical error.
+impl From<Vec<ParseError>> for CalcError {
+    fn from(value: Vec<ParseError>) -> Self {
+        let is_lexical = value.iter().all(|error| matches!(error, ParseError::Lexical { .. } | ParseError::InvalidLiteral { .. }));
+        let diagnostics = Diagnostics(value.into_iter().map(Diagnostic::from).collect());
+        if is_lexical {
+            CalcError::Lex(diagnostics)
+        }
+        else {
+            CalcError::Parse(diagnostics)
+        }
+    }
+}
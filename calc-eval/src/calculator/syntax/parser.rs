@@ -0,0 +1,255 @@
+use crate::calculator::tokenizer::{Token, TokenKind};
+use super::{
+    assignment_expression_syntax::AssignmentExpressionSyntax,
+    error_expression_syntax::ErrorExpressionSyntax,
+    expression_syntax::ExpressionSyntax,
+    function_definition_syntax::FunctionDefinitionSyntax,
+    parse_error::ParseError
+};
+
+/// Tokens that close an enclosing construct. The parser stops at these instead of skipping them while recovering.
+const BOUNDARY_OPERATORS: [&str; 4] = [")", "]", ",", ":"];
+
+/// How deeply expressions may nest inside each other, which keeps deeply nested input from overflowing the stack of
+/// the parser and of everything that walks the syntax tree afterwards.
+const MAX_NESTING_DEPTH: usize = 100;
+
+pub struct ParseResult {
+    pub expr: Box<dyn ExpressionSyntax>,
+    pub errors: Vec<ParseError>
+}
+
+/// Cursor over a token stream that records errors instead of giving up on the first one.
+pub struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    errors: Vec<ParseError>,
+    /// How many calls to `parse_nested` are in progress.
+    depth: usize,
+    /// Set once the input turned out to be nested too deeply, after which nothing else is parsed or reported.
+    is_abandoned: bool
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(tokens: &'a [Token]) -> Self {
+        debug_assert!(tokens.last().is_some_and(|token| token.get_kind() == TokenKind::EOF));
+
+        Self {
+            tokens,
+            pos: 0,
+            errors: vec![],
+            depth: 0,
+            is_abandoned: false
+        }
+    }
+
+    /// Parses a complete expression or function definition, reporting anything left over after it.
+    pub fn parse(mut self) -> ParseResult {
+        let expr = if FunctionDefinitionSyntax::is_at_definition(&self) {
+            FunctionDefinitionSyntax::parse_expression(&mut self)
+        }
+        else {
+            self.parse_expression()
+        };
+
+        while !self.is_at_end() {
+            self.report_unexpected("end of input");
+            self.advance();
+
+            //Skip ahead to something that can start an expression and parse it only to report the errors it contains
+            while !self.is_at_end() && !self.can_start_expression() {
+                self.advance();
+            }
+            if !self.is_at_end() {
+                self.parse_expression();
+            }
+        }
+
+        ParseResult {
+            expr,
+            errors: self.errors
+        }
+    }
+
+    /// Parses a nested expression, such as a call argument or the contents of parentheses.
+    pub fn parse_expression(&mut self) -> Box<dyn ExpressionSyntax> {
+        self.parse_nested(AssignmentExpressionSyntax::parse_expression)
+    }
+
+    /// Parses with `parse` one level deeper. Rules that recurse into themselves go through here, so that input nested
+    /// too deeply is reported instead of overflowing the stack. The rest of the input is skipped when that happens.
+    pub fn parse_nested(&mut self, parse: fn(&mut Parser<'a>) -> Box<dyn ExpressionSyntax>) -> Box<dyn ExpressionSyntax> {
+        if self.depth >= MAX_NESTING_DEPTH {
+            let span = self.peek().span;
+            self.report(ParseError::Invalid {
+                message: String::from("Expression is nested too deeply"),
+                span
+            });
+            self.is_abandoned = true;
+            while !self.is_at_end() {
+                self.advance();
+            }
+            return ErrorExpressionSyntax::at(span);
+        }
+
+        self.depth += 1;
+        let expr = parse(self);
+        self.depth -= 1;
+        expr
+    }
+
+    pub fn peek(&self) -> &'a Token {
+        self.peek_at(0)
+    }
+
+    pub fn peek_next(&self) -> &'a Token {
+        self.peek_at(1)
+    }
+
+    /// Looks `offset` tokens ahead without consuming anything. Looking past the end returns the EOF token.
+    pub fn peek_at(&self, offset: usize) -> &'a Token {
+        &self.tokens[(self.pos + offset).min(self.tokens.len() - 1)]
+    }
+
+    /// Returns the most recently consumed token.
+    pub fn previous(&self) -> &'a Token {
+        &self.tokens[self.pos.saturating_sub(1)]
+    }
+
+    pub fn is_at_end(&self) -> bool {
+        self.peek().get_kind() == TokenKind::EOF
+    }
+
+    pub fn advance(&mut self) -> &'a Token {
+        let token = self.peek();
+        if !self.is_at_end() {
+            self.pos += 1;
+        }
+        token
+    }
+
+    pub fn is_operator(&self, op: &str) -> bool {
+        self.peek().is_operator(op)
+    }
+
+    pub fn try_consume_operator(&mut self, op: &str) -> Option<&'a Token> {
+        if self.is_operator(op) {
+            Some(self.advance())
+        }
+        else {
+            None
+        }
+    }
+
+    pub fn is_at_boundary(&self) -> bool {
+        self.is_at_end() || BOUNDARY_OPERATORS.iter().any(|op| self.is_operator(op))
+    }
+
+    /// Unrecognized input counts too, so that it gets parsed and reported as a lexical error rather than skipped.
+    pub fn can_start_expression(&self) -> bool {
+        let token = self.peek();
+        token.get_kind() == TokenKind::Error
+            || token.is_literal()
+            || token.is_identifier()
+            || ["(", "[", "+", "-", "!"].iter().any(|op| token.is_operator(op))
+    }
+
+    /// Records that the current token is not what the grammar `expected`.
+    pub fn report_unexpected(&mut self, expected: &str) {
+        self.report(ParseError::unexpected(expected, self.peek()));
+    }
+
+    pub fn report(&mut self, error: ParseError) {
+        if self.is_abandoned {
+            return;
+        }
+        //A single mistake often trips several rules at the same place; only the first of them is worth reporting
+        if self.errors.iter().any(|existing| existing.get_span().start == error.get_span().start) {
+            return;
+        }
+        self.errors.push(error);
+    }
+
+    /// Skips tokens until one of `stop_ops` is found outside any nested parentheses or brackets, or the input ends.
+    pub fn skip_until(&mut self, stop_ops: &[&str]) {
+        let mut depth = 0usize;
+        while !self.is_at_end() {
+            if depth == 0 && stop_ops.iter().any(|op| self.is_operator(op)) {
+                break;
+            }
+
+            if self.is_operator("(") || self.is_operator("[") {
+                depth += 1;
+            }
+            else if self.is_operator(")") || self.is_operator("]") {
+                if depth == 0 {
+                    break;
+                }
+                depth -= 1;
+            }
+            self.advance();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calculator::{
+        syntax::parse_expression,
+        tokenizer::Tokenizer
+    };
+
+    type ExpectedError = (&'static str, (usize, usize));
+
+    #[test]
+    fn parse_expression_should_report_every_error() {
+        let test_cases: &[(&str, &str, &[ExpectedError])] = &[
+            ("", "<error>", &[("Expected expression, found end of input.", (0, 0))]),
+            ("1 +", "1 + <error>", &[("Expected expression, found end of input.", (3, 3))]),
+            ("1 + * 2", "1 + 2", &[("Expected expression, found '*'.", (4, 5))]),
+            ("3 * $x", "3 * <error>", &[("Unrecognized input: '$x'.", (4, 6))]),
+            ("(1 + 2", "1 + 2", &[("Expected ')', found end of input.", (6, 6))]),
+            ("(1 2) * 3", "1 * 3", &[("Expected ')', found '2'.", (3, 4))]),
+            ("1 + 2 )", "1 + 2", &[("Expected end of input, found ')'.", (6, 7))]),
+            ("a ? 1 2", "a ? 1 : <error>", &[("Expected ':', found '2'.", (6, 7))]),
+            ("3 km in parsecs", "3 km", &[("Expected unit, found 'parsecs'.", (8, 15))]),
+            ("max(1 2, 3 4, 5)", "max(1, 3, 5)", &[
+                ("Expected ',' or ')', found '2'.", (6, 7)),
+                ("Expected ',' or ')', found '4'.", (11, 12)),
+            ]),
+            ("f(1, , 2) + (3 * ) - g(4 $ 5", "f(1, <error>, 2) + 3 * <error> - g(4)", &[
+                ("Expected expression, found ','.", (5, 6)),
+                ("Expected expression, found ')'.", (17, 18)),
+                ("Unrecognized input: '$'.", (25, 26)),
+                ("Expected ')', found end of input.", (28, 28)),
+            ]),
+            ("[1 2, [3, 4 5]] + [", "[1, [3, 4]] + [<error>]", &[
+                ("Expected ',' or ']', found '2'.", (3, 4)),
+                ("Expected ',' or ']', found '5'.", (12, 13)),
+                ("Expected expression, found end of input.", (19, 19)),
+            ]),
+            ("v[1 + ] * 2", "v[1 + <error>] * 2", &[("Expected expression, found ']'.", (6, 7))]),
+            ("1 + 2 ) * (3 +", "1 + 2", &[
+                ("Expected end of input, found ')'.", (6, 7)),
+                ("Expected expression, found end of input.", (14, 14)),
+            ]),
+        ];
+
+        let tokenizer = Tokenizer::new();
+
+        for &(input, expected_output, expected_errors) in test_cases {
+            let tokens = tokenizer.tokenize(input).collect::<Vec<_>>();
+            let result = parse_expression(&tokens);
+
+            let errors = result.errors.iter()
+                .map(|error| (error.to_string(), (error.get_span().start, error.get_span().end)))
+                .collect::<Vec<_>>();
+            let expected_errors = expected_errors.iter()
+                .map(|&(message, span)| (message.to_string(), span))
+                .collect::<Vec<_>>();
+
+            assert_eq!(errors, expected_errors, "for input {:?}", input);
+            assert_eq!(result.expr.to_string(), expected_output, "for input {:?}", input);
+        }
+    }
+}
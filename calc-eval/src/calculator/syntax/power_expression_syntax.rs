@@ -0,0 +1,184 @@
+use std::fmt::Display;
+use crate::calculator::{
+    error::Result,
+    interpreter::{Closure, ClosureCompiler, MethodBuilder, Op},
+    tokenizer::Span
+};
+use super::{
+    expression_syntax::{ExpressionPrecedence, ExpressionSyntax},
+    parser::Parser,
+    index_expression_syntax::IndexExpressionSyntax,
+    simplifier,
+    syntax::Syntax,
+    unary_expression_syntax::UnaryExpressionSyntax
+};
+
+#[derive(Clone)]
+pub struct PowerExpressionSyntax {
+    base_expr: Box<dyn ExpressionSyntax>,
+    exponent_expr: Box<dyn ExpressionSyntax>
+}
+
+impl PowerExpressionSyntax {
+    pub fn new(base_expr: Box<dyn ExpressionSyntax>, exponent_expr: Box<dyn ExpressionSyntax>) -> Self {
+        Self {
+            base_expr,
+            exponent_expr
+        }
+    }
+
+    pub fn parse_expression(parser: &mut Parser) -> Box<dyn ExpressionSyntax> {
+        let base_expr = IndexExpressionSyntax::parse_expression(parser);
+
+        if parser.try_consume_operator("^").is_some() {
+            //The exponent is parsed as a unary expression, which recurses back into this one, so `^` is right-associative
+            //and `2 ^ -1` is allowed, while a leading `-` still applies to the whole power (`-2 ^ 2` is `-(2 ^ 2)`)
+            let exponent_expr = parser.parse_nested(UnaryExpressionSyntax::parse_expression);
+            return Box::new(PowerExpressionSyntax {
+                base_expr,
+                exponent_expr
+            });
+        }
+
+        base_expr
+    }
+}
+
+impl ExpressionSyntax for PowerExpressionSyntax {
+    fn get_expression_precedence(&self) -> ExpressionPrecedence {
+        ExpressionPrecedence::Power
+    }
+
+    fn get_span(&self) -> Span {
+        self.base_expr.get_span().merge(self.exponent_expr.get_span())
+    }
+
+    fn emit_bytecode(&self, method_builder: &mut MethodBuilder) -> Result<()> {
+        self.base_expr.emit_bytecode(method_builder)?;
+        self.exponent_expr.emit_bytecode(method_builder)?;
+
+        method_builder.emit_spanned(Op::Pow, self.get_span());
+
+        Ok(())
+    }
+
+    fn compile_closure(&self, compiler: &ClosureCompiler) -> Result<Closure> {
+        let base = self.base_expr.compile_closure(compiler)?;
+        let exponent = self.exponent_expr.compile_closure(compiler)?;
+        Ok(compiler.binary(Op::Pow, base, exponent, self.get_span()))
+    }
+
+    fn get_label(&self) -> String {
+        "^".to_owned()
+    }
+
+    fn get_children(&self) -> Vec<&dyn ExpressionSyntax> {
+        vec![self.base_expr.as_ref(), self.exponent_expr.as_ref()]
+    }
+
+    fn derivative(&self, var: &str) -> Result<Box<dyn ExpressionSyntax>> {
+        let span = self.get_span();
+        let base_derivative = self.base_expr.derivative(var)?;
+        let exponent_derivative = self.exponent_expr.derivative(var)?;
+
+        if simplifier::is_zero(exponent_derivative.as_ref()) {
+            //Power rule: d(u ^ n) = n * u ^ (n - 1) * du
+            let lowered_exponent = match self.exponent_expr.get_number() {
+                Some(exponent) => simplifier::number(exponent - 1.0, self.exponent_expr.get_span()),
+                None => simplifier::subtract(self.exponent_expr.clone(), simplifier::number(1.0, self.exponent_expr.get_span()))
+            };
+            return Ok(simplifier::multiply(
+                simplifier::multiply(self.exponent_expr.clone(), simplifier::power(self.base_expr.clone(), lowered_exponent)),
+                base_derivative
+            ));
+        }
+
+        let ln_base = simplifier::call("ln", vec![self.base_expr.clone()], span);
+        if simplifier::is_zero(base_derivative.as_ref()) {
+            //d(a ^ v) = a ^ v * ln(a) * dv
+            return Ok(simplifier::multiply(simplifier::multiply(self.clone_expression(), ln_base), exponent_derivative));
+        }
+
+        //d(u ^ v) = u ^ v * (dv * ln(u) + v * du / u)
+        Ok(simplifier::multiply(
+            self.clone_expression(),
+            simplifier::add(
+                simplifier::multiply(exponent_derivative, ln_base),
+                simplifier::divide(simplifier::multiply(self.exponent_expr.clone(), base_derivative), self.base_expr.clone())
+            )
+        ))
+    }
+
+    fn clone_expression(&self) -> Box<dyn ExpressionSyntax> {
+        Box::new(self.clone())
+    }
+}
+
+impl Syntax for PowerExpressionSyntax { }
+
+impl Display for PowerExpressionSyntax {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        {
+            let needs_parenthesis = self.base_expr.get_expression_precedence() >= self.get_expression_precedence();
+            if needs_parenthesis {
+                write!(f, "({})", self.base_expr)?;
+            }
+            else {
+                write!(f, "{}", self.base_expr)?;
+            }
+        }
+
+        write!(f, " ^ ")?;
+
+        {
+            let needs_parenthesis = self.exponent_expr.get_expression_precedence() > ExpressionPrecedence::Unary;
+            if needs_parenthesis {
+                write!(f, "({})", self.exponent_expr)?;
+            }
+            else {
+                write!(f, "{}", self.exponent_expr)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calculator::{
+        syntax::parse_expression,
+        tokenizer::Tokenizer
+    };
+    use super::*;
+
+    #[test]
+    fn emit_bytecode_should_emit_correct_bytecode() -> Result<()> {
+        let test_cases: &[(&str, &[Op])] = &[
+            ("2^3", &[Op::LdcI8(2), Op::LdcI8(3), Op::Pow]),
+            ("2^3^2", &[Op::LdcI8(2), Op::LdcI8(3), Op::LdcI8(2), Op::Pow, Op::Pow]),
+            ("(2^3)^2", &[Op::LdcI8(2), Op::LdcI8(3), Op::Pow, Op::LdcI8(2), Op::Pow]),
+            ("-2^2", &[Op::LdcI8(2), Op::LdcI8(2), Op::Pow, Op::Neg]),
+            ("(-2)^2", &[Op::LdcI8(2), Op::Neg, Op::LdcI8(2), Op::Pow]),
+            ("2^-1", &[Op::LdcI8(2), Op::LdcI8(1), Op::Neg, Op::Pow]),
+            ("2*3^2", &[Op::LdcI8(2), Op::LdcI8(3), Op::LdcI8(2), Op::Pow, Op::Mul]),
+        ];
+
+        let tokenizer = Tokenizer::new();
+
+        for &(input, expected_ops) in test_cases {
+            let tokens = tokenizer.tokenize(input).collect::<Vec<_>>();
+
+            let result = parse_expression(&tokens);
+
+            assert!(result.errors.is_empty());
+
+            let mut method_builder = MethodBuilder::new();
+            result.expr.emit_bytecode(&mut method_builder)?;
+
+            assert_eq!(&method_builder.ops[..], expected_ops);
+        }
+
+        Ok(())
+    }
+}
@@ -1,64 +1,290 @@
 use std::fmt::Display;
+use num_complex::Complex64;
 use crate::calculator::{
-    interpreter::{MethodBuilder, Op},
-    tokenizer::Token
+    error::{runtime_error, Result},
+    interpreter::{Closure, ClosureCompiler, Interpreter, MethodBuilder, Op, Quantity, Value},
+    tokenizer::{Span, Token, TokenKind}
 };
 use super::{
+    call_expression_syntax::CallExpressionSyntax,
+    derivative_expression_syntax::DerivativeExpressionSyntax,
+    error_expression_syntax::ErrorExpressionSyntax,
     expression_syntax::{ExpressionPrecedence, ExpressionSyntax},
-    syntax::Syntax
+    parse_error::ParseError,
+    parser::Parser,
+    simplifier,
+    syntax::Syntax,
+    unary_expression_syntax::UnaryExpressionSyntax,
+    unit_syntax::UnitSyntax,
+    vector_expression_syntax::VectorExpressionSyntax
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum PrimaryExpressionKind {
-    Literal
+    Literal,
+    Variable
 }
 
+#[derive(Clone)]
 pub struct PrimaryExpressionSyntax {
     kind: PrimaryExpressionKind,
-    literal_token: Option<Token>
+    literal_token: Option<Token>,
+    identifier_token: Option<Token>,
+    /// The unit written after a numeric literal, as in `3 km`.
+    unit_syntax: Option<UnitSyntax>
 }
 
 impl PrimaryExpressionSyntax {
-    pub fn try_parse_expression(tokens: &Vec<Token>, pos: &mut usize) -> Option<Box<dyn ExpressionSyntax>> {
-        if *pos >= tokens.len() {
-            return None;
-        }
+    /// Builds the float literal `val`, which must not be negative. It's a float even when it's written as a whole
+    /// number, so that a derivative like `1 / x` doesn't fall into integer division.
+    pub fn number(val: f64, span: Span) -> Box<dyn ExpressionSyntax> {
+        debug_assert!(val >= 0.0);
 
-        let token = &tokens[*pos];
+        Box::new(PrimaryExpressionSyntax {
+            kind: PrimaryExpressionKind::Literal,
+            literal_token: Some(Token {
+                source: val.to_string(),
+                token_kind: TokenKind::Float,
+                span
+            }),
+            identifier_token: None,
+            unit_syntax: None
+        })
+    }
+
+    pub fn parse_expression(parser: &mut Parser) -> Box<dyn ExpressionSyntax> {
+        let token = parser.peek();
         if token.is_literal() {
-            *pos += 1;
+            parser.advance();
+
+            //Decimal integers that are too large fall back to floats, the same way `1e20` is a float. A literal written
+            //in another base is a bit pattern, and rounding it would change the bits, so it has to fit
+            if token.get_kind().get_radix() != 10 {
+                if let Err(err) = i64::try_from(token) {
+                    parser.report(ParseError::InvalidLiteral {
+                        message: err.to_string(),
+                        span: token.span
+                    });
+                }
+            }
 
-            return Some(Box::new(PrimaryExpressionSyntax {
+            let is_numeric = token.get_kind().is_numeric();
+            let unit_syntax = if is_numeric && UnitSyntax::is_at_unit(parser, 0) {
+                UnitSyntax::parse(parser)
+            }
+            else {
+                None
+            };
+
+            return Box::new(PrimaryExpressionSyntax {
                 kind: PrimaryExpressionKind::Literal,
-                literal_token: Some(token.clone())
-            }))
+                literal_token: Some(token.clone()),
+                identifier_token: None,
+                unit_syntax
+            })
         }
 
-        if token.is_operator("(") {
-            let mut npos = *pos + 1;
-            let nested_expr_opt = super::expression_syntax::try_parse_expression(tokens, &mut npos);
-            if let Some(nested_expr) = nested_expr_opt {
-                if npos < tokens.len() && tokens[npos].is_operator(")") {
-                    *pos = npos + 1;
-                    return Some(nested_expr)
-                }
+        if DerivativeExpressionSyntax::is_at_derivative(parser) {
+            return DerivativeExpressionSyntax::parse_expression(parser);
+        }
+
+        if token.is_identifier() {
+            if parser.peek_next().is_operator("(") {
+                return CallExpressionSyntax::parse_expression(parser);
+            }
+
+            parser.advance();
+
+            return Box::new(PrimaryExpressionSyntax {
+                kind: PrimaryExpressionKind::Variable,
+                literal_token: None,
+                identifier_token: Some(token.clone()),
+                unit_syntax: None
+            })
+        }
+
+        if token.is_operator("[") {
+            return VectorExpressionSyntax::parse_expression(parser);
+        }
+
+        if parser.try_consume_operator("(").is_some() {
+            let nested_expr = parser.parse_expression();
+            if parser.try_consume_operator(")").is_none() {
+                parser.report_unexpected("')'");
+                parser.skip_until(&[")"]);
+                parser.try_consume_operator(")");
             }
+            return nested_expr;
         }
 
-        None
+        parser.report_unexpected("expression");
+        if token.get_kind() == TokenKind::Error {
+            parser.advance();
+            return ErrorExpressionSyntax::at(token.span);
+        }
+        if parser.is_at_boundary() {
+            return ErrorExpressionSyntax::at(token.span);
+        }
+
+        //Skip over stray operators (`1 + * 2`) and carry on with whatever expression follows them
+        while !parser.is_at_boundary() && !parser.can_start_expression() {
+            parser.advance();
+        }
+        if parser.is_at_boundary() {
+            return ErrorExpressionSyntax::at(token.span.merge(parser.previous().span));
+        }
+        UnaryExpressionSyntax::parse_expression(parser)
     }
 }
 
 impl ExpressionSyntax for PrimaryExpressionSyntax {
+    //`3 km` is really a product, so `(3 km) ^ 2` has to keep its parentheses
     fn get_expression_precedence(&self) -> ExpressionPrecedence {
-        ExpressionPrecedence::Primary
+        match self.unit_syntax {
+            Some(_) => ExpressionPrecedence::Power,
+            None => ExpressionPrecedence::Primary
+        }
+    }
+
+    fn get_span(&self) -> Span {
+        match self.kind {
+            PrimaryExpressionKind::Literal => {
+                let literal_span = self.literal_token.as_ref().unwrap().span;
+                match &self.unit_syntax {
+                    Some(unit_syntax) => literal_span.merge(unit_syntax.get_span()),
+                    None => literal_span
+                }
+            },
+            PrimaryExpressionKind::Variable => self.identifier_token.as_ref().unwrap().span
+        }
+    }
+
+    fn emit_bytecode(&self, method_builder: &mut MethodBuilder) -> Result<()> {
+        self.emit_bytecode_unspanned(method_builder)
+            .map_err(|err| err.with_span(self.get_span()))
     }
 
-    fn emit_bytecode(&self, method_builder: &mut MethodBuilder) -> anyhow::Result<()> {
+    fn compile_closure(&self, compiler: &ClosureCompiler) -> Result<Closure> {
         match self.kind {
             PrimaryExpressionKind::Literal => {
-                let val = f64::try_from(self.literal_token.as_ref().unwrap())?;
-                method_builder.ops.push(Op::LdcF8(val));
+                let val = self.get_literal_value().map_err(|err| err.with_span(self.get_span()))?;
+                let val = match val {
+                    Value::Integer(_) | Value::Float(_) => compiler.get_mode().load_constant(val),
+                    val => Ok(val)
+                };
+                let val = match &self.unit_syntax {
+                    Some(unit_syntax) => val.and_then(|val| Ok(Quantity::new(val.to_f64()?, unit_syntax.get_unit().clone()).into_value())),
+                    None => val
+                };
+                Ok(compiler.constant(val))
+            },
+            PrimaryExpressionKind::Variable => {
+                let name = self.identifier_token.as_ref().unwrap().source.clone();
+                let span = self.get_span();
+
+                Ok(Box::new(move |context| {
+                    Interpreter::load_variable(&name, context.environment).map_err(|err| err.with_span(span))
+                }))
+            }
+        }
+    }
+
+    fn get_label(&self) -> String {
+        //Literals and variables have nothing beneath them, so the label is the whole expression
+        self.to_string()
+    }
+
+    fn get_children(&self) -> Vec<&dyn ExpressionSyntax> {
+        vec![]
+    }
+
+    fn derivative(&self, var: &str) -> Result<Box<dyn ExpressionSyntax>> {
+        let span = self.get_span();
+        match self.kind {
+            PrimaryExpressionKind::Literal => match self.literal_token.as_ref().unwrap().get_kind() {
+                kind if kind.is_numeric() || kind == TokenKind::Imaginary => Ok(simplifier::number(0.0, span)),
+                _ => Err(runtime_error!("Only numbers can be differentiated, found {}", self).with_span(span))
+            },
+            PrimaryExpressionKind::Variable => {
+                let is_var = self.identifier_token.as_ref().unwrap().source == var;
+                Ok(simplifier::number(if is_var { 1.0 } else { 0.0 }, span))
+            }
+        }
+    }
+
+    fn clone_expression(&self) -> Box<dyn ExpressionSyntax> {
+        Box::new(self.clone())
+    }
+
+    fn get_number(&self) -> Option<f64> {
+        if self.unit_syntax.is_some() {
+            return None;
+        }
+
+        match self.kind {
+            PrimaryExpressionKind::Literal => self.literal_token.as_ref().and_then(|token| f64::try_from(token).ok()),
+            PrimaryExpressionKind::Variable => None
+        }
+    }
+
+    fn get_variable_name(&self) -> Option<&str> {
+        match self.kind {
+            PrimaryExpressionKind::Literal => None,
+            PrimaryExpressionKind::Variable => self.identifier_token.as_ref().map(|token| token.source.as_str())
+        }
+    }
+}
+
+impl PrimaryExpressionSyntax {
+    fn get_literal_value(&self) -> Result<Value> {
+        let literal_token = self.literal_token.as_ref().unwrap();
+        match literal_token.get_kind() {
+            //Decimal integer literals too large for an i64 fall back to a float rather than failing outright, like in
+            //most languages with a separate float type. The parser already refused other bases that don't fit
+            TokenKind::Integer => match i64::try_from(literal_token) {
+                Ok(val) => Ok(Value::Integer(val)),
+                Err(_) => Ok(Value::Float(f64::try_from(literal_token)?))
+            },
+            kind if kind.is_integer() => Ok(Value::Integer(i64::try_from(literal_token)?)),
+            TokenKind::Imaginary => {
+                let source = literal_token.source.trim_end_matches('i').replace('_', "");
+                let val = source.parse::<f64>().map_err(|err| runtime_error!("{}", err))?;
+                Ok(Value::from(Complex64::new(0.0, val)))
+            },
+            TokenKind::Boolean => Ok(Value::Boolean(bool::try_from(literal_token)?)),
+            TokenKind::String => Ok(Value::String(String::try_from(literal_token)?)),
+            _ => Ok(Value::Float(f64::try_from(literal_token)?))
+        }
+    }
+
+    fn emit_bytecode_unspanned(&self, method_builder: &mut MethodBuilder) -> Result<()> {
+        match self.kind {
+            PrimaryExpressionKind::Literal => {
+                match self.get_literal_value()? {
+                    Value::Integer(val) => method_builder.ops.push(Op::LdcI8(val)),
+                    Value::Complex(val) => method_builder.ops.push(Op::LdcImag(val.im)),
+                    Value::Boolean(val) => method_builder.ops.push(Op::LdcBool(val)),
+                    Value::String(val) => {
+                        let string_idx = method_builder.intern_string(&val);
+                        method_builder.ops.push(Op::Ldstr(string_idx));
+                    },
+                    val => method_builder.ops.push(Op::LdcF8(val.to_f64()?))
+                }
+
+                if let Some(unit_syntax) = &self.unit_syntax {
+                    let unit_idx = method_builder.intern_unit(unit_syntax.get_unit());
+                    method_builder.ops.push(Op::Unit(unit_idx));
+                }
+            },
+            PrimaryExpressionKind::Variable => {
+                let name = &self.identifier_token.as_ref().unwrap().source;
+                if let Some(arg_idx) = method_builder.get_parameter_index(name) {
+                    method_builder.ops.push(Op::Ldarg(arg_idx));
+                }
+                else {
+                    let name_idx = method_builder.intern_name(name);
+                    method_builder.emit_spanned(Op::Ldvar(name_idx), self.get_span());
+                }
             }
         }
 
@@ -73,6 +299,12 @@ impl Display for PrimaryExpressionSyntax {
         match self.kind {
             PrimaryExpressionKind::Literal => {
                 self.literal_token.as_ref().unwrap().repr(f)?;
+                if let Some(unit_syntax) = &self.unit_syntax {
+                    write!(f, " {}", unit_syntax)?;
+                }
+            },
+            PrimaryExpressionKind::Variable => {
+                self.identifier_token.as_ref().unwrap().repr(f)?;
             }
         };
 
@@ -83,33 +315,44 @@ impl Display for PrimaryExpressionSyntax {
 #[cfg(test)]
 mod tests {
     use crate::calculator::{
-        syntax::try_parse_expression,
+        syntax::parse_expression,
         tokenizer::Tokenizer
     };
-    use anyhow::*;
     use super::*;
 
     #[test]
     fn emit_bytecode_should_emit_correct_bytecode() -> Result<()> {
         let test_cases: &[(&str, &[Op])] = &[
-            ("25", &[Op::LdcF8(25.0)]),
+            ("25", &[Op::LdcI8(25)]),
             ("123.456", &[Op::LdcF8(123.456)]),
-            ("(42)", &[Op::LdcF8(42.0)]),
+            ("(42)", &[Op::LdcI8(42)]),
+            ("x", &[Op::Ldvar(0)]),
+            ("true", &[Op::LdcBool(true)]),
+            ("\"fish\"", &[Op::Ldstr(0)]),
+            ("9223372036854775807", &[Op::LdcI8(i64::MAX)]),
+            ("9223372036854775808", &[Op::LdcF8(9223372036854775808.0)]),
+            ("0xFF + 0b1_0000 + 0o17", &[Op::LdcI8(255), Op::LdcI8(16), Op::Add, Op::LdcI8(15), Op::Add]),
+            ("0x7FFF_FFFF_FFFF_FFFF", &[Op::LdcI8(i64::MAX)]),
+            ("1_000.5e-3", &[Op::LdcF8(1.0005)]),
+            (".5", &[Op::LdcF8(0.5)]),
+            ("2.5i", &[Op::LdcImag(2.5)]),
+            ("1_000i", &[Op::LdcImag(1000.0)]),
+            ("3 km", &[Op::LdcI8(3), Op::Unit(0)]),
+            ("9.81 m/s^2", &[Op::LdcF8(9.81), Op::Unit(0)]),
+            ("2 m * x", &[Op::LdcI8(2), Op::Unit(0), Op::Ldvar(0), Op::Mul]),
         ];
 
         let tokenizer = Tokenizer::new();
 
         for &(input, expected_ops) in test_cases {
-            let tokens = tokenizer.tokenize(input).collect();
+            let tokens = tokenizer.tokenize(input).collect::<Vec<_>>();
 
-            let mut pos = 0;
-            let expr = try_parse_expression(&tokens, &mut pos);
+            let result = parse_expression(&tokens);
 
-            assert!(expr.is_some());
-            assert_eq!(pos, tokens.len() - 1);
+            assert!(result.errors.is_empty());
 
             let mut method_builder = MethodBuilder::new();
-            expr.unwrap().emit_bytecode(&mut method_builder)?;
+            result.expr.emit_bytecode(&mut method_builder)?;
 
             assert_eq!(&method_builder.ops[..], expected_ops);
         }
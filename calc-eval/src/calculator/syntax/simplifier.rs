@@ -0,0 +1,139 @@
+use crate::calculator::tokenizer::Span;
+use super::{
+    additive_expression_syntax::{AdditiveExpressionKind, AdditiveExpressionSyntax},
+    call_expression_syntax::CallExpressionSyntax,
+    expression_syntax::ExpressionSyntax,
+    multiplicative_expression_syntax::{MultiplicativeExpressionKind, MultiplicativeExpressionSyntax},
+    power_expression_syntax::PowerExpressionSyntax,
+    primary_expression_syntax::PrimaryExpressionSyntax,
+    unary_expression_syntax::{UnaryExpressionKind, UnaryExpressionSyntax}
+};
+
+//Builders for the expressions a derivative is made of. Each one simplifies as it goes, dropping terms that are zero,
+//factors that are one and folding constants, so the result stays about as small as one written by hand
+
+/// Builds the number `val`, which is written as a negated literal if it's below zero.
+pub fn number(val: f64, span: Span) -> Box<dyn ExpressionSyntax> {
+    if val < 0.0 {
+        Box::new(UnaryExpressionSyntax::new(UnaryExpressionKind::Minus, PrimaryExpressionSyntax::number(-val, span), span))
+    }
+    else {
+        PrimaryExpressionSyntax::number(val, span)
+    }
+}
+
+pub fn is_zero(expr: &dyn ExpressionSyntax) -> bool {
+    expr.get_number() == Some(0.0)
+}
+
+fn is_one(expr: &dyn ExpressionSyntax) -> bool {
+    expr.get_number() == Some(1.0)
+}
+
+/// Folds `op` over two numbers, unless the result isn't finite and is better left for the interpreter to report.
+fn fold(lhs: &dyn ExpressionSyntax, rhs: &dyn ExpressionSyntax, op: impl Fn(f64, f64) -> f64) -> Option<f64> {
+    let val = op(lhs.get_number()?, rhs.get_number()?);
+    val.is_finite().then_some(val)
+}
+
+pub fn negate(expr: Box<dyn ExpressionSyntax>, span: Span) -> Box<dyn ExpressionSyntax> {
+    match expr.get_number() {
+        Some(val) => number(-val, span),
+        None => Box::new(UnaryExpressionSyntax::new(UnaryExpressionKind::Minus, expr, span))
+    }
+}
+
+pub fn add(lhs: Box<dyn ExpressionSyntax>, rhs: Box<dyn ExpressionSyntax>) -> Box<dyn ExpressionSyntax> {
+    if let Some(val) = fold(lhs.as_ref(), rhs.as_ref(), |lhs, rhs| lhs + rhs) {
+        return number(val, lhs.get_span().merge(rhs.get_span()));
+    }
+
+    if is_zero(lhs.as_ref()) {
+        rhs
+    }
+    else if is_zero(rhs.as_ref()) {
+        lhs
+    }
+    else {
+        Box::new(AdditiveExpressionSyntax::new(AdditiveExpressionKind::Add, lhs, rhs))
+    }
+}
+
+pub fn subtract(lhs: Box<dyn ExpressionSyntax>, rhs: Box<dyn ExpressionSyntax>) -> Box<dyn ExpressionSyntax> {
+    if let Some(val) = fold(lhs.as_ref(), rhs.as_ref(), |lhs, rhs| lhs - rhs) {
+        return number(val, lhs.get_span().merge(rhs.get_span()));
+    }
+
+    if is_zero(lhs.as_ref()) {
+        let span = rhs.get_span();
+        negate(rhs, span)
+    }
+    else if is_zero(rhs.as_ref()) {
+        lhs
+    }
+    else {
+        Box::new(AdditiveExpressionSyntax::new(AdditiveExpressionKind::Subtract, lhs, rhs))
+    }
+}
+
+/// Constant factors are moved to the front, so `cos(x) * 2` comes out as `2 * cos(x)`.
+pub fn multiply(lhs: Box<dyn ExpressionSyntax>, rhs: Box<dyn ExpressionSyntax>) -> Box<dyn ExpressionSyntax> {
+    let span = lhs.get_span().merge(rhs.get_span());
+    if let Some(val) = fold(lhs.as_ref(), rhs.as_ref(), |lhs, rhs| lhs * rhs) {
+        return number(val, span);
+    }
+
+    if is_zero(lhs.as_ref()) || is_zero(rhs.as_ref()) {
+        number(0.0, span)
+    }
+    else if is_one(lhs.as_ref()) {
+        rhs
+    }
+    else if is_one(rhs.as_ref()) {
+        lhs
+    }
+    else if lhs.get_number() == Some(-1.0) {
+        negate(rhs, span)
+    }
+    else if rhs.get_number() == Some(-1.0) {
+        negate(lhs, span)
+    }
+    else if rhs.get_number().is_some() {
+        Box::new(MultiplicativeExpressionSyntax::new(MultiplicativeExpressionKind::Multiply, rhs, lhs))
+    }
+    else {
+        Box::new(MultiplicativeExpressionSyntax::new(MultiplicativeExpressionKind::Multiply, lhs, rhs))
+    }
+}
+
+/// Numbers aren't divided ahead of time, since integer division truncates and the result depends on the evaluation
+/// mode.
+pub fn divide(lhs: Box<dyn ExpressionSyntax>, rhs: Box<dyn ExpressionSyntax>) -> Box<dyn ExpressionSyntax> {
+    if is_zero(lhs.as_ref()) && !is_zero(rhs.as_ref()) {
+        let span = lhs.get_span().merge(rhs.get_span());
+        number(0.0, span)
+    }
+    else if is_one(rhs.as_ref()) {
+        lhs
+    }
+    else {
+        Box::new(MultiplicativeExpressionSyntax::new(MultiplicativeExpressionKind::Divide, lhs, rhs))
+    }
+}
+
+pub fn power(base: Box<dyn ExpressionSyntax>, exponent: Box<dyn ExpressionSyntax>) -> Box<dyn ExpressionSyntax> {
+    if is_zero(exponent.as_ref()) {
+        let span = base.get_span().merge(exponent.get_span());
+        number(1.0, span)
+    }
+    else if is_one(exponent.as_ref()) {
+        base
+    }
+    else {
+        Box::new(PowerExpressionSyntax::new(base, exponent))
+    }
+}
+
+pub fn call(name: &str, argument_exprs: Vec<Box<dyn ExpressionSyntax>>, span: Span) -> Box<dyn ExpressionSyntax> {
+    Box::new(CallExpressionSyntax::new(name, argument_exprs, span))
+}
@@ -1,32 +1,42 @@
 use std::fmt::Display;
 use crate::calculator::{
-    interpreter::{MethodBuilder, Op},
-    tokenizer::Token
+    error::{runtime_error, Result},
+    interpreter::{Closure, ClosureCompiler, MethodBuilder, Op},
+    tokenizer::Span
 };
 use super::{
     expression_syntax::{ExpressionPrecedence, ExpressionSyntax},
-    primary_expression_syntax::PrimaryExpressionSyntax,
+    parser::Parser,
+    power_expression_syntax::PowerExpressionSyntax,
+    simplifier,
     syntax::Syntax
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum UnaryExpressionKind {
     Plus,
-    Minus
+    Minus,
+    Not
 }
 
+#[derive(Clone)]
 pub struct UnaryExpressionSyntax {
     kind: UnaryExpressionKind,
-    nested_expr: Box<dyn ExpressionSyntax>
+    nested_expr: Box<dyn ExpressionSyntax>,
+    span: Span
 }
 
 impl UnaryExpressionSyntax {
-    pub fn try_parse_expression(tokens: &Vec<Token>, pos: &mut usize) -> Option<Box<dyn ExpressionSyntax>> {
-        if *pos >= tokens.len() {
-            return None;
+    pub fn new(kind: UnaryExpressionKind, nested_expr: Box<dyn ExpressionSyntax>, span: Span) -> Self {
+        Self {
+            kind,
+            nested_expr,
+            span
         }
+    }
 
-        let token = &tokens[*pos];
+    pub fn parse_expression(parser: &mut Parser) -> Box<dyn ExpressionSyntax> {
+        let token = parser.peek();
         let mut kind_opt = None;
         if token.is_operator("+") {
             kind_opt = Some(UnaryExpressionKind::Plus);
@@ -34,24 +44,23 @@ impl UnaryExpressionSyntax {
         else if token.is_operator("-") {
             kind_opt = Some(UnaryExpressionKind::Minus);
         }
+        else if token.is_operator("!") {
+            kind_opt = Some(UnaryExpressionKind::Not);
+        }
 
         if let Some(kind) = kind_opt {
-            let mut npos = *pos + 1;
-            let nested_expr_opt = UnaryExpressionSyntax::try_parse_expression(tokens, &mut npos);
-            if let Some(nested_expr) = nested_expr_opt {
-                *pos = npos;
-
-                Some(Box::new(UnaryExpressionSyntax {
-                    kind,
-                    nested_expr
-                }))
-            }
-            else {
-                None
-            }
+            parser.advance();
+
+            let nested_expr = parser.parse_nested(UnaryExpressionSyntax::parse_expression);
+            let span = token.span.merge(nested_expr.get_span());
+            Box::new(UnaryExpressionSyntax {
+                kind,
+                nested_expr,
+                span
+            })
         }
         else {
-            PrimaryExpressionSyntax::try_parse_expression(tokens, pos)
+            PowerExpressionSyntax::parse_expression(parser)
         }
     }
 }
@@ -61,18 +70,67 @@ impl ExpressionSyntax for UnaryExpressionSyntax {
         ExpressionPrecedence::Unary
     }
 
-    fn emit_bytecode(&self, method_builder: &mut MethodBuilder) -> anyhow::Result<()> {
+    fn get_span(&self) -> Span {
+        self.span
+    }
+
+    fn emit_bytecode(&self, method_builder: &mut MethodBuilder) -> Result<()> {
         self.nested_expr.emit_bytecode(method_builder)?;
 
         match self.kind {
             UnaryExpressionKind::Minus => {
-                method_builder.ops.push(Op::Neg);
+                method_builder.emit_spanned(Op::Neg, self.span);
+            },
+            UnaryExpressionKind::Not => {
+                method_builder.emit_spanned(Op::Not, self.span);
             },
             UnaryExpressionKind::Plus => { }
         }
 
         Ok(())
     }
+
+    fn compile_closure(&self, compiler: &ClosureCompiler) -> Result<Closure> {
+        let operand = self.nested_expr.compile_closure(compiler)?;
+
+        match self.kind {
+            UnaryExpressionKind::Minus => Ok(compiler.unary(Op::Neg, operand, self.span)),
+            UnaryExpressionKind::Not => Ok(compiler.unary(Op::Not, operand, self.span)),
+            UnaryExpressionKind::Plus => Ok(operand)
+        }
+    }
+
+    fn get_label(&self) -> String {
+        match self.kind {
+            UnaryExpressionKind::Plus => "+".to_owned(),
+            UnaryExpressionKind::Minus => "-".to_owned(),
+            UnaryExpressionKind::Not => "!".to_owned()
+        }
+    }
+
+    fn get_children(&self) -> Vec<&dyn ExpressionSyntax> {
+        vec![self.nested_expr.as_ref()]
+    }
+
+    fn derivative(&self, var: &str) -> Result<Box<dyn ExpressionSyntax>> {
+        match self.kind {
+            UnaryExpressionKind::Plus => self.nested_expr.derivative(var),
+            UnaryExpressionKind::Minus => Ok(simplifier::negate(self.nested_expr.derivative(var)?, self.span)),
+            UnaryExpressionKind::Not => Err(runtime_error!("'!' can't be differentiated").with_span(self.span))
+        }
+    }
+
+    fn clone_expression(&self) -> Box<dyn ExpressionSyntax> {
+        Box::new(self.clone())
+    }
+
+    fn get_number(&self) -> Option<f64> {
+        match self.kind {
+            UnaryExpressionKind::Plus => self.nested_expr.get_number(),
+            UnaryExpressionKind::Minus => self.nested_expr.get_number().map(|val| -val),
+            UnaryExpressionKind::Not => None
+        }
+    }
 }
 
 impl Syntax for UnaryExpressionSyntax { }
@@ -85,6 +143,9 @@ impl Display for UnaryExpressionSyntax {
             },
             UnaryExpressionKind::Minus => {
                 write!(f, "-")?;
+            },
+            UnaryExpressionKind::Not => {
+                write!(f, "!")?;
             }
         };
 
@@ -103,35 +164,34 @@ impl Display for UnaryExpressionSyntax {
 #[cfg(test)]
 mod tests {
     use crate::calculator::{
-        syntax::try_parse_expression,
+        syntax::parse_expression,
         tokenizer::Tokenizer
     };
-    use anyhow::*;
     use super::*;
 
     #[test]
     fn emit_bytecode_should_emit_correct_bytecode() -> Result<()> {
         let test_cases: &[(&str, &[Op])] = &[
-            ("+25", &[Op::LdcF8(25.0)]),
-            ("-25", &[Op::LdcF8(25.0), Op::Neg]),
-            ("+-+-+-+3", &[Op::LdcF8(3.0), Op::Neg, Op::Neg, Op::Neg]),
-            ("(-(-5))", &[Op::LdcF8(5.0), Op::Neg, Op::Neg]),
-            ("(+(+7))", &[Op::LdcF8(7.0)]),
+            ("+25", &[Op::LdcI8(25)]),
+            ("-25", &[Op::LdcI8(25), Op::Neg]),
+            ("+-+-+-+3", &[Op::LdcI8(3), Op::Neg, Op::Neg, Op::Neg]),
+            ("(-(-5))", &[Op::LdcI8(5), Op::Neg, Op::Neg]),
+            ("(+(+7))", &[Op::LdcI8(7)]),
+            ("!0", &[Op::LdcI8(0), Op::Not]),
+            ("!-x", &[Op::Ldvar(0), Op::Neg, Op::Not]),
         ];
 
         let tokenizer = Tokenizer::new();
 
         for &(input, expected_ops) in test_cases {
-            let tokens = tokenizer.tokenize(input).collect();
+            let tokens = tokenizer.tokenize(input).collect::<Vec<_>>();
 
-            let mut pos = 0;
-            let expr = try_parse_expression(&tokens, &mut pos);
+            let result = parse_expression(&tokens);
 
-            assert!(expr.is_some());
-            assert_eq!(pos, tokens.len() - 1);
+            assert!(result.errors.is_empty());
 
             let mut method_builder = MethodBuilder::new();
-            expr.unwrap().emit_bytecode(&mut method_builder)?;
+            result.expr.emit_bytecode(&mut method_builder)?;
 
             assert_eq!(&method_builder.ops[..], expected_ops);
         }
@@ -0,0 +1,94 @@
+use std::fmt::Display;
+use crate::calculator::{
+    interpreter::Unit,
+    tokenizer::{Span, TokenKind}
+};
+use super::{parse_error::ParseError, parser::Parser};
+
+/// A unit written after a number or an `in`, like `km`, `mi/h` or `kg*m/s^2`. Only known unit names are accepted.
+#[derive(Clone)]
+pub struct UnitSyntax {
+    unit: Unit,
+    span: Span
+}
+
+impl UnitSyntax {
+    /// Whether the token `offset` ahead names a unit. A name followed by '(' is a call instead, as in `min(1, 2)`.
+    pub fn is_at_unit(parser: &Parser, offset: usize) -> bool {
+        let token = parser.peek_at(offset);
+        token.is_identifier() && !parser.peek_at(offset + 1).is_operator("(") && Unit::lookup(&token.source).is_some()
+    }
+
+    /// Parses a unit, reporting an error and returning `None` if there isn't one.
+    pub fn parse(parser: &mut Parser) -> Option<UnitSyntax> {
+        if !Self::is_at_unit(parser, 0) {
+            parser.report_unexpected("unit");
+            return None;
+        }
+
+        let start_span = parser.peek().span;
+        let mut unit = Self::parse_factor(parser);
+
+        //Stop in front of anything that isn't followed by another unit name, so `2 m * x` multiplies by the variable
+        loop {
+            let is_multiply = parser.is_operator("*");
+            if !(is_multiply || parser.is_operator("/")) || !Self::is_at_unit(parser, 1) {
+                break;
+            }
+            parser.advance();
+
+            let rhs_unit = Self::parse_factor(parser);
+            match if is_multiply { unit.mul(&rhs_unit) } else { unit.div(&rhs_unit) } {
+                Ok(combined_unit) => unit = combined_unit,
+                Err(err) => parser.report(ParseError::Invalid {
+                    message: err.to_string(),
+                    span: start_span.merge(parser.previous().span)
+                })
+            }
+        }
+
+        Some(UnitSyntax {
+            unit,
+            span: start_span.merge(parser.previous().span)
+        })
+    }
+
+    //Exponents that don't fit are left for the `^` operator, which reports them when the expression is evaluated
+    fn parse_factor(parser: &mut Parser) -> Unit {
+        let unit = Unit::lookup(&parser.advance().source).unwrap();
+        if !parser.is_operator("^") {
+            return unit;
+        }
+
+        let is_negative = parser.peek_next().is_operator("-");
+        let exponent_offset = if is_negative { 2 } else { 1 };
+        let exponent_unit = Some(parser.peek_at(exponent_offset))
+            .filter(|token| token.get_kind() == TokenKind::Integer)
+            .and_then(|token| token.source.parse::<i32>().ok())
+            .and_then(|exponent| unit.pow(if is_negative { -exponent } else { exponent }).ok());
+
+        match exponent_unit {
+            Some(exponent_unit) => {
+                for _ in 0..=exponent_offset {
+                    parser.advance();
+                }
+                exponent_unit
+            },
+            None => unit
+        }
+    }
+
+    pub fn get_unit(&self) -> &Unit {
+        &self.unit
+    }
+
+    pub fn get_span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Display for UnitSyntax {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.unit.name)
+    }
+}
@@ -0,0 +1,165 @@
+use std::fmt::Display;
+use crate::calculator::{
+    error::Result,
+    interpreter::{Closure, ClosureCompiler, MethodBuilder, Op, Value},
+    tokenizer::Span
+};
+use super::{
+    expression_syntax::{ExpressionPrecedence, ExpressionSyntax},
+    parser::Parser,
+    syntax::Syntax
+};
+
+/// A vector literal like `[1, 2, 3]`. Matrices are vectors of rows, like `[[1, 2], [3, 4]]`.
+#[derive(Clone)]
+pub struct VectorExpressionSyntax {
+    element_exprs: Vec<Box<dyn ExpressionSyntax>>,
+    span: Span
+}
+
+impl VectorExpressionSyntax {
+    pub fn parse_expression(parser: &mut Parser) -> Box<dyn ExpressionSyntax> {
+        debug_assert!(parser.is_operator("["));
+
+        let start_span = parser.advance().span;
+
+        let mut element_exprs = vec![];
+        if parser.try_consume_operator("]").is_none() {
+            loop {
+                element_exprs.push(parser.parse_expression());
+
+                if parser.try_consume_operator(",").is_some() {
+                    continue;
+                }
+                if parser.try_consume_operator("]").is_some() {
+                    break;
+                }
+
+                //Skip the rest of a malformed element and pick up again at the next one
+                parser.report_unexpected("',' or ']'");
+                parser.skip_until(&[",", "]"]);
+                if parser.try_consume_operator(",").is_some() {
+                    continue;
+                }
+                if parser.try_consume_operator("]").is_none() {
+                    parser.report_unexpected("']'");
+                }
+                break;
+            }
+        }
+
+        Box::new(VectorExpressionSyntax {
+            element_exprs,
+            span: start_span.merge(parser.previous().span)
+        })
+    }
+}
+
+impl ExpressionSyntax for VectorExpressionSyntax {
+    fn get_expression_precedence(&self) -> ExpressionPrecedence {
+        ExpressionPrecedence::Primary
+    }
+
+    fn get_span(&self) -> Span {
+        self.span
+    }
+
+    fn emit_bytecode(&self, method_builder: &mut MethodBuilder) -> Result<()> {
+        for element_expr in self.element_exprs.iter() {
+            element_expr.emit_bytecode(method_builder)?;
+        }
+
+        method_builder.ops.push(Op::Newvec(self.element_exprs.len()));
+
+        Ok(())
+    }
+
+    fn compile_closure(&self, compiler: &ClosureCompiler) -> Result<Closure> {
+        let elements = self.element_exprs.iter()
+            .map(|element_expr| element_expr.compile_closure(compiler))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Box::new(move |context| {
+            let vals = elements.iter()
+                .map(|element| element(context))
+                .collect::<Result<Vec<_>>>()?;
+            Ok(Value::Vector(vals))
+        }))
+    }
+
+    fn get_label(&self) -> String {
+        "vector".to_owned()
+    }
+
+    fn get_children(&self) -> Vec<&dyn ExpressionSyntax> {
+        self.element_exprs.iter().map(|element_expr| element_expr.as_ref()).collect()
+    }
+
+    //Each element is differentiated on its own, so the derivative of a vector is a vector
+    fn derivative(&self, var: &str) -> Result<Box<dyn ExpressionSyntax>> {
+        let element_exprs = self.element_exprs.iter()
+            .map(|element_expr| element_expr.derivative(var))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Box::new(VectorExpressionSyntax {
+            element_exprs,
+            span: self.span
+        }))
+    }
+
+    fn clone_expression(&self) -> Box<dyn ExpressionSyntax> {
+        Box::new(self.clone())
+    }
+}
+
+impl Syntax for VectorExpressionSyntax { }
+
+impl Display for VectorExpressionSyntax {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[")?;
+        for (idx, element_expr) in self.element_exprs.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", element_expr)?;
+        }
+        write!(f, "]")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calculator::{
+        syntax::parse_expression,
+        tokenizer::Tokenizer
+    };
+    use super::*;
+
+    #[test]
+    fn emit_bytecode_should_emit_correct_bytecode() -> Result<()> {
+        let test_cases: &[(&str, &[Op])] = &[
+            ("[]", &[Op::Newvec(0)]),
+            ("[1, x]", &[Op::LdcI8(1), Op::Ldvar(0), Op::Newvec(2)]),
+            ("[[1], [2, 3]]", &[Op::LdcI8(1), Op::Newvec(1), Op::LdcI8(2), Op::LdcI8(3), Op::Newvec(2), Op::Newvec(2)]),
+            ("[1, 2][0]", &[Op::LdcI8(1), Op::LdcI8(2), Op::Newvec(2), Op::LdcI8(0), Op::Ldelem]),
+            ("-m[0][1] ^ 2", &[Op::Ldvar(0), Op::LdcI8(0), Op::Ldelem, Op::LdcI8(1), Op::Ldelem, Op::LdcI8(2), Op::Pow, Op::Neg]),
+        ];
+
+        let tokenizer = Tokenizer::new();
+
+        for &(input, expected_ops) in test_cases {
+            let tokens = tokenizer.tokenize(input).collect::<Vec<_>>();
+
+            let result = parse_expression(&tokens);
+
+            assert!(result.errors.is_empty());
+
+            let mut method_builder = MethodBuilder::new();
+            result.expr.emit_bytecode(&mut method_builder)?;
+
+            assert_eq!(&method_builder.ops[..], expected_ops, "for input {:?}", input);
+        }
+
+        Ok(())
+    }
+}
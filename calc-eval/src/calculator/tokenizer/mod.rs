@@ -2,4 +2,4 @@ mod tokenizer;
 mod token;
 
 pub use tokenizer::Tokenizer;
-pub use token::Token;
+pub use token::{Span, Token, TokenKind};
@@ -1,9 +1,21 @@
 use std::fmt::Display;
+use crate::calculator::error::{runtime_error, CalcError};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum TokenKind {
     Integer,
+    /// An integer written in hexadecimal, like `0xFF`.
+    HexInteger,
+    /// An integer written in binary, like `0b1010`.
+    BinaryInteger,
+    /// An integer written in octal, like `0o755`.
+    OctalInteger,
     Float,
+    /// A number followed by `i`, like `3i` or `2.5i`.
+    Imaginary,
+    Boolean,
+    String,
     Operator,
     Identifier,
     Error,
@@ -12,19 +24,63 @@ pub enum TokenKind {
 
 impl TokenKind {
     pub fn is_literal(self) -> bool {
+        self.is_numeric() || matches!(self, Self::Imaginary | Self::Boolean | Self::String)
+    }
+
+    pub fn is_integer(self) -> bool {
+        matches!(self, Self::Integer | Self::HexInteger | Self::BinaryInteger | Self::OctalInteger)
+    }
+
+    /// Integers in any base and floats, but not imaginary numbers.
+    pub fn is_numeric(self) -> bool {
+        self.is_integer() || self == Self::Float
+    }
+
+    /// What integer literals of this kind are called in messages, like "Hex".
+    pub fn get_literal_name(self) -> &'static str {
+        match self {
+            Self::HexInteger => "Hex",
+            Self::BinaryInteger => "Binary",
+            Self::OctalInteger => "Octal",
+            _ => "Integer"
+        }
+    }
+
+    /// The base integer literals of this kind are written in, which is 10 for anything that isn't an integer.
+    pub fn get_radix(self) -> u32 {
         match self {
-            Self::Integer |
-            Self::Float => true,
-            _ => false
+            Self::HexInteger => 16,
+            Self::BinaryInteger => 2,
+            Self::OctalInteger => 8,
+            _ => 10
         }
     }
 }
 
+/// A range of byte offsets into the source the tokens were collected from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// Returns the smallest span covering both `self` and `other`.
+    pub fn merge(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[allow(unused)]
 pub struct Token {
     pub source: String,
-    pub token_kind: TokenKind
+    pub token_kind: TokenKind,
+    pub span: Span
 }
 
 impl Token {
@@ -36,10 +92,23 @@ impl Token {
         self.get_kind().is_literal()
     }
 
+    pub fn is_identifier(&self) -> bool {
+        self.get_kind() == TokenKind::Identifier
+    }
+
     pub fn is_operator(&self, op: &str) -> bool {
         self.get_kind() == TokenKind::Operator && op == self.source
     }
 
+    //The digits with their radix prefix and underscores taken out, ready to be parsed
+    fn get_digits(&self) -> String {
+        let digits = match self.get_kind().get_radix() {
+            10 => &self.source[..],
+            _ => &self.source[2..]
+        };
+        digits.replace('_', "")
+    }
+
     pub fn repr(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!("{}", self.source))
     }
@@ -52,24 +121,70 @@ impl Display for Token {
 }
 
 impl TryFrom<&Token> for f64 {
-    type Error = anyhow::Error;
+    type Error = CalcError;
 
     fn try_from(value: &Token) -> Result<Self, Self::Error> {
         match value.get_kind() {
+            //Integers in other bases are bit patterns, so they're never rounded to the nearest float
+            TokenKind::HexInteger |
+            TokenKind::BinaryInteger |
+            TokenKind::OctalInteger => Ok(i64::try_from(value)? as f64),
             TokenKind::Integer |
-            TokenKind::Float => Ok(value.source.parse::<f64>()?),
-            _ => Err(anyhow::anyhow!("This token can't be interpreted as a f64"))
+            TokenKind::Float => Ok(value.get_digits().parse::<f64>().map_err(|err| runtime_error!("{}", err))?),
+            _ => Err(runtime_error!("This token can't be interpreted as a f64"))
         }
     }
 }
 
 impl TryFrom<&Token> for i64 {
-    type Error = anyhow::Error;
+    type Error = CalcError;
+
+    fn try_from(value: &Token) -> Result<Self, Self::Error> {
+        match value.get_kind() {
+            //The tokenizer only collects valid digits, so the only thing that can go wrong is the number being too large
+            kind if kind.is_integer() => i64::from_str_radix(&value.get_digits(), kind.get_radix())
+                .map_err(|_| runtime_error!("{} literal '{}' does not fit in a 64-bit integer", kind.get_literal_name(), value.source)),
+            _ => Err(runtime_error!("This token can't be interpreted as a i64"))
+        }
+    }
+}
+
+impl TryFrom<&Token> for bool {
+    type Error = CalcError;
 
     fn try_from(value: &Token) -> Result<Self, Self::Error> {
         match value.get_kind() {
-            TokenKind::Integer => Ok(value.source.parse::<i64>()?),
-            _ => Err(anyhow::anyhow!("This token can't be interpreted as a i64"))
+            TokenKind::Boolean => Ok(value.source.parse::<bool>().map_err(|err| runtime_error!("{}", err))?),
+            _ => Err(runtime_error!("This token can't be interpreted as a bool"))
         }
     }
 }
+
+impl TryFrom<&Token> for String {
+    type Error = CalcError;
+
+    fn try_from(value: &Token) -> Result<Self, Self::Error> {
+        if value.get_kind() != TokenKind::String {
+            return Err(runtime_error!("This token can't be interpreted as a string"));
+        }
+
+        let mut result = String::new();
+        let mut chars = value.source[1..value.source.len() - 1].chars();
+        while let Some(chr) = chars.next() {
+            if chr != '\\' {
+                result.push(chr);
+                continue;
+            }
+
+            match chars.next() {
+                Some('n') => result.push('\n'),
+                Some('t') => result.push('\t'),
+                Some(escaped @ ('\\' | '"')) => result.push(escaped),
+                Some(other) => return Err(runtime_error!("Unknown escape sequence: \\{}", other)),
+                None => return Err(runtime_error!("Unterminated escape sequence"))
+            }
+        }
+
+        Ok(result)
+    }
+}
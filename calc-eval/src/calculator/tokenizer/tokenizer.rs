@@ -1,5 +1,5 @@
 use unicode_categories::UnicodeCategories;
-use super::token::{Token, TokenKind};
+use super::token::{Span, Token, TokenKind};
 
 macro_rules! count {
     ( ) => {
@@ -27,13 +27,31 @@ char_array_const!
         '*',
         '/',
         '%',
+        '^',
         '(',
         ')',
+        '[',
+        ']',
         '.',
-        ','
+        ',',
+        '=',
+        '<',
+        '>',
+        '!',
+        '?',
+        ':'
     ];
 }
 
+const MULTI_CHAR_OPERATORS: [&str; 6] = [
+    "==",
+    "!=",
+    "<=",
+    ">=",
+    "&&",
+    "||"
+];
+
 pub struct Tokenize<'a> {
     full_source: &'a str,
     char_indices: Vec<(usize, char)>,
@@ -53,60 +71,148 @@ impl<'a> Tokenize<'a> {
         }
     }
 
+    fn char_at(&self, pos: usize) -> Option<char> {
+        self.char_indices.get(pos).map(|&(_, chr)| chr)
+    }
+
+    fn byte_offset(&self, pos: usize) -> usize {
+        self.char_indices.get(pos).map_or(self.full_source.len(), |&(idx, _)| idx)
+    }
+
+    /// Skips the digits starting at `pos`, which may be separated by single underscores like `1_000`. Returns where
+    /// they end, or `None` if there are no digits or an underscore isn't followed by one.
+    fn skip_digits(&self, mut pos: usize, radix: u32) -> Option<usize> {
+        let is_digit_at = |pos: usize| self.char_at(pos).is_some_and(|chr| chr.is_digit(radix));
+        if !is_digit_at(pos) {
+            return None;
+        }
+
+        loop {
+            match self.char_at(pos) {
+                Some(chr) if chr.is_digit(radix) => pos += 1,
+                Some('_') if is_digit_at(pos + 1) => pos += 2,
+                Some('_') => return None,
+                _ => return Some(pos)
+            }
+        }
+    }
+
     fn try_collect_numeric(&mut self) -> Option<Token> {
         let (start_idx, chr) = self.char_indices[self.pos];
-        if chr < '0' || chr > '9' {
+        let starts_with_period = chr == '.' && self.char_at(self.pos + 1).is_some_and(|chr| chr.is_ascii_digit());
+        if !chr.is_ascii_digit() && !starts_with_period {
             return None;
         }
 
-        let mut end_idx = start_idx + chr.len_utf8();
-        let mut mpos = self.pos + 1;
-
-        let mut collected_period = false;
-        let mut has_number_after_period = false;
-        while mpos < self.char_indices.len() {
-            let (next_idx, next_chr) = self.char_indices[mpos];
-            debug_assert!(next_idx == end_idx);
+        let radix_kind = match (chr, self.char_at(self.pos + 1)) {
+            ('0', Some('x' | 'X')) => Some(TokenKind::HexInteger),
+            ('0', Some('b' | 'B')) => Some(TokenKind::BinaryInteger),
+            ('0', Some('o' | 'O')) => Some(TokenKind::OctalInteger),
+            _ => None
+        };
 
-            match next_chr {
-                '.' => {
-                    if collected_period {
-                        return None;
-                    }
-                    collected_period = true;
-                    end_idx += next_chr.len_utf8();
-                    mpos += 1;
-                },
-                '0'..='9' => {
-                    if collected_period {
-                        has_number_after_period = true;
-                    }
-                    end_idx += next_chr.len_utf8();
-                    mpos += 1;
-                },
-                _ => break
+        let mut mpos;
+        let mut token_kind;
+        if let Some(kind) = radix_kind {
+            //A letter or digit straight after the digits, as in 0b102 or 0xFG, means the literal is malformed
+            mpos = self.skip_digits(self.pos + 2, kind.get_radix())?;
+            if self.char_at(mpos).is_some_and(|chr| chr == '_' || chr.is_alphanumeric()) {
+                return None;
             }
+            token_kind = kind;
         }
+        else {
+            //The digits before the period are optional, as in .5, but the ones after it aren't
+            mpos = match starts_with_period {
+                true => self.pos,
+                false => self.skip_digits(self.pos, 10)?
+            };
+            token_kind = TokenKind::Integer;
+            if self.char_at(mpos) == Some('.') {
+                mpos = self.skip_digits(mpos + 1, 10)?;
+                token_kind = TokenKind::Float;
+            }
+            if self.char_at(mpos) == Some('.') {
+                return None;
+            }
 
-        if collected_period && !has_number_after_period {
-            return None;
+            //An e that isn't followed by an exponent is left alone, so it can start a unit or a name
+            if matches!(self.char_at(mpos), Some('e' | 'E')) {
+                let sign_len = if matches!(self.char_at(mpos + 1), Some('+' | '-')) { 1 } else { 0 };
+                if let Some(exponent_end) = self.skip_digits(mpos + 1 + sign_len, 10) {
+                    mpos = exponent_end;
+                    token_kind = TokenKind::Float;
+                }
+            }
+
+            //A trailing `i` makes the number imaginary, as long as it isn't the start of a longer name like `in`
+            let is_imaginary = self.char_at(mpos) == Some('i')
+                && !self.char_at(mpos + 1).is_some_and(|chr| chr == '_' || chr.is_alphanumeric());
+            if is_imaginary {
+                mpos += 1;
+                token_kind = TokenKind::Imaginary;
+            }
         }
 
+        let end_idx = self.byte_offset(mpos);
         self.pos = mpos;
         Some(Token {
             source: self.full_source[start_idx..end_idx].to_owned(),
-            token_kind: if collected_period { TokenKind::Float } else { TokenKind::Integer }
+            token_kind,
+            span: Span::new(start_idx, end_idx)
         })
     }
 
+    fn try_collect_string(&mut self) -> Option<Token> {
+        let (start_idx, chr) = self.char_indices[self.pos];
+        if chr != '"' {
+            return None;
+        }
+
+        let mut mpos = self.pos + 1;
+        let mut escaped = false;
+        while mpos < self.char_indices.len() {
+            let (next_idx, next_chr) = self.char_indices[mpos];
+            mpos += 1;
+
+            if escaped {
+                escaped = false;
+            }
+            else if next_chr == '\\' {
+                escaped = true;
+            }
+            else if next_chr == '"' {
+                self.pos = mpos;
+                return Some(Token {
+                    source: self.full_source[start_idx..next_idx + 1].to_owned(),
+                    token_kind: TokenKind::String,
+                    span: Span::new(start_idx, next_idx + 1)
+                });
+            }
+        }
+
+        //Unterminated strings are left for collect_error
+        None
+    }
+
     fn try_collect_operator(&mut self) -> Option<Token> {
         let (start_idx, this_chr) = self.char_indices[self.pos];
-        if SINGLE_CHAR_OPERATORS.contains(&this_chr) {
+        if let Some(op) = MULTI_CHAR_OPERATORS.iter().find(|op| self.full_source[start_idx..].starts_with(*op)) {
+            let end_idx = start_idx + op.len();
+            self.pos += op.chars().count();
+            Some(Token {
+                source: self.full_source[start_idx..end_idx].to_owned(),
+                token_kind: TokenKind::Operator,
+                span: Span::new(start_idx, end_idx)
+            })
+        }
+        else if SINGLE_CHAR_OPERATORS.contains(&this_chr) {
             let end_idx = start_idx + this_chr.len_utf8();
             self.pos += 1;
             Some(Token {
                 source: self.full_source[start_idx..end_idx].to_owned(),
-                token_kind: TokenKind::Operator
+                token_kind: TokenKind::Operator,
+                span: Span::new(start_idx, end_idx)
             })
         }
         else {
@@ -136,9 +242,11 @@ impl<'a> Tokenize<'a> {
             }
         }
 
+        let source = &self.full_source[start_idx..end_idx];
         Some(Token {
-            source: self.full_source[start_idx..end_idx].to_owned(),
-            token_kind: TokenKind::Identifier
+            source: source.to_owned(),
+            token_kind: if source == "true" || source == "false" { TokenKind::Boolean } else { TokenKind::Identifier },
+            span: Span::new(start_idx, end_idx)
         })
     }
 
@@ -161,7 +269,8 @@ impl<'a> Tokenize<'a> {
 
         Token {
             source: self.full_source[start_idx..end_idx].to_owned(),
-            token_kind: TokenKind::Error
+            token_kind: TokenKind::Error,
+            span: Span::new(start_idx, end_idx)
         }
     }
 
@@ -184,11 +293,13 @@ impl<'a> Tokenize<'a> {
             self.sent_eof = true;
             Some(Token {
                 source: "".to_owned(),
-                token_kind: TokenKind::EOF
+                token_kind: TokenKind::EOF,
+                span: Span::new(self.full_source.len(), self.full_source.len())
             })
         }
         else {
             self.try_collect_numeric()
+                .or_else(|| self.try_collect_string())
                 .or_else(|| self.try_collect_operator())
                 .or_else(|| self.try_collect_identifier())
                 .or_else(|| Some(self.collect_error()))
@@ -217,19 +328,25 @@ impl Tokenizer {
     }
 }
 
+impl Default for Tokenizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     macro_rules! eof {
         ( ) => {
-            Token { token_kind: TokenKind::EOF, source: "".to_owned() }
+            Token { token_kind: TokenKind::EOF, source: "".to_owned(), span: Span::default() }
         };
     }
 
     macro_rules! tok {
         ( $kind:ident , $source:expr ) => {
-            Token { token_kind: TokenKind::$kind, source: ($source).to_owned() }
+            Token { token_kind: TokenKind::$kind, source: ($source).to_owned(), span: Span::default() }
         };
     }
 
@@ -242,7 +359,8 @@ mod tests {
             ("123", &[tok!(Integer, "123"), eof!()]),
             ("1.2", &[tok!(Float, "1.2"), eof!()]),
             ("1.", &[tok!(Error, "1."), eof!()]),
-            (".2", &[tok!(Operator, "."), tok!(Integer, "2"), eof!()]),
+            (".2", &[tok!(Float, ".2"), eof!()]),
+            ("1.2.3", &[tok!(Error, "1.2.3"), eof!()]),
             ("0.0", &[tok!(Float, "0.0"), eof!()]),
             ("0..0", &[tok!(Error, "0..0"), eof!()]),
             ("123.456", &[tok!(Float, "123.456"), eof!()]),
@@ -251,18 +369,70 @@ mod tests {
             ("612%(4/2)", &[tok!(Integer, "612"), tok!(Operator, "%"), tok!(Operator, "("), tok!(Integer, "4"), tok!(Operator, "/"), tok!(Integer, "2"), tok!(Operator, ")"), eof!()]),
             ("fish", &[tok!(Identifier, "fish"), eof!()]),
             ("fish and chips", &[tok!(Identifier, "fish"), tok!(Identifier, "and"), tok!(Identifier, "chips"), eof!()]),
+            ("2^-3", &[tok!(Integer, "2"), tok!(Operator, "^"), tok!(Operator, "-"), tok!(Integer, "3"), eof!()]),
+            ("a<=b", &[tok!(Identifier, "a"), tok!(Operator, "<="), tok!(Identifier, "b"), eof!()]),
+            ("a < = b", &[tok!(Identifier, "a"), tok!(Operator, "<"), tok!(Operator, "="), tok!(Identifier, "b"), eof!()]),
+            ("!x!=y==z", &[tok!(Operator, "!"), tok!(Identifier, "x"), tok!(Operator, "!="), tok!(Identifier, "y"), tok!(Operator, "=="), tok!(Identifier, "z"), eof!()]),
+            ("a&&b||c", &[tok!(Identifier, "a"), tok!(Operator, "&&"), tok!(Identifier, "b"), tok!(Operator, "||"), tok!(Identifier, "c"), eof!()]),
+            ("x>1?2:3", &[tok!(Identifier, "x"), tok!(Operator, ">"), tok!(Integer, "1"), tok!(Operator, "?"), tok!(Integer, "2"), tok!(Operator, ":"), tok!(Integer, "3"), eof!()]),
+            ("a & b", &[tok!(Identifier, "a"), tok!(Error, "&"), tok!(Identifier, "b"), eof!()]),
+            ("true false truth", &[tok!(Boolean, "true"), tok!(Boolean, "false"), tok!(Identifier, "truth"), eof!()]),
+            ("\"\"", &[tok!(String, "\"\""), eof!()]),
+            ("\"fish\" + \"chips\"", &[tok!(String, "\"fish\""), tok!(Operator, "+"), tok!(String, "\"chips\""), eof!()]),
+            ("\"say \\\"hi\\\"\"", &[tok!(String, "\"say \\\"hi\\\"\""), eof!()]),
+            ("\"open", &[tok!(Error, "\"open"), eof!()]),
+            ("x = 3", &[tok!(Identifier, "x"), tok!(Operator, "="), tok!(Integer, "3"), eof!()]),
+            ("1e-9 2.5E+3 7e2", &[tok!(Float, "1e-9"), tok!(Float, "2.5E+3"), tok!(Float, "7e2"), eof!()]),
+            ("2em 3e", &[tok!(Integer, "2"), tok!(Identifier, "em"), tok!(Integer, "3"), tok!(Identifier, "e"), eof!()]),
+            ("1_000_000 0.000_1", &[tok!(Integer, "1_000_000"), tok!(Float, "0.000_1"), eof!()]),
+            ("1__0 1_", &[tok!(Error, "1__0"), tok!(Error, "1_"), eof!()]),
+            ("0xFF 0x_ff", &[tok!(HexInteger, "0xFF"), tok!(Error, "0x_ff"), eof!()]),
+            ("0b1010_0101 0B1", &[tok!(BinaryInteger, "0b1010_0101"), tok!(BinaryInteger, "0B1"), eof!()]),
+            ("0o755", &[tok!(OctalInteger, "0o755"), eof!()]),
+            ("0x 0b102 0xFG", &[tok!(Error, "0x"), tok!(Error, "0b102"), tok!(Error, "0xFG"), eof!()]),
+            ("0xA+0b1", &[tok!(HexInteger, "0xA"), tok!(Operator, "+"), tok!(BinaryInteger, "0b1"), eof!()]),
+            ("1.5e3i", &[tok!(Imaginary, "1.5e3i"), eof!()]),
+            ("[[1,2],[3]][0]", &[tok!(Operator, "["), tok!(Operator, "["), tok!(Integer, "1"), tok!(Operator, ","), tok!(Integer, "2"), tok!(Operator, "]"), tok!(Operator, ","), tok!(Operator, "["), tok!(Integer, "3"), tok!(Operator, "]"), tok!(Operator, "]"), tok!(Operator, "["), tok!(Integer, "0"), tok!(Operator, "]"), eof!()]),
+            ("3i", &[tok!(Imaginary, "3i"), eof!()]),
+            ("(3+4.5i)*2i", &[tok!(Operator, "("), tok!(Integer, "3"), tok!(Operator, "+"), tok!(Imaginary, "4.5i"), tok!(Operator, ")"), tok!(Operator, "*"), tok!(Imaginary, "2i"), eof!()]),
+            ("3 i", &[tok!(Integer, "3"), tok!(Identifier, "i"), eof!()]),
+            ("3in", &[tok!(Integer, "3"), tok!(Identifier, "in"), eof!()]),
+            ("3i2", &[tok!(Integer, "3"), tok!(Identifier, "i2"), eof!()]),
             ("min(3.5, 2.7)", &[tok!(Identifier, "min"), tok!(Operator, "("), tok!(Float, "3.5"), tok!(Operator, ","), tok!(Float, "2.7"), tok!(Operator, ")"), eof!()]),
         ];
 
         let tokenizer = Tokenizer::new();
 
+        //Spans are covered separately, since the padding below shifts them
+        let without_span = |token: Token| Token { span: Span::default(), ..token };
+
         for &(source, expected_tokens) in test_cases {
-            let actual_tokens = tokenizer.tokenize(source).collect::<Vec<Token>>();
+            let actual_tokens = tokenizer.tokenize(source).map(without_span).collect::<Vec<Token>>();
             assert_eq!(&actual_tokens[..], expected_tokens);
 
             let source_with_padding = format!("  {}  ", source);
-            let actual_tokens_with_padding = tokenizer.tokenize(source_with_padding.as_str()).collect::<Vec<Token>>();
+            let actual_tokens_with_padding = tokenizer.tokenize(source_with_padding.as_str()).map(without_span).collect::<Vec<Token>>();
             assert_eq!(&actual_tokens_with_padding[..], expected_tokens);
         }
     }
+
+    #[test]
+    fn tokenizer_should_record_byte_spans() {
+        let test_cases: &[(&str, &[(usize, usize)])] = &[
+            ("", &[(0, 0)]),
+            ("1 + 23", &[(0, 1), (2, 3), (4, 6), (6, 6)]),
+            ("  x>=1.5 ", &[(2, 3), (3, 5), (5, 8), (9, 9)]),
+            ("\"é\" + ü", &[(0, 4), (5, 6), (7, 9), (9, 9)]),
+            ("1 $$ 2", &[(0, 1), (2, 4), (5, 6), (6, 6)]),
+        ];
+
+        let tokenizer = Tokenizer::new();
+
+        for &(source, expected_spans) in test_cases {
+            let actual_spans = tokenizer.tokenize(source)
+                .map(|token| (token.span.start, token.span.end))
+                .collect::<Vec<(usize, usize)>>();
+            assert_eq!(&actual_spans[..], expected_spans);
+        }
+    }
 }
@@ -0,0 +1,24 @@
+#![allow(clippy::module_inception)]
+
+//! An expression calculator: a tokenizer and parser that compile input to bytecode, and an interpreter that runs it.
+//!
+//! Most uses only need [`Calculator`], which keeps variables and functions between calls to [`Calculator::eval`].
+//! The stages it is built from are exposed through the [`tokenizer`], [`syntax`] and [`interpreter`] modules.
+
+mod calculator;
+
+pub use calculator::{
+    interpreter,
+    syntax,
+    tokenizer,
+    Backend,
+    CalcError,
+    Calculator,
+    CompiledExpression,
+    Diagnostic,
+    Diagnostics,
+    Result
+};
+pub use calculator::interpreter::{disassemble, verify, EvaluationMode, MethodBuilder, Op, Radix, Value};
+pub use calculator::syntax::format_syntax_tree;
+pub use calculator::tokenizer::Tokenizer;
@@ -1,42 +1,85 @@
-#![feature(lazy_cell)]
+mod batch;
+mod repl;
 
-mod calculator;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, IsTerminal},
+    path::PathBuf,
+    process::ExitCode
+};
+use clap::Parser;
+use calc_eval::{Calculator, EvaluationMode, Radix};
+use batch::OutputFormat;
 
-use anyhow::Result;
-use std::io::Write;
-use core::cell::LazyCell;
-use calculator::Calculator;
+/// Evaluates expressions interactively, or in a batch from the command line, a file or piped standard input.
+///
+/// Exits with 1 if any expression in a batch fails to evaluate, and 2 if the input can't be read.
+#[derive(Parser)]
+#[command(version, about)]
+struct Args {
+    /// Evaluates EXPR instead of reading input. Can be given more than once.
+    #[arg(short = 'e', long = "eval", value_name = "EXPR", conflicts_with = "file")]
+    expressions: Vec<String>,
 
-fn read_user_input() -> Result<String> {
-    let mut buffer = String::new();
-    std::io::stdin().read_line(&mut buffer)?;
-    Ok(String::from(buffer.trim()))
-}
+    /// Evaluates each line of FILE. Blank lines and lines starting with '#' are skipped.
+    file: Option<PathBuf>,
 
-fn main() -> Result<()> {
-    let mut stdout = std::io::stdout();
+    /// Writes one JSON object per evaluated line instead of plain text.
+    #[arg(long)]
+    json: bool,
 
-    let calc = LazyCell::new(|| {
-        Calculator::new()
-    });
+    /// The evaluation mode to start in: float or rational.
+    #[arg(long, default_value_t = EvaluationMode::Float)]
+    mode: EvaluationMode,
 
-    println!("Enter expressions to evaluate, or \".exit\" to exit.");
+    /// The base to show whole numbers in: decimal, hex or binary.
+    #[arg(long, default_value_t = Radix::Decimal)]
+    radix: Radix
+}
 
-    loop {
-        print!(" > ");
-        stdout.flush()?;
-        let line = read_user_input()?;
+fn main() -> ExitCode {
+    let args = Args::parse();
 
-        if line == ".exit" {
-            break;
-        }
+    let format = if args.json { OutputFormat::Json } else { OutputFormat::Text };
+    let mut calc = Calculator::new();
+    calc.set_mode(args.mode);
+    calc.set_radix(args.radix);
 
-        let result = calc.eval(line);
-        match result {
-            Ok(n) => println!("{}", n),
-            Err(err) => println!("There was an error evaluating your input. {}", err)
-        }
+    let mut stdout = std::io::stdout().lock();
+    let mut stderr = std::io::stderr().lock();
+
+    let result = if !args.expressions.is_empty() {
+        let lines = args.expressions.into_iter().map(Ok);
+        batch::run(&mut calc, "<args>", lines, format, &mut stdout, &mut stderr)
     }
+    else if let Some(path) = args.file {
+        File::open(&path).and_then(|file| {
+            let lines = BufReader::new(file).lines();
+            batch::run(&mut calc, &path.display().to_string(), lines, format, &mut stdout, &mut stderr)
+        })
+        .map_err(|err| std::io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))
+    }
+    else if !std::io::stdin().is_terminal() {
+        let lines = std::io::stdin().lock().lines();
+        batch::run(&mut calc, "<stdin>", lines, format, &mut stdout, &mut stderr)
+    }
+    else {
+        drop((stdout, stderr));
+        return match repl::run(args.mode, args.radix) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("{}", err);
+                ExitCode::from(2)
+            }
+        };
+    };
 
-    Ok(())
+    match result {
+        Ok(0) => ExitCode::SUCCESS,
+        Ok(_) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::from(2)
+        }
+    }
 }
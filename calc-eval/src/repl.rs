@@ -0,0 +1,205 @@
+use std::path::PathBuf;
+use anyhow::Result;
+use rustyline::{
+    error::ReadlineError,
+    history::FileHistory,
+    validate::{ValidationContext, ValidationResult, Validator},
+    Completer, Editor, Helper, Highlighter, Hinter
+};
+use calc_eval::{disassemble, format_syntax_tree, CalcError, Calculator, EvaluationMode, Radix, Tokenizer};
+
+const HELP: &str = "\
+Enter an expression to evaluate it. Input continues on the next line while parentheses or brackets are unclosed.
+
+Commands:
+  .help            Show this message
+  .vars            List variables and functions
+  .clear           Forget all variables and functions
+  .mode [MODE]     Show or set the evaluation mode: float or rational
+  .radix [RADIX]   Show or set the base whole numbers are shown in: decimal, hex or binary
+  .ast EXPR        Show the tree EXPR parses to, with the precedence of each node
+  .bytecode EXPR   Show the bytecode EXPR compiles to, with the stack depth after each op
+  .derive VAR EXPR Show the derivative of EXPR with respect to VAR
+  .exit            Exit";
+
+#[derive(Helper, Completer, Hinter, Highlighter)]
+struct ReplHelper {
+    tokenizer: Tokenizer
+}
+
+impl ReplHelper {
+    fn is_incomplete(&self, input: &str) -> bool {
+        if input.trim_start().starts_with('.') {
+            return false;
+        }
+
+        let depth = self.tokenizer.tokenize(input).fold(0isize, |depth, token| {
+            if token.is_operator("(") || token.is_operator("[") {
+                depth + 1
+            }
+            else if token.is_operator(")") || token.is_operator("]") {
+                depth - 1
+            }
+            else {
+                depth
+            }
+        });
+        depth > 0
+    }
+}
+
+impl Validator for ReplHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        if self.is_incomplete(ctx.input()) {
+            Ok(ValidationResult::Incomplete)
+        }
+        else {
+            Ok(ValidationResult::Valid(None))
+        }
+    }
+}
+
+fn get_history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".calc_eval_history"))
+}
+
+pub fn run(mode: EvaluationMode, radix: Radix) -> Result<()> {
+    let mut calc = Calculator::new();
+    calc.set_mode(mode);
+    calc.set_radix(radix);
+
+    let mut editor = Editor::<ReplHelper, FileHistory>::new()?;
+    editor.set_helper(Some(ReplHelper {
+        tokenizer: Tokenizer::new()
+    }));
+
+    let history_path = get_history_path();
+    if let Some(path) = &history_path {
+        //There is no history to load the first time the REPL runs
+        let _ = editor.load_history(path);
+    }
+
+    println!("Enter expressions to evaluate, \".help\" for a list of commands, or \".exit\" to exit.");
+
+    loop {
+        let line = match editor.readline(" > ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(err) => return Err(err.into())
+        };
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        editor.add_history_entry(line)?;
+
+        if let Some(command) = line.strip_prefix('.') {
+            let (name, args) = command.split_once(char::is_whitespace).unwrap_or((command, ""));
+            if name == "exit" {
+                break;
+            }
+            run_command(&mut calc, name, args.trim());
+            continue;
+        }
+
+        match calc.eval(line) {
+            Ok(val) => println!("{}", calc.format(&val)),
+            Err(err) => print_error(&err, line)
+        }
+    }
+
+    if let Some(path) = &history_path {
+        if let Err(err) = editor.save_history(path) {
+            eprintln!("Couldn't save history to {}: {}", path.display(), err);
+        }
+    }
+
+    Ok(())
+}
+
+fn run_command(calc: &mut Calculator, name: &str, args: &str) {
+    match name {
+        "help" => println!("{}", HELP),
+        "vars" => {
+            let environment = calc.get_environment();
+            for (name, val) in environment.get_variables() {
+                println!("{} = {}", name, calc.format(val));
+            }
+            for function in environment.get_functions() {
+                println!("{}", function);
+            }
+        },
+        "clear" => {
+            calc.clear();
+            println!("Cleared all variables and functions.");
+        },
+        "mode" if args.is_empty() => println!("Evaluation mode is {}.", calc.get_mode()),
+        "mode" => match args.parse() {
+            Ok(mode) => {
+                calc.set_mode(mode);
+                println!("Evaluation mode set to {}.", mode);
+            },
+            Err(err) => println!("{}", err)
+        },
+        "radix" if args.is_empty() => println!("Output radix is {}.", calc.get_radix()),
+        "radix" => match args.parse() {
+            Ok(radix) => {
+                calc.set_radix(radix);
+                println!("Output radix set to {}.", radix);
+            },
+            Err(err) => println!("{}", err)
+        },
+        "ast" | "bytecode" if args.is_empty() => println!("Usage: .{} EXPR", name),
+        "ast" => match calc.parse(args) {
+            Ok(expr) => print!("{}", format_syntax_tree(expr.as_ref())),
+            Err(err) => print_error(&err, args)
+        },
+        "bytecode" => match calc.emit(args) {
+            Ok(method_builder) => print!("{}", disassemble(&method_builder)),
+            Err(err) => print_error(&err, args)
+        },
+        "derive" => match args.split_once(char::is_whitespace) {
+            Some((var, expr)) => match calc.differentiate(expr, var) {
+                Ok(derivative) => println!("{}", derivative),
+                Err(err) => print_error(&err, expr)
+            },
+            None => println!("Usage: .derive VAR EXPR")
+        },
+        _ => println!("Unknown command: .{}. Enter \".help\" for a list of commands.", name)
+    }
+}
+
+fn print_error(err: &CalcError, source: &str) {
+    match err {
+        CalcError::Lex(_) | CalcError::Parse(_) => println!("There were errors in your input.\n{}", err.render(source)),
+        _ => println!("There was an error evaluating your input. {}", err.render(source))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_incomplete_should_wait_for_closing_parentheses() {
+        let helper = ReplHelper {
+            tokenizer: Tokenizer::new()
+        };
+
+        let test_cases: &[(&str, bool)] = &[
+            ("1 + 2", false),
+            ("max(1,", true),
+            ("max(1,\n (2 + 3)", true),
+            ("max(1,\n (2 + 3))", false),
+            ("1 + 2)", false),
+            ("\"(\"", false),
+            (".ast (1 +", false),
+        ];
+
+        for &(input, expected) in test_cases {
+            assert_eq!(helper.is_incomplete(input), expected, "for input {:?}", input);
+        }
+    }
+}